@@ -0,0 +1,65 @@
+//! Running the same programs with the tree walking evaluator and with the
+//! bytecode virtual machine.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use parsing::{
+    dialect::Dialect,
+    eval::{self, Environment},
+    parser::Parser,
+    tokenizer::Tokenizer,
+    vm::{Compiler, Vm},
+    Source,
+};
+
+const PROGRAMS: &[(&str, &str)] = &[
+    (
+        "formula",
+        "let x = 3; let y = 4; (x * x + y * y) * 2 - x / y % 5",
+    ),
+    (
+        "loop",
+        "let i = 0; let sum = 0; while i < 1000 { sum = sum + i * i; i = i + 1; } sum",
+    ),
+    (
+        "array",
+        "let xs = [2; 100]; let i = 0; let sum = 0; \
+         while i < len(xs) { sum = sum + xs[i]; i = i + 1; } sum",
+    ),
+];
+
+fn programs(c: &mut Criterion) {
+    let dialect = Dialect::default();
+    let env = Environment::with_prelude();
+
+    for (name, text) in PROGRAMS {
+        let source = Source::new(name, text);
+        let (mut tokenizer_diagnostics, mut parser_diagnostics) = (vec![], vec![]);
+        let tokenizer = Tokenizer::with_dialect(source, &mut tokenizer_diagnostics, &dialect);
+        let statements = Parser::with_dialect(tokenizer, &mut parser_diagnostics, &dialect)
+            .program()
+            .expect("the benchmarked programs parse");
+        let chunk = Compiler::new()
+            .compile_program(&statements)
+            .expect("the benchmarked programs compile");
+
+        let mut machine = Vm::new();
+        assert_eq!(
+            eval::run(&statements, &env.child()).map(|v| v.to_string()),
+            machine.run(&chunk, &env.child()).map(|v| v.to_string()),
+            "both ways give the same value"
+        );
+
+        let mut group = c.benchmark_group(*name);
+
+        group.bench_function("tree walking", |b| {
+            b.iter(|| eval::run(&statements, &env.child()))
+        });
+
+        group.bench_function("bytecode", |b| b.iter(|| machine.run(&chunk, &env.child())));
+
+        group.finish();
+    }
+}
+
+criterion_group!(benches, programs);
+criterion_main!(benches);
@@ -0,0 +1,170 @@
+//! Typed views of the concrete syntax tree, each a [`SyntaxNode`] of a known kind.
+
+use super::{
+    red::{SyntaxNode, SyntaxToken},
+    NodeKind,
+};
+
+pub trait AstNode<'s>: Sized {
+    fn can_cast(kind: NodeKind) -> bool;
+
+    fn cast(node: SyntaxNode<'s>) -> Option<Self>;
+
+    fn syntax(&self) -> &SyntaxNode<'s>;
+}
+
+macro_rules! ast_nodes {
+    ($($(#[$meta:meta])* $name:ident => $($kind:ident)|+;)+) => {
+        $(
+            $(#[$meta])*
+            #[derive(Debug, Clone)]
+            pub struct $name<'s>(SyntaxNode<'s>);
+
+            impl<'s> AstNode<'s> for $name<'s> {
+                fn can_cast(kind: NodeKind) -> bool {
+                    matches!(kind, $(NodeKind::$kind)|+)
+                }
+
+                fn cast(node: SyntaxNode<'s>) -> Option<Self> {
+                    Self::can_cast(node.kind()).then_some(Self(node))
+                }
+
+                fn syntax(&self) -> &SyntaxNode<'s> {
+                    &self.0
+                }
+            }
+        )+
+    };
+}
+
+ast_nodes! {
+    Program => Program;
+    Block => Block;
+    LetStatement => LetStatement;
+    ExpressionStatement => ExpressionStatement;
+    DocumentedStatement => DocumentedStatement;
+    Pattern => Pattern;
+    Type => Type;
+    /// Any kind of expression.
+    Expr => PrefixExpr | InfixExpr | CustomInfixExpr | PostfixExpr | CallExpr | NameExpr
+        | LiteralExpr | IndexExpr | LambdaExpr | MatchExpr | TupleExpr | ArrayExpr | RepeatExpr
+        | MapExpr | StructExpr | BlockExpr | IfExpr | WhileExpr | AttributedExpr | MacroCallExpr
+        | ParenExpr;
+    InfixExpr => InfixExpr | CustomInfixExpr;
+    CallExpr => CallExpr;
+    NameExpr => NameExpr;
+    LiteralExpr => LiteralExpr;
+}
+
+fn child<'s, N: AstNode<'s>>(node: &SyntaxNode<'s>) -> Option<N> {
+    node.children().find_map(N::cast)
+}
+
+fn children<'s, N: AstNode<'s> + 's>(node: &SyntaxNode<'s>) -> impl Iterator<Item = N> + 's {
+    node.children().filter_map(N::cast)
+}
+
+/// The tokens of the node that aren't trivia or in a child node.
+fn significant_tokens<'s>(node: &SyntaxNode<'s>) -> impl Iterator<Item = SyntaxToken<'s>> {
+    node.child_tokens().filter(|t| !t.is_trivia())
+}
+
+impl<'s> Program<'s> {
+    /// The top level statements.
+    pub fn statements(&self) -> impl Iterator<Item = SyntaxNode<'s>> {
+        self.0.children().filter(|n| n.kind().is_statement())
+    }
+}
+
+impl<'s> Block<'s> {
+    pub fn statements(&self) -> impl Iterator<Item = SyntaxNode<'s>> {
+        self.0.children().filter(|n| n.kind().is_statement())
+    }
+}
+
+impl<'s> LetStatement<'s> {
+    pub fn pattern(&self) -> Option<Pattern<'s>> {
+        child(&self.0)
+    }
+
+    pub fn ty(&self) -> Option<Type<'s>> {
+        child(&self.0)
+    }
+
+    pub fn value(&self) -> Option<Expr<'s>> {
+        child(&self.0)
+    }
+}
+
+impl<'s> ExpressionStatement<'s> {
+    pub fn expr(&self) -> Option<Expr<'s>> {
+        child(&self.0)
+    }
+
+    pub fn has_semicolon(&self) -> bool {
+        significant_tokens(&self.0).any(|t| t.text() == ";")
+    }
+}
+
+impl<'s> DocumentedStatement<'s> {
+    /// The doc comments, markers included.
+    pub fn docs(&self) -> impl Iterator<Item = SyntaxToken<'s>> {
+        self.0
+            .child_tokens()
+            .filter(|t| matches!(t.kind(), crate::tokenizer::TokenType::Comment(c) if c.is_doc()))
+    }
+
+    pub fn statement(&self) -> Option<SyntaxNode<'s>> {
+        self.0.children().find(|n| n.kind().is_statement())
+    }
+}
+
+impl<'s> Expr<'s> {
+    pub fn kind(&self) -> NodeKind {
+        self.0.kind()
+    }
+
+    /// The expressions directly inside this one.
+    pub fn operands(&self) -> impl Iterator<Item = Expr<'s>> {
+        children(&self.0)
+    }
+}
+
+impl<'s> InfixExpr<'s> {
+    pub fn lhs(&self) -> Option<Expr<'s>> {
+        children(&self.0).next()
+    }
+
+    pub fn rhs(&self) -> Option<Expr<'s>> {
+        children(&self.0).nth(1)
+    }
+
+    /// The operator's text, joined if it's made of several tokens.
+    pub fn op(&self) -> String {
+        significant_tokens(&self.0).map(|t| t.text()).collect()
+    }
+}
+
+impl<'s> CallExpr<'s> {
+    pub fn arguments(&self) -> impl Iterator<Item = Expr<'s>> {
+        children(&self.0)
+    }
+}
+
+impl NameExpr<'_> {
+    /// The path, without trivia.
+    pub fn path(&self) -> String {
+        self.0
+            .tokens()
+            .iter()
+            .filter(|t| !t.is_trivia())
+            .map(|t| t.text())
+            .collect()
+    }
+}
+
+impl<'s> LiteralExpr<'s> {
+    pub fn token(&self) -> Option<SyntaxToken<'s>> {
+        significant_tokens(&self.0).next()
+    }
+}
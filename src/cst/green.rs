@@ -0,0 +1,89 @@
+use std::{fmt::Display, rc::Rc};
+
+use crate::tokenizer::TokenType;
+
+use super::NodeKind;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GreenToken<'s> {
+    pub(crate) kind: TokenType,
+    pub(crate) text: &'s str,
+}
+
+impl<'s> GreenToken<'s> {
+    pub fn new(kind: TokenType, text: &'s str) -> Self {
+        Self { kind, text }
+    }
+
+    pub fn kind(&self) -> &TokenType {
+        &self.kind
+    }
+
+    pub fn text(&self) -> &'s str {
+        self.text
+    }
+
+    pub fn width(&self) -> usize {
+        self.text.len()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GreenElement<'s> {
+    Node(Rc<GreenNode<'s>>),
+    Token(Rc<GreenToken<'s>>),
+}
+
+impl GreenElement<'_> {
+    pub fn width(&self) -> usize {
+        match self {
+            Self::Node(n) => n.width(),
+            Self::Token(t) => t.width(),
+        }
+    }
+}
+
+/// A node without a position, so equal subtrees can be shared.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GreenNode<'s> {
+    pub(crate) kind: NodeKind,
+    pub(crate) width: usize,
+    pub(crate) children: Vec<GreenElement<'s>>,
+}
+
+impl<'s> GreenNode<'s> {
+    pub fn new(kind: NodeKind, children: Vec<GreenElement<'s>>) -> Self {
+        Self {
+            kind,
+            width: children.iter().map(GreenElement::width).sum(),
+            children,
+        }
+    }
+
+    pub fn kind(&self) -> NodeKind {
+        self.kind
+    }
+
+    /// The length of the node's text in bytes.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn children(&self) -> &[GreenElement<'s>] {
+        &self.children
+    }
+}
+
+/// Writes the exact source text of the node.
+impl Display for GreenNode<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for c in &self.children {
+            match c {
+                GreenElement::Node(n) => write!(f, "{n}")?,
+                GreenElement::Token(t) => write!(f, "{}", t.text)?,
+            }
+        }
+
+        Ok(())
+    }
+}
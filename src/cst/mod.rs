@@ -0,0 +1,361 @@
+//! A lossless concrete syntax tree, where every byte of the source belongs to
+//! a token, whitespace and comments included.
+//!
+//! The [`green`] tree is immutable and knows only the widths of its nodes,
+//! the [`red`] tree on top of it knows the positions and the parents, and
+//! [`ast`] has typed views of the red nodes.
+
+pub mod ast;
+pub mod green;
+pub mod red;
+
+use std::rc::Rc;
+
+use crate::{
+    dialect::Dialect,
+    parser::{expression::Expression, statement::Statement, Parser, ParserError},
+    tokenizer::{Token, TokenType, Tokenizer},
+    Diagnostic, Source,
+};
+
+use self::{
+    green::{GreenElement, GreenNode, GreenToken},
+    red::SyntaxNode,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NodeKind {
+    Program,
+
+    LetStatement,
+    ExpressionStatement,
+    AttributedStatement,
+    DocumentedStatement,
+    MacroDefinition,
+    FixityDeclaration,
+
+    Block,
+    Attribute,
+    MatchArm,
+    Pattern,
+    Type,
+
+    PrefixExpr,
+    InfixExpr,
+    CustomInfixExpr,
+    PostfixExpr,
+    CallExpr,
+    NameExpr,
+    LiteralExpr,
+    IndexExpr,
+    LambdaExpr,
+    MatchExpr,
+    TupleExpr,
+    ArrayExpr,
+    RepeatExpr,
+    MapExpr,
+    StructExpr,
+    BlockExpr,
+    IfExpr,
+    WhileExpr,
+    AttributedExpr,
+    MacroCallExpr,
+    /// `(expr)`, grouping parentheses aren't a part of the AST.
+    ParenExpr,
+}
+
+impl NodeKind {
+    pub fn of_statement(statement: &Statement<'_>) -> Self {
+        match statement {
+            Statement::Let { .. } => Self::LetStatement,
+            Statement::Expression { .. } => Self::ExpressionStatement,
+            Statement::Attributed { .. } => Self::AttributedStatement,
+            Statement::Documented { .. } => Self::DocumentedStatement,
+            Statement::Macro(_) => Self::MacroDefinition,
+            Statement::Fixity { .. } => Self::FixityDeclaration,
+        }
+    }
+
+    pub fn of_expression(expr: &Expression<'_>) -> Self {
+        match expr {
+            Expression::Prefix { .. } => Self::PrefixExpr,
+            Expression::Infix { .. } => Self::InfixExpr,
+            Expression::CustomInfix { .. } => Self::CustomInfixExpr,
+            Expression::Postfix { .. } => Self::PostfixExpr,
+            Expression::Call { .. } => Self::CallExpr,
+            Expression::Name(_) => Self::NameExpr,
+            Expression::Lit { .. } => Self::LiteralExpr,
+            Expression::Index { .. } => Self::IndexExpr,
+            Expression::Lambda { .. } => Self::LambdaExpr,
+            Expression::Match { .. } => Self::MatchExpr,
+            Expression::Tuple { .. } => Self::TupleExpr,
+            Expression::Array { .. } => Self::ArrayExpr,
+            Expression::Repeat { .. } => Self::RepeatExpr,
+            Expression::Map { .. } => Self::MapExpr,
+            Expression::Struct { .. } => Self::StructExpr,
+            Expression::Block(_) => Self::BlockExpr,
+            Expression::If { .. } => Self::IfExpr,
+            Expression::While { .. } => Self::WhileExpr,
+            Expression::Attributed { .. } => Self::AttributedExpr,
+            Expression::MacroCall { .. } => Self::MacroCallExpr,
+        }
+    }
+
+    pub fn is_statement(&self) -> bool {
+        matches!(
+            self,
+            Self::LetStatement
+                | Self::ExpressionStatement
+                | Self::AttributedStatement
+                | Self::DocumentedStatement
+                | Self::MacroDefinition
+                | Self::FixityDeclaration
+        )
+    }
+
+    pub fn is_expression(&self) -> bool {
+        matches!(
+            self,
+            Self::PrefixExpr
+                | Self::InfixExpr
+                | Self::CustomInfixExpr
+                | Self::PostfixExpr
+                | Self::CallExpr
+                | Self::NameExpr
+                | Self::LiteralExpr
+                | Self::IndexExpr
+                | Self::LambdaExpr
+                | Self::MatchExpr
+                | Self::TupleExpr
+                | Self::ArrayExpr
+                | Self::RepeatExpr
+                | Self::MapExpr
+                | Self::StructExpr
+                | Self::BlockExpr
+                | Self::IfExpr
+                | Self::WhileExpr
+                | Self::AttributedExpr
+                | Self::MacroCallExpr
+                | Self::ParenExpr
+        )
+    }
+}
+
+/// The bytes of a node, from its first token to the end of its last one,
+/// recorded by the parser.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeSpan {
+    pub kind: NodeKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Whitespace, newlines and comments, which the parser doesn't see.
+pub fn is_trivia(ty: &TokenType) -> bool {
+    matches!(
+        ty,
+        TokenType::Whitespace | TokenType::Newline | TokenType::Comment(_)
+    )
+}
+
+/// Builds the green tree of a whole source from all of its tokens and the
+/// spans of its nodes.
+///
+/// Tokens belong to the innermost node containing them, so trivia between
+/// two nodes belongs to their parent. Indents and dedents are left out, the
+/// newlines already own their bytes.
+pub fn build<'s>(text: &'s str, tokens: &[Token<'s>], spans: &[NodeSpan]) -> Rc<GreenNode<'s>> {
+    // outer nodes first, the parser finishes inner ones first
+    let mut spans: Vec<(usize, &NodeSpan)> = spans.iter().enumerate().collect();
+    spans.sort_by(|(ia, a), (ib, b)| {
+        a.start
+            .cmp(&b.start)
+            .then(b.end.cmp(&a.end))
+            .then(ib.cmp(ia))
+    });
+    let mut spans = spans.into_iter().map(|(_, s)| s).peekable();
+
+    let mut stack: Vec<(NodeKind, usize, Vec<GreenElement<'s>>)> =
+        vec![(NodeKind::Program, text.len(), vec![])];
+
+    fn finish<'s>(stack: &mut Vec<(NodeKind, usize, Vec<GreenElement<'s>>)>) {
+        let (kind, _, children) = stack.pop().unwrap();
+        let node = GreenNode::new(kind, children);
+        stack
+            .last_mut()
+            .unwrap()
+            .2
+            .push(GreenElement::Node(Rc::new(node)));
+    }
+
+    for t in tokens {
+        if matches!(t.ty, TokenType::Indent | TokenType::Dedent) {
+            continue;
+        }
+
+        let offset = t.position.absolute_position;
+
+        while stack.len() > 1 && stack.last().unwrap().1 <= offset {
+            finish(&mut stack);
+        }
+
+        while let Some(span) = spans.next_if(|s| s.start <= offset) {
+            stack.push((span.kind, span.end, vec![]));
+
+            if span.end <= offset {
+                finish(&mut stack);
+            }
+        }
+
+        stack
+            .last_mut()
+            .unwrap()
+            .2
+            .push(GreenElement::Token(Rc::new(GreenToken::new(
+                t.ty.clone(),
+                t.text(),
+            ))));
+    }
+
+    for span in spans {
+        stack.push((span.kind, span.end, vec![]));
+    }
+
+    while stack.len() > 1 {
+        finish(&mut stack);
+    }
+
+    let (kind, _, children) = stack.pop().unwrap();
+    Rc::new(GreenNode::new(kind, children))
+}
+
+/// Parses a source into its concrete syntax tree and its AST.
+pub fn parse<'s, D: Extend<Diagnostic<'s>>>(
+    source: Source<'_, 's>,
+    dialect: &Dialect,
+    diagnostics: &mut D,
+) -> Result<(SyntaxNode<'s>, Vec<Statement<'s>>), ParserError> {
+    let mut tokenizer_diagnostics = vec![];
+    let mut tokenizer = Tokenizer::with_dialect(source, &mut tokenizer_diagnostics, dialect);
+    tokenizer.emit_whitespace = true;
+    tokenizer.emit_comments = true;
+
+    let tokens = tokenizer.collect::<Result<Vec<_>, _>>();
+    diagnostics.extend(tokenizer_diagnostics);
+    let tokens = tokens?;
+
+    let mut parser_diagnostics = vec![];
+    let mut parser = Parser::with_dialect(
+        tokens.iter().cloned().map(Ok),
+        &mut parser_diagnostics,
+        dialect,
+    );
+    parser.record_nodes();
+
+    let statements = parser.program();
+    let spans = parser.take_nodes();
+    diagnostics.extend(parser_diagnostics);
+
+    let green = build(source.text, &tokens, &spans);
+    Ok((SyntaxNode::new_root(green), statements?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ast::{AstNode, Program},
+        *,
+    };
+
+    /// The tree of the text, checking that it and each of its nodes cover
+    /// exactly their part of the text.
+    fn round_trip<'s>(text: &'s str, dialect: &Dialect) -> SyntaxNode<'s> {
+        let (root, _) = parse(Source::new("test", text), dialect, &mut vec![])
+            .unwrap_or_else(|e| panic!("{e} in {text:?}"));
+
+        assert_eq!(root.to_string(), text);
+        for node in root.descendants() {
+            assert_eq!(node.to_string(), text[node.text_range()], "{node:?}");
+        }
+
+        root
+    }
+
+    fn statements(root: &SyntaxNode<'_>) -> usize {
+        Program::cast(root.clone()).unwrap().statements().count()
+    }
+
+    fn kinds(root: &SyntaxNode<'_>) -> Vec<NodeKind> {
+        root.descendants().iter().map(SyntaxNode::kind).collect()
+    }
+
+    #[test]
+    fn comments() {
+        let root = round_trip(
+            "// line\nlet a = 1; /* block */ let b = /* inside */ a + 2; // end",
+            &Dialect::standard(),
+        );
+
+        assert_eq!(
+            statements(&root),
+            2,
+            "the comments are trivia, not statements"
+        );
+    }
+
+    #[test]
+    fn doc_comments() {
+        let root = round_trip(
+            "/// The answer.\n/// Twice.\nlet a = 42;\n\n/// Unused.\n",
+            &Dialect::standard(),
+        );
+
+        assert!(kinds(&root).contains(&NodeKind::DocumentedStatement));
+    }
+
+    #[test]
+    fn multibyte_text() {
+        round_trip(
+            "let s = \"日本語\"; // ünïcödé\nlet c = 'é'; /* 🦀 */\nprint(s, c, \"ß\");",
+            &Dialect::standard(),
+        );
+    }
+
+    #[test]
+    fn automatic_semicolons() {
+        let root = round_trip(
+            "# comment\nlet a = 1\nlet b = a +\n    2\nprint(a, b) # trailing\n",
+            &Dialect::script(),
+        );
+
+        assert_eq!(statements(&root), 3);
+    }
+
+    #[test]
+    fn indentation_blocks() {
+        let root = round_trip(
+            "if x > 0:\n    let y = x\n    print(y)\nelse:\n    print(0)\nprint(1)\n",
+            &Dialect::indented(),
+        );
+
+        assert!(kinds(&root).contains(&NodeKind::Block));
+    }
+
+    #[test]
+    fn split_right_shift() {
+        let root = round_trip(
+            "let a: Vec<Vec<i32>> = f::<Map<K, Vec<V>>>(1);",
+            &Dialect::standard(),
+        );
+
+        assert!(kinds(&root).contains(&NodeKind::Type));
+    }
+
+    #[test]
+    fn expressions() {
+        round_trip(
+            "let f = (x) => { let y = x * 2; y };\nmatch f(1) { -1 => [0; 3], n if n > 0 => { \"k\": n }, _ => (1, 2) }\n@memoize let g = x => -x ** 2!;",
+            &Dialect::standard(),
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use std::{fmt::Display, ops::Range, rc::Rc};
+
+use crate::tokenizer::TokenType;
+
+use super::{
+    green::{GreenElement, GreenNode, GreenToken},
+    is_trivia, NodeKind,
+};
+
+/// A node of the green tree with its position and parent, made on demand
+/// while walking down from the root.
+#[derive(Debug, Clone)]
+pub struct SyntaxNode<'s>(Rc<NodeData<'s>>);
+
+#[derive(Debug)]
+struct NodeData<'s> {
+    green: Rc<GreenNode<'s>>,
+    parent: Option<SyntaxNode<'s>>,
+    offset: usize,
+}
+
+impl<'s> SyntaxNode<'s> {
+    pub fn new_root(green: Rc<GreenNode<'s>>) -> Self {
+        Self(Rc::new(NodeData {
+            green,
+            parent: None,
+            offset: 0,
+        }))
+    }
+
+    pub fn kind(&self) -> NodeKind {
+        self.0.green.kind
+    }
+
+    pub fn green(&self) -> &Rc<GreenNode<'s>> {
+        &self.0.green
+    }
+
+    pub fn parent(&self) -> Option<&SyntaxNode<'s>> {
+        self.0.parent.as_ref()
+    }
+
+    /// The bytes of the source the node covers, trivia inside it included.
+    pub fn text_range(&self) -> Range<usize> {
+        self.0.offset..self.0.offset + self.0.green.width
+    }
+
+    pub fn children_with_tokens(&self) -> Vec<SyntaxElement<'s>> {
+        let mut offset = self.0.offset;
+
+        self.0
+            .green
+            .children
+            .iter()
+            .map(|c| {
+                let element = match c {
+                    GreenElement::Node(n) => SyntaxElement::Node(Self(Rc::new(NodeData {
+                        green: n.clone(),
+                        parent: Some(self.clone()),
+                        offset,
+                    }))),
+
+                    GreenElement::Token(t) => SyntaxElement::Token(SyntaxToken {
+                        green: t.clone(),
+                        parent: self.clone(),
+                        offset,
+                    }),
+                };
+
+                offset += c.width();
+                element
+            })
+            .collect()
+    }
+
+    pub fn children(&self) -> impl Iterator<Item = SyntaxNode<'s>> {
+        self.children_with_tokens()
+            .into_iter()
+            .filter_map(SyntaxElement::into_node)
+    }
+
+    /// The tokens that are direct children of the node.
+    pub fn child_tokens(&self) -> impl Iterator<Item = SyntaxToken<'s>> {
+        self.children_with_tokens()
+            .into_iter()
+            .filter_map(SyntaxElement::into_token)
+    }
+
+    /// The node and all the nodes inside it, in source order.
+    pub fn descendants(&self) -> Vec<SyntaxNode<'s>> {
+        let mut out = vec![self.clone()];
+
+        for c in self.children() {
+            out.extend(c.descendants());
+        }
+
+        out
+    }
+
+    /// All the tokens inside the node, in source order.
+    pub fn tokens(&self) -> Vec<SyntaxToken<'s>> {
+        let mut out = vec![];
+
+        for c in self.children_with_tokens() {
+            match c {
+                SyntaxElement::Node(n) => out.extend(n.tokens()),
+                SyntaxElement::Token(t) => out.push(t),
+            }
+        }
+
+        out
+    }
+
+    /// Writes the tree with a node or token on each line, for debugging.
+    pub fn debug_tree(&self) -> String {
+        fn write(node: &SyntaxNode<'_>, depth: usize, out: &mut String) {
+            let range = node.text_range();
+            out.push_str(&format!(
+                "{}{:?}@{}..{}\n",
+                "  ".repeat(depth),
+                node.kind(),
+                range.start,
+                range.end
+            ));
+
+            for c in node.children_with_tokens() {
+                match c {
+                    SyntaxElement::Node(n) => write(&n, depth + 1, out),
+                    SyntaxElement::Token(t) => out.push_str(&format!(
+                        "{}{:?} {:?}\n",
+                        "  ".repeat(depth + 1),
+                        t.kind(),
+                        t.text()
+                    )),
+                }
+            }
+        }
+
+        let mut out = String::new();
+        write(self, 0, &mut out);
+        out
+    }
+}
+
+/// Writes the exact source text of the node.
+impl Display for SyntaxNode<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.green)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SyntaxToken<'s> {
+    green: Rc<GreenToken<'s>>,
+    parent: SyntaxNode<'s>,
+    offset: usize,
+}
+
+impl<'s> SyntaxToken<'s> {
+    pub fn kind(&self) -> &TokenType {
+        &self.green.kind
+    }
+
+    pub fn text(&self) -> &'s str {
+        self.green.text
+    }
+
+    pub fn parent(&self) -> &SyntaxNode<'s> {
+        &self.parent
+    }
+
+    pub fn text_range(&self) -> Range<usize> {
+        self.offset..self.offset + self.green.width()
+    }
+
+    pub fn is_trivia(&self) -> bool {
+        is_trivia(&self.green.kind)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum SyntaxElement<'s> {
+    Node(SyntaxNode<'s>),
+    Token(SyntaxToken<'s>),
+}
+
+impl<'s> SyntaxElement<'s> {
+    pub fn into_node(self) -> Option<SyntaxNode<'s>> {
+        match self {
+            Self::Node(n) => Some(n),
+            Self::Token(_) => None,
+        }
+    }
+
+    pub fn into_token(self) -> Option<SyntaxToken<'s>> {
+        match self {
+            Self::Node(_) => None,
+            Self::Token(t) => Some(t),
+        }
+    }
+}
@@ -0,0 +1,342 @@
+//! Variations of the language's syntax, for embedders that need slightly
+//! different grammars from the same tokenizer and parser.
+
+use std::collections::HashSet;
+
+use crate::{
+    parser::operator::{Associativity, Fixity, OperatorTable},
+    tokenizer::{Keyword, Operator, KEYWORDS},
+    units::UnitRegistry,
+};
+
+/// Which comments the tokenizer recognizes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommentSyntax {
+    /// `// comment`
+    pub double_slash: bool,
+    /// `# comment`, which makes `#[attributes]` unavailable, only `@attributes` remain.
+    pub hash: bool,
+    /// `/* comment */`
+    pub block: bool,
+}
+
+impl Default for CommentSyntax {
+    fn default() -> Self {
+        Self {
+            double_slash: true,
+            hash: false,
+            block: true,
+        }
+    }
+}
+
+/// Constructs the parser accepts, everything is allowed by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Constructs {
+    /// `a = b` and compound assignments like `a += b`
+    pub assignments: bool,
+    /// `macro` definitions and `name!(...)` calls
+    pub macros: bool,
+    /// `#[attributes]` and `@decorators`
+    pub attributes: bool,
+    /// `x => x + 1`
+    pub lambdas: bool,
+    /// `infixl 6 <+>;`
+    pub operator_declarations: bool,
+}
+
+impl Default for Constructs {
+    fn default() -> Self {
+        Self {
+            assignments: true,
+            macros: true,
+            attributes: true,
+            lambdas: true,
+            operator_declarations: true,
+        }
+    }
+}
+
+/// A language profile, consumed by both the [`Tokenizer`](crate::tokenizer::Tokenizer)
+/// and the [`Parser`](crate::parser::Parser).
+#[derive(Debug, Clone)]
+pub struct Dialect {
+    pub name: &'static str,
+
+    pub comments: CommentSyntax,
+    /// Keywords not in the set are tokenized as identifiers.
+    pub keywords: HashSet<Keyword>,
+    /// Tokenize `^` and `^=` as `**` and `**=`, the table needs a `^` entry too.
+    pub caret_is_power: bool,
+    /// Line breaks end statements, like in Go, the tokenizer emits newlines for it.
+    pub automatic_semicolons: bool,
+    /// Blocks can be indented lines instead of curly braces, like in Python.
+    pub indentation_blocks: bool,
+
+    pub operators: OperatorTable,
+    pub constructs: Constructs,
+    /// The units of quantity literals like `500ms`, the others are reported.
+    pub units: UnitRegistry,
+}
+
+impl Dialect {
+    /// The names of the built-in presets, for [`Dialect::preset`].
+    pub const PRESETS: &'static [&'static str] =
+        &["standard", "script", "formula", "math", "indented"];
+
+    /// The whole language, the same as [`Dialect::default`].
+    pub fn standard() -> Self {
+        Self {
+            name: "standard",
+
+            comments: CommentSyntax::default(),
+            keywords: KEYWORDS.iter().copied().collect(),
+            caret_is_power: false,
+            automatic_semicolons: false,
+            indentation_blocks: false,
+
+            operators: OperatorTable::default(),
+            constructs: Constructs::default(),
+            units: UnitRegistry::standard().clone(),
+        }
+    }
+
+    /// Shell-like `#` line comments instead of `//` and `/* */`, and line
+    /// breaks ending statements.
+    pub fn script() -> Self {
+        Self {
+            name: "script",
+
+            comments: CommentSyntax {
+                double_slash: false,
+                hash: true,
+                block: false,
+            },
+            automatic_semicolons: true,
+
+            ..Self::standard()
+        }
+    }
+
+    /// Side effect free expressions: no assignments, macros or operator
+    /// declarations, and their keywords are free to use as names.
+    pub fn formula() -> Self {
+        let mut operators = OperatorTable::default();
+        for op in Operator::ASSIGNMENTS {
+            operators.remove_infix(&op.to_string());
+        }
+
+        let mut keywords: HashSet<Keyword> = KEYWORDS.iter().copied().collect();
+        for k in [
+            Keyword::Macro,
+            Keyword::Infix,
+            Keyword::Infixl,
+            Keyword::Infixr,
+        ] {
+            keywords.remove(&k);
+        }
+
+        Self {
+            name: "formula",
+
+            keywords,
+            operators,
+            constructs: Constructs {
+                assignments: false,
+                macros: false,
+                operator_declarations: false,
+                ..Constructs::default()
+            },
+
+            ..Self::standard()
+        }
+    }
+
+    /// `^` is exponentiation instead of a bitwise xor, as in math notation.
+    pub fn math() -> Self {
+        let mut operators = OperatorTable::default();
+        operators.add_infix(
+            "^",
+            operators
+                .infix(&Operator::DoubleStar.to_string())
+                .unwrap_or(Fixity::new(80, Associativity::Right)),
+        );
+        operators.add_infix(
+            "^=",
+            operators
+                .infix(&Operator::DoubleStarEquals.to_string())
+                .unwrap_or(Fixity::new(0, Associativity::Right)),
+        );
+
+        Self {
+            name: "math",
+
+            caret_is_power: true,
+            operators,
+
+            ..Self::standard()
+        }
+    }
+
+    /// Python-like, `#` comments and blocks made of indented lines.
+    pub fn indented() -> Self {
+        Self {
+            name: "indented",
+
+            indentation_blocks: true,
+
+            ..Self::script()
+        }
+    }
+
+    /// A built-in preset by its name.
+    pub fn preset(name: &str) -> Option<Self> {
+        Some(match name {
+            "standard" => Self::standard(),
+            "script" => Self::script(),
+            "formula" => Self::formula(),
+            "math" => Self::math(),
+            "indented" => Self::indented(),
+
+            _ => return None,
+        })
+    }
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parser::{Parser, ParserError},
+        tokenizer::Tokenizer,
+        Source,
+    };
+
+    /// The statements parsed from the text, one per line.
+    fn parse(text: &str, dialect: &Dialect) -> Result<String, ParserError> {
+        let source = Source::new("test", text);
+        let (mut tokenizer_diagnostics, mut parser_diagnostics) = (vec![], vec![]);
+
+        let tokenizer = Tokenizer::with_dialect(source, &mut tokenizer_diagnostics, dialect);
+        let statements =
+            Parser::with_dialect(tokenizer, &mut parser_diagnostics, dialect).program()?;
+
+        Ok(statements
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+
+    #[test]
+    fn presets_by_name() {
+        for name in Dialect::PRESETS {
+            assert_eq!(Dialect::preset(name).unwrap().name, *name);
+        }
+
+        assert!(Dialect::preset("unknown").is_none());
+    }
+
+    #[test]
+    fn standard() {
+        let dialect = Dialect::standard();
+
+        assert_eq!(
+            parse(
+                "// comment\nlet a = 2 ^ 3 ^ 2; /* block */ a += 1;",
+                &dialect
+            )
+            .unwrap(),
+            "let a = ((2 ^ 3) ^ 2);\n(a += 1);"
+        );
+        assert_eq!(
+            parse("#[inline] let f = (x) => x;", &dialect).unwrap(),
+            "#[inline] let f = ((x) => x);"
+        );
+        assert!(parse("let a = 1\nlet b = 2", &dialect).is_err());
+    }
+
+    #[test]
+    fn script() {
+        let dialect = Dialect::script();
+
+        assert_eq!(
+            parse(
+                "# comment\nlet a = 1\nlet b = a + 1 # trailing\nb",
+                &dialect
+            )
+            .unwrap(),
+            "let a = 1;\nlet b = (a + 1);\nb"
+        );
+        // a comment that ends the input without a line break
+        assert_eq!(parse("a # comment", &dialect).unwrap(), "a");
+
+        assert!(parse("let a = 1 // not a comment", &dialect).is_err());
+        // `#` starts a comment, so there are no `#[attributes]`
+        assert_eq!(parse("#[inline] f()", &dialect).unwrap(), "");
+    }
+
+    #[test]
+    fn formula() {
+        let dialect = Dialect::formula();
+
+        assert_eq!(
+            parse("let macro = 1; let infixl = macro + 1;", &dialect).unwrap(),
+            "let macro = 1;\nlet infixl = (macro + 1);"
+        );
+
+        assert!(matches!(
+            parse("a = 1;", &dialect),
+            Err(ParserError::Disallowed(_))
+        ));
+        assert!(parse("a += 1;", &dialect).is_err());
+    }
+
+    #[test]
+    fn math() {
+        let dialect = Dialect::math();
+
+        assert_eq!(
+            parse("let a = 2 ^ 3 ^ 2; a ^= 2;", &dialect).unwrap(),
+            "let a = (2 ** (3 ** 2));\n(a **= 2);"
+        );
+        assert_eq!(parse("-a ^ 2;", &dialect).unwrap(), "(-(a ** 2));");
+    }
+
+    #[test]
+    fn indented() {
+        let dialect = Dialect::indented();
+
+        assert_eq!(
+            parse(
+                "# comment\nif x > 0:\n    let y = x\n    print(y)\nelse:\n    print(0)\n",
+                &dialect
+            )
+            .unwrap(),
+            "if (x > 0) { let y = x; (print(y)) } else { (print(0)) }"
+        );
+        assert_eq!(
+            parse("if x:\n    a\nb\n", &dialect).unwrap(),
+            "if x { a }\nb"
+        );
+        assert_eq!(
+            parse("if x:\n    a\n    # c\n\n    b\n", &dialect).unwrap(),
+            "if x { a b }"
+        );
+
+        let block_comments = Dialect {
+            comments: Dialect::standard().comments,
+            ..Dialect::indented()
+        };
+        assert_eq!(
+            parse("if x:\n    /* c */ a\n    b\n", &block_comments).unwrap(),
+            "if x { a b }"
+        );
+    }
+}
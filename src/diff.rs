@@ -0,0 +1,468 @@
+//! Symbolic differentiation of expressions.
+//!
+//! [`diff`] applies the sum, product, quotient, power and chain rules, and
+//! simplifies the derivative while building it so `x ** 2` gives `2 * x`
+//! rather than `2 * x ** 1 * 1`. The new nodes have empty spans, the parts
+//! copied from the expression keep theirs.
+
+use crate::{
+    eval::{self, Value},
+    fold,
+    parser::expression::{Expression, Path},
+    tokenizer::{Literal, Operator, Span},
+};
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum DiffError {
+    #[error("{construct} can't be differentiated, at {span}")]
+    Unsupported { construct: &'static str, span: Span },
+
+    #[error("The derivative of {name} isn't known, at {span}")]
+    UnknownFunction { name: String, span: Span },
+}
+
+/// The functions of one argument with a known derivative.
+pub const FUNCTIONS: &[&str] = &["sin", "cos", "exp", "ln"];
+
+/// The derivative of the expression with respect to the variable `var`,
+/// the other variables are constants.
+///
+/// The constant divisions are folded to reals, since `1 / 2` is zero with
+/// integers, so the derivative is meant to be evaluated with real values.
+/// Only the number constants are folded, quantities are left as written.
+pub fn diff<'s>(expr: &Expression<'s>, var: &str) -> Result<Expression<'s>, DiffError> {
+    let span = expr.span();
+    let unsupported = |construct| Err(DiffError::Unsupported { construct, span });
+
+    Ok(match expr {
+        Expression::Lit {
+            value: Literal::Number(..) | Literal::Quantity { .. },
+            ..
+        } => integer(0),
+        Expression::Lit { .. } => return unsupported("a string or char literal"),
+
+        Expression::Name(path) => integer((path.as_identifier() == Some(var)) as i64),
+
+        Expression::Prefix {
+            op: Operator::Minus,
+            right,
+            ..
+        } => neg(diff(right, var)?),
+        Expression::Prefix {
+            op: Operator::Plus,
+            right,
+            ..
+        } => diff(right, var)?,
+
+        Expression::Infix {
+            left, op, right, ..
+        } => {
+            let (u, v) = (left.as_ref(), right.as_ref());
+
+            match op {
+                Operator::Plus => add(diff(u, var)?, diff(v, var)?),
+                Operator::Minus => sub(diff(u, var)?, diff(v, var)?),
+
+                // (uv)' = u'v + uv'
+                Operator::Star => add(mul(diff(u, var)?, v.clone()), mul(u.clone(), diff(v, var)?)),
+
+                // (u / v)' = (u'v - uv') / v²
+                Operator::Slash => div(
+                    sub(mul(diff(u, var)?, v.clone()), mul(u.clone(), diff(v, var)?)),
+                    pow(v.clone(), integer(2)),
+                ),
+
+                Operator::DoubleStar => power(u, v, var)?,
+
+                _ => return unsupported("this operator"),
+            }
+        }
+
+        Expression::Call {
+            function,
+            arguments,
+            ..
+        } => match (function.as_identifier(), arguments.as_slice()) {
+            // the chain rule, f(u)' = f'(u) u'
+            (Some(name), [u]) if FUNCTIONS.contains(&name) => {
+                let du = diff(u, var)?;
+
+                match name {
+                    "sin" => mul(call("cos", u.clone()), du),
+                    "cos" => neg(mul(call("sin", u.clone()), du)),
+                    "exp" => mul(call("exp", u.clone()), du),
+                    _ => div(du, u.clone()),
+                }
+            }
+
+            _ if arguments.iter().all(|a| !contains(a, var)) => integer(0),
+            _ => {
+                return Err(DiffError::UnknownFunction {
+                    name: function.to_string(),
+                    span,
+                })
+            }
+        },
+
+        Expression::Attributed { expr, .. } => diff(expr, var)?,
+
+        _ => return unsupported("this expression"),
+    })
+}
+
+/// `u ** v`, with the rules for a constant exponent or base when they apply.
+fn power<'s>(
+    u: &Expression<'s>,
+    v: &Expression<'s>,
+    var: &str,
+) -> Result<Expression<'s>, DiffError> {
+    let (u_varies, v_varies) = (contains(u, var), contains(v, var));
+
+    Ok(match (u_varies, v_varies) {
+        (false, false) => integer(0),
+
+        // (uⁿ)' = n uⁿ⁻¹ u'
+        (true, false) => mul(
+            mul(v.clone(), pow(u.clone(), sub(v.clone(), integer(1)))),
+            diff(u, var)?,
+        ),
+
+        // (aᵛ)' = aᵛ ln(a) v'
+        (false, true) => mul(
+            mul(pow(u.clone(), v.clone()), call("ln", u.clone())),
+            diff(v, var)?,
+        ),
+
+        // (uᵛ)' = uᵛ (v' ln(u) + v u' / u)
+        (true, true) => mul(
+            pow(u.clone(), v.clone()),
+            add(
+                mul(diff(v, var)?, call("ln", u.clone())),
+                div(mul(v.clone(), diff(u, var)?), u.clone()),
+            ),
+        ),
+    })
+}
+
+/// Whether the variable appears in the expression, conservatively `true`
+/// for the expressions that can't be differentiated.
+fn contains(expr: &Expression<'_>, var: &str) -> bool {
+    match expr {
+        Expression::Lit { .. } => false,
+        Expression::Name(path) => path.as_identifier() == Some(var),
+
+        Expression::Prefix { right, .. } => contains(right, var),
+        Expression::Infix { left, right, .. } => contains(left, var) || contains(right, var),
+        Expression::Call { arguments, .. } => arguments.iter().any(|a| contains(a, var)),
+        Expression::Attributed { expr, .. } => contains(expr, var),
+
+        _ => true,
+    }
+}
+
+fn integer<'s>(n: i64) -> Expression<'s> {
+    fold::constant_expression(Value::Integer(n), Span::default())
+        .expect("an integer always has a literal")
+}
+
+fn is(expr: &Expression<'_>, n: i64) -> bool {
+    fold::number(expr) == Some(Value::Integer(n))
+}
+
+fn negated<'e, 's>(expr: &'e Expression<'s>) -> Option<&'e Expression<'s>> {
+    match expr {
+        Expression::Prefix {
+            op: Operator::Minus,
+            right,
+            ..
+        } => Some(right),
+
+        _ => None,
+    }
+}
+
+fn infix<'s>(left: Expression<'s>, op: Operator, right: Expression<'s>) -> Expression<'s> {
+    Expression::Infix {
+        left: Box::new(left),
+        op,
+        right: Box::new(right),
+        span: Span::default(),
+    }
+}
+
+fn call<'s>(name: &'s str, argument: Expression<'s>) -> Expression<'s> {
+    Expression::Call {
+        function: Path::single(name),
+        arguments: vec![argument],
+        span: Span::default(),
+    }
+}
+
+/// The constant the operator gives with constant operands.
+fn apply<'s>(
+    left: &Expression<'s>,
+    op: Operator,
+    right: &Expression<'s>,
+) -> Option<Expression<'s>> {
+    let (mut a, b) = (fold::number(left)?, fold::number(right)?);
+
+    // `1 / 2` and `2 ** -1` are reals, not truncated or failing
+    match (op, &a, &b) {
+        (Operator::Slash, Value::Integer(x), Value::Integer(y))
+            if x.checked_rem(*y).is_some_and(|r| r != 0) =>
+        {
+            a = Value::Real(*x as f64)
+        }
+        (Operator::DoubleStar, Value::Integer(x), Value::Integer(y)) if *y < 0 => {
+            a = Value::Real(*x as f64)
+        }
+
+        _ => (),
+    }
+
+    let value = eval::infix(a, op, b).ok()?;
+    fold::constant_expression(value, Span::default())
+}
+
+fn neg(expr: Expression<'_>) -> Expression<'_> {
+    if let Some(value) = fold::number(&expr) {
+        if let Some(folded) = eval::prefix(Operator::Minus, value)
+            .ok()
+            .and_then(|v| fold::constant_expression(v, Span::default()))
+        {
+            return folded;
+        }
+    }
+
+    match expr {
+        Expression::Prefix {
+            op: Operator::Minus,
+            right,
+            ..
+        } => *right,
+
+        e => Expression::Prefix {
+            op: Operator::Minus,
+            right: Box::new(e),
+            span: Span::default(),
+        },
+    }
+}
+
+fn add<'s>(a: Expression<'s>, b: Expression<'s>) -> Expression<'s> {
+    if let Some(folded) = apply(&a, Operator::Plus, &b) {
+        return folded;
+    }
+
+    if is(&a, 0) {
+        return b;
+    }
+
+    if is(&b, 0) {
+        return a;
+    }
+
+    match negated(&b) {
+        Some(b) => sub(a, b.clone()),
+        None => infix(a, Operator::Plus, b),
+    }
+}
+
+fn sub<'s>(a: Expression<'s>, b: Expression<'s>) -> Expression<'s> {
+    if let Some(folded) = apply(&a, Operator::Minus, &b) {
+        return folded;
+    }
+
+    if is(&b, 0) {
+        return a;
+    }
+
+    if is(&a, 0) {
+        return neg(b);
+    }
+
+    match negated(&b) {
+        Some(b) => add(a, b.clone()),
+        None => infix(a, Operator::Minus, b),
+    }
+}
+
+fn mul<'s>(a: Expression<'s>, b: Expression<'s>) -> Expression<'s> {
+    if let Some(folded) = apply(&a, Operator::Star, &b) {
+        return folded;
+    }
+
+    if is(&a, 0) || is(&b, 0) {
+        return integer(0);
+    }
+
+    if is(&a, 1) {
+        return b;
+    }
+
+    if is(&b, 1) {
+        return a;
+    }
+
+    // the signs and the constants go to the front, `x * -2` is `-(2 * x)`
+    if let Some(a) = negated(&a) {
+        return neg(mul(a.clone(), b));
+    }
+
+    if let Some(b) = negated(&b) {
+        return neg(mul(a, b.clone()));
+    }
+
+    if fold::number(&b).is_some() && fold::number(&a).is_none() {
+        return mul(b, a);
+    }
+
+    // `2 * (3 * x)` is `6 * x`
+    if let Expression::Infix {
+        left,
+        op: Operator::Star,
+        right,
+        ..
+    } = &b
+    {
+        if let Some(folded) = apply(&a, Operator::Star, left) {
+            return mul(folded, right.as_ref().clone());
+        }
+    }
+
+    infix(a, Operator::Star, b)
+}
+
+fn div<'s>(a: Expression<'s>, b: Expression<'s>) -> Expression<'s> {
+    if let Some(folded) = apply(&a, Operator::Slash, &b) {
+        return folded;
+    }
+
+    if is(&a, 0) {
+        return integer(0);
+    }
+
+    if is(&b, 1) {
+        return a;
+    }
+
+    if let Some(a) = negated(&a) {
+        return neg(div(a.clone(), b));
+    }
+
+    infix(a, Operator::Slash, b)
+}
+
+fn pow<'s>(a: Expression<'s>, b: Expression<'s>) -> Expression<'s> {
+    if let Some(folded) = apply(&a, Operator::DoubleStar, &b) {
+        return folded;
+    }
+
+    if is(&b, 0) || is(&a, 1) {
+        return integer(1);
+    }
+
+    if is(&b, 1) {
+        return a;
+    }
+
+    infix(a, Operator::DoubleStar, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        eval::Environment,
+        parser::{statement::Statement, Parser},
+        tokenizer::Tokenizer,
+        Source,
+    };
+
+    fn parse(text: &str) -> Expression<'_> {
+        let source = Source::new("test", text);
+        let (mut tokenizer_diagnostics, mut parser_diagnostics) = (vec![], vec![]);
+
+        let tokenizer = Tokenizer::new(source, &mut tokenizer_diagnostics);
+        let mut statements = Parser::new(tokenizer, &mut parser_diagnostics, Default::default())
+            .program()
+            .unwrap();
+
+        match statements.pop() {
+            Some(Statement::Expression { expr, .. }) => expr,
+            s => panic!("`{text}` isn't an expression: {s:?}"),
+        }
+    }
+
+    /// The value of the expression with `x` and the constant `c = 1.5`.
+    fn at(expr: &Expression<'_>, x: f64) -> f64 {
+        let env = Environment::with_prelude();
+        env.define("x", Value::Real(x));
+        env.define("c", Value::Real(1.5));
+
+        match eval::eval(expr, &env) {
+            Ok(Value::Real(r)) => r,
+            Ok(Value::Integer(i)) => i as f64,
+            result => panic!("`{expr}` at {x} is {result:?}"),
+        }
+    }
+
+    /// Compares the derivative with the central difference
+    /// `(f(x + h) - f(x - h)) / 2h` at a few points.
+    fn check(text: &str) {
+        const H: f64 = 1e-6;
+
+        let expr = parse(text);
+        let derivative = diff(&expr, "x").unwrap();
+
+        for x in [0.3, 1.1, 2.7] {
+            let expected = (at(&expr, x + H) - at(&expr, x - H)) / (2. * H);
+            let found = at(&derivative, x);
+
+            assert!(
+                (found - expected).abs() <= 1e-5 * expected.abs().max(1.),
+                "d/dx {text} = {derivative} is {found} at {x}, expected {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn sums() {
+        check("x + 3");
+        check("x + x * 2 - c");
+        check("-x - c * x");
+    }
+
+    #[test]
+    fn products() {
+        check("x * x");
+        check("c * x * (x + 1)");
+    }
+
+    #[test]
+    fn quotients() {
+        check("1 / x");
+        check("(x + 1) / (x * x + c)");
+        check("x / c");
+    }
+
+    #[test]
+    fn powers() {
+        check("x ** 3");
+        check("x ** c");
+        check("c ** x");
+        check("x ** x");
+        check("(x * x + 1) ** 2");
+    }
+
+    #[test]
+    fn chain_rule() {
+        check("sin(x)");
+        check("cos(x * x)");
+        check("exp(c * x)");
+        check("ln(x)");
+        check("sin(cos(x))");
+        check("exp(sin(x) ** 2)");
+        check("ln(x * x + 1) / x");
+    }
+}
@@ -0,0 +1,1199 @@
+//! A tree walking evaluator for expressions and statements.
+//!
+//! Names are looked up in an [`Environment`] of variables and native
+//! functions. Errors carry the [`Span`] of the expression that failed.
+
+pub mod value;
+
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use crate::{
+    parser::{
+        expression::{Expression, Path},
+        pattern::{Pattern, PatternLiteral},
+        statement::{Block, Statement},
+    },
+    tokenizer::{Literal, NumberSuffix, Operator, Span},
+    units::UnitRegistry,
+};
+
+pub use self::value::{Closure, NativeFunction, Value};
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum RuntimeErrorKind {
+    #[error("Division by zero")]
+    DivisionByZero,
+
+    #[error("Unknown name {0}")]
+    UnknownName(String),
+
+    #[error("Expected {expected} arguments, found {found}")]
+    ArityMismatch { expected: usize, found: usize },
+
+    #[error("Expected {expected}, found a value of type {found}")]
+    TypeMismatch {
+        expected: &'static str,
+        found: &'static str,
+    },
+
+    #[error("Index {index} is out of bounds for length {len}")]
+    IndexOutOfBounds { index: i64, len: usize },
+
+    #[error("Key {0} is not in the map")]
+    MissingKey(String),
+
+    #[error("Integer overflow")]
+    Overflow,
+
+    #[error("The count {0} of a repeat array is negative")]
+    NegativeCount(i64),
+
+    #[error("An array of {len} items is longer than the limit of {max}")]
+    TooLong { len: i64, max: usize },
+
+    #[error("A value of type {0} is not callable")]
+    NotCallable(&'static str),
+
+    #[error("No pattern matched the value {0}")]
+    NoMatch(String),
+
+    #[error("{0} can't be evaluated")]
+    Unsupported(&'static str),
+
+    #[error("Unknown unit {0}")]
+    UnknownUnit(String),
+
+    #[error("Incompatible units, a quantity of {left} with a value of {right}")]
+    IncompatibleUnits {
+        left: &'static str,
+        right: &'static str,
+    },
+}
+
+impl RuntimeErrorKind {
+    pub fn type_mismatch(expected: &'static str, found: &Value<'_>) -> Self {
+        Self::TypeMismatch {
+            expected,
+            found: found.type_name(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[error("{kind} at {span}")]
+pub struct RuntimeError {
+    pub(crate) kind: RuntimeErrorKind,
+    pub(crate) span: Span,
+}
+
+impl RuntimeError {
+    pub fn new(kind: RuntimeErrorKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+
+    pub fn kind(&self) -> &RuntimeErrorKind {
+        &self.kind
+    }
+
+    /// The span of the expression that failed.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+}
+
+#[derive(Debug, Default)]
+struct Scope<'s> {
+    values: RefCell<HashMap<String, Value<'s>>>,
+    parent: Option<Rc<Scope<'s>>>,
+}
+
+/// Nested scopes of variables, cheap to clone since the clones share them.
+///
+/// Names are whole paths like `math::sqrt`, as written in the source.
+#[derive(Debug, Clone)]
+pub struct Environment<'s> {
+    scope: Rc<Scope<'s>>,
+    /// The units of the quantity literals, the same in every scope.
+    units: Rc<UnitRegistry>,
+}
+
+impl Default for Environment<'_> {
+    fn default() -> Self {
+        Self::with_units(UnitRegistry::standard())
+    }
+}
+
+impl<'s> Environment<'s> {
+    /// An empty environment with the standard units.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An empty environment whose quantity literals are in the given units.
+    pub fn with_units(units: &UnitRegistry) -> Self {
+        Self {
+            scope: Rc::default(),
+            units: Rc::new(units.clone()),
+        }
+    }
+
+    /// An environment with `true`, `false` and the builtin functions.
+    pub fn with_prelude() -> Self {
+        let env = Self::new();
+        env.define_prelude();
+        env
+    }
+
+    /// Defines `true`, `false` and the builtin functions.
+    pub fn define_prelude(&self) {
+        self.define("true", Value::Bool(true));
+        self.define("false", Value::Bool(false));
+
+        self.define_native("print", None, |args| {
+            let mut line = String::new();
+            for (i, a) in args.iter().enumerate() {
+                if i > 0 {
+                    line.push(' ');
+                }
+
+                match a {
+                    Value::String(s) => line.push_str(s),
+                    a => line.push_str(&a.to_string()),
+                }
+            }
+
+            println!("{line}");
+            Ok(Value::unit())
+        });
+
+        self.define_native("len", Some(1), |args| match &args[0] {
+            Value::String(s) => Ok(Value::Integer(s.chars().count() as i64)),
+            Value::Tuple(items) | Value::Array(items) => Ok(Value::Integer(items.len() as i64)),
+            Value::Map(entries) => Ok(Value::Integer(entries.len() as i64)),
+
+            v => Err(RuntimeErrorKind::type_mismatch(
+                "a string or a collection",
+                v,
+            )),
+        });
+
+        self.define_native("abs", Some(1), |args| match &args[0] {
+            Value::Integer(i) => i
+                .checked_abs()
+                .map(Value::Integer)
+                .ok_or(RuntimeErrorKind::Overflow),
+            Value::Real(r) => Ok(Value::Real(r.abs())),
+
+            v => Err(RuntimeErrorKind::type_mismatch("a number", v)),
+        });
+
+        self.define_native("min", Some(2), |args| {
+            min_max(&args[0], &args[1], std::cmp::Ordering::Less)
+        });
+        self.define_native("max", Some(2), |args| {
+            min_max(&args[0], &args[1], std::cmp::Ordering::Greater)
+        });
+
+        for (name, function) in [
+            ("sqrt", f64::sqrt as fn(f64) -> f64),
+            ("sin", f64::sin),
+            ("cos", f64::cos),
+            ("exp", f64::exp),
+            ("ln", f64::ln),
+        ] {
+            self.define_native(name, Some(1), move |args| match args[0].as_real() {
+                Some(x) => Ok(Value::Real(function(x))),
+                None => Err(RuntimeErrorKind::type_mismatch("a number", &args[0])),
+            });
+        }
+
+        self.define_native("string", Some(1), |args| match &args[0] {
+            Value::String(s) => Ok(Value::String(s.clone())),
+            Value::Char(c) => Ok(Value::String(c.to_string())),
+            v => Ok(Value::String(v.to_string())),
+        });
+    }
+
+    /// A new scope inside this one, its definitions are dropped with it.
+    pub fn child(&self) -> Self {
+        Self {
+            scope: Rc::new(Scope {
+                values: RefCell::default(),
+                parent: Some(self.scope.clone()),
+            }),
+            units: self.units.clone(),
+        }
+    }
+
+    pub fn units(&self) -> &UnitRegistry {
+        &self.units
+    }
+
+    /// Defines a variable in the innermost scope, shadowing any other with the name.
+    pub fn define(&self, name: impl Into<String>, value: Value<'s>) {
+        self.scope.values.borrow_mut().insert(name.into(), value);
+    }
+
+    /// Defines a function implemented in Rust, `arity` is `None` for
+    /// functions taking any number of arguments.
+    pub fn define_native(
+        &self,
+        name: &str,
+        arity: Option<usize>,
+        function: impl Fn(&[Value<'s>]) -> Result<Value<'s>, RuntimeErrorKind> + 's,
+    ) {
+        self.define(
+            name,
+            Value::Native(NativeFunction {
+                name: name.to_string(),
+                arity,
+                function: Rc::new(function),
+            }),
+        );
+    }
+
+    pub fn get(&self, name: &str) -> Option<Value<'s>> {
+        let mut scope = Some(&self.scope);
+
+        while let Some(s) = scope {
+            if let Some(v) = s.values.borrow().get(name) {
+                return Some(v.clone());
+            }
+
+            scope = s.parent.as_ref();
+        }
+
+        None
+    }
+
+    /// The names of every variable in scope.
+    pub fn names(&self) -> Vec<String> {
+        let mut names = vec![];
+        let mut scope = Some(&self.scope);
+
+        while let Some(s) = scope {
+            names.extend(s.values.borrow().keys().cloned());
+            scope = s.parent.as_ref();
+        }
+
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Changes the variable in the innermost scope defining it, returns
+    /// `false` if there is none.
+    pub fn assign(&self, name: &str, value: Value<'s>) -> bool {
+        let mut scope = Some(&self.scope);
+
+        while let Some(s) = scope {
+            if let Some(v) = s.values.borrow_mut().get_mut(name) {
+                *v = value;
+                return true;
+            }
+
+            scope = s.parent.as_ref();
+        }
+
+        false
+    }
+}
+
+fn min_max<'s>(
+    a: &Value<'s>,
+    b: &Value<'s>,
+    keep: std::cmp::Ordering,
+) -> Result<Value<'s>, RuntimeErrorKind> {
+    match a.compare(b) {
+        Some(o) if o == keep => Ok(a.clone()),
+        Some(_) => Ok(b.clone()),
+        None => Err(RuntimeErrorKind::type_mismatch("comparable values", b)),
+    }
+}
+
+/// Evaluates the statements in order, to the value of the last one.
+pub fn run<'s>(
+    statements: &[Statement<'s>],
+    env: &Environment<'s>,
+) -> Result<Value<'s>, RuntimeError> {
+    let mut value = Value::unit();
+
+    for s in statements {
+        value = statement(s, env)?;
+    }
+
+    Ok(value)
+}
+
+/// Evaluates a statement to the value of its expression, or to the unit
+/// value when it ends with a semicolon or isn't an expression.
+pub fn statement<'s>(
+    statement: &Statement<'s>,
+    env: &Environment<'s>,
+) -> Result<Value<'s>, RuntimeError> {
+    match statement.inner() {
+        Statement::Let { pattern, value, .. } => {
+            let Some(value) = value else {
+                // there's nothing to check the later assignments against
+                bind(pattern, Value::unit(), env, Span::default())?;
+                return Ok(Value::unit());
+            };
+
+            let span = value.span();
+            let value = eval(value, env)?;
+            bind(pattern, value, env, span)?;
+
+            Ok(Value::unit())
+        }
+
+        Statement::Expression { expr, semicolon } => {
+            let value = eval(expr, env)?;
+            Ok(if *semicolon { Value::unit() } else { value })
+        }
+
+        // expanded or applied before evaluation
+        Statement::Macro(_) | Statement::Fixity { .. } => Ok(Value::unit()),
+
+        Statement::Attributed { .. } | Statement::Documented { .. } => unreachable!(),
+    }
+}
+
+/// Binds the variables of a pattern that has to match.
+fn bind<'s>(
+    pattern: &Pattern<'s>,
+    value: Value<'s>,
+    env: &Environment<'s>,
+    span: Span,
+) -> Result<(), RuntimeError> {
+    let mut bindings = vec![];
+    if !matches(pattern, &value, env, &mut bindings).map_err(|k| RuntimeError::new(k, span))? {
+        return Err(RuntimeError::new(
+            RuntimeErrorKind::NoMatch(value.to_string()),
+            span,
+        ));
+    }
+
+    for (name, value) in bindings {
+        env.define(name, value);
+    }
+
+    Ok(())
+}
+
+fn block<'s>(block: &Block<'s>, env: &Environment<'s>) -> Result<Value<'s>, RuntimeError> {
+    run(block.statements(), &env.child())
+}
+
+pub fn eval<'s>(expr: &Expression<'s>, env: &Environment<'s>) -> Result<Value<'s>, RuntimeError> {
+    let error = |kind| RuntimeError::new(kind, expr.span());
+
+    match expr {
+        Expression::Lit { value, .. } => literal(value, env.units()).map_err(error),
+
+        Expression::Name(path) => lookup(path, env),
+
+        Expression::Prefix { op, right, .. } => {
+            if let (Operator::Minus, Expression::Lit { value, .. }) = (op, right.as_ref()) {
+                return negated_literal(value, env.units()).map_err(error);
+            }
+
+            let right = eval(right, env)?;
+            prefix(*op, right).map_err(error)
+        }
+
+        Expression::Postfix { left, op, .. } => {
+            let left = eval(left, env)?;
+            postfix(*op, left).map_err(error)
+        }
+
+        Expression::Infix {
+            left, op, right, ..
+        } => match op {
+            Operator::DoubleAnd | Operator::DoubleOr => {
+                let short_circuit = *op == Operator::DoubleOr;
+
+                match eval(left, env)? {
+                    Value::Bool(b) if b == short_circuit => Ok(Value::Bool(b)),
+                    Value::Bool(_) => match eval(right, env)? {
+                        Value::Bool(b) => Ok(Value::Bool(b)),
+                        v => Err(error(RuntimeErrorKind::type_mismatch("a bool", &v))),
+                    },
+
+                    v => Err(error(RuntimeErrorKind::type_mismatch("a bool", &v))),
+                }
+            }
+
+            Operator::Pipeline => {
+                let value = eval(left, env)?;
+
+                // `x |> f(y)` is `f(x, y)`
+                if let Expression::Call {
+                    function,
+                    arguments,
+                    ..
+                } = right.as_ref()
+                {
+                    let mut args = vec![value];
+                    for a in arguments {
+                        args.push(eval(a, env)?);
+                    }
+
+                    let function = lookup(function, env)?;
+                    return call(&function, args, expr.span());
+                }
+
+                let function = eval(right, env)?;
+                call(&function, vec![value], expr.span())
+            }
+
+            op if op.is_assignment() => assign(left, *op, right, env, expr.span()),
+
+            op => {
+                let left = eval(left, env)?;
+                let right = eval(right, env)?;
+                infix(left, *op, right).map_err(error)
+            }
+        },
+
+        Expression::CustomInfix {
+            left, op, right, ..
+        } => {
+            let Some(function) = env.get(op) else {
+                return Err(error(RuntimeErrorKind::UnknownName(op.clone())));
+            };
+
+            let args = vec![eval(left, env)?, eval(right, env)?];
+            call(&function, args, expr.span())
+        }
+
+        Expression::Call {
+            function,
+            arguments,
+            ..
+        } => {
+            let function = lookup(function, env)?;
+
+            let mut args = vec![];
+            for a in arguments {
+                args.push(eval(a, env)?);
+            }
+
+            call(&function, args, expr.span())
+        }
+
+        Expression::Index { expr, with, .. } => {
+            let value = eval(expr, env)?;
+            let index = eval(with, env)?;
+            self::index(value, index).map_err(error)
+        }
+
+        Expression::Lambda {
+            parameters, body, ..
+        } => Ok(Value::Closure(Closure {
+            parameters: parameters.iter().map(|p| p.name()).collect(),
+            body: Rc::new(body.as_ref().clone()),
+            environment: env.clone(),
+        })),
+
+        Expression::Match {
+            scrutinee, arms, ..
+        } => {
+            let value = eval(scrutinee, env)?;
+
+            for arm in arms {
+                let mut bindings = vec![];
+                if !matches(arm.pattern(), &value, env, &mut bindings).map_err(error)? {
+                    continue;
+                }
+
+                let env = env.child();
+                for (name, value) in bindings {
+                    env.define(name, value);
+                }
+
+                if let Some(guard) = arm.guard() {
+                    match eval(guard, &env)? {
+                        Value::Bool(true) => (),
+                        Value::Bool(false) => continue,
+                        v => {
+                            return Err(RuntimeError::new(
+                                RuntimeErrorKind::type_mismatch("a bool", &v),
+                                guard.span(),
+                            ))
+                        }
+                    }
+                }
+
+                return eval(arm.body(), &env);
+            }
+
+            Err(error(RuntimeErrorKind::NoMatch(value.to_string())))
+        }
+
+        Expression::Tuple { items, .. } => Ok(Value::Tuple(eval_all(items, env)?)),
+        Expression::Array { items, .. } => Ok(Value::Array(eval_all(items, env)?)),
+
+        Expression::Repeat { value, count, .. } => {
+            let value = eval(value, env)?;
+            let n = eval(count, env)?;
+
+            repeat(value, n).map_err(|kind| RuntimeError::new(kind, count.span()))
+        }
+
+        Expression::Map { entries, .. } => {
+            let mut map: Vec<(Value, Value)> = vec![];
+
+            for (key, value) in entries {
+                let key = eval(key, env)?;
+                let value = eval(value, env)?;
+
+                match map.iter_mut().find(|(k, _)| *k == key) {
+                    Some((_, v)) => *v = value,
+                    None => map.push((key, value)),
+                }
+            }
+
+            Ok(Value::Map(map))
+        }
+
+        Expression::Struct {
+            path, fields, base, ..
+        } => {
+            let mut values = vec![];
+            for field in fields {
+                let value = match field.value() {
+                    Some(value) => eval(value, env)?,
+                    None => lookup(&Path::single(field.name()), env)?,
+                };
+
+                values.push((field.name(), value));
+            }
+
+            if let Some(base) = base {
+                match eval(base, env)? {
+                    Value::Struct { fields, .. } => {
+                        for (name, value) in fields {
+                            if !values.iter().any(|(n, _)| *n == name) {
+                                values.push((name, value));
+                            }
+                        }
+                    }
+
+                    v => {
+                        return Err(RuntimeError::new(
+                            RuntimeErrorKind::type_mismatch("a struct", &v),
+                            base.span(),
+                        ))
+                    }
+                }
+            }
+
+            Ok(Value::Struct {
+                name: path.to_string(),
+                fields: values,
+            })
+        }
+
+        Expression::Block(b) => block(b, env),
+
+        Expression::If {
+            condition,
+            then,
+            otherwise,
+            ..
+        } => {
+            if self::condition(condition, env)? {
+                block(then, env)
+            } else if let Some(otherwise) = otherwise {
+                eval(otherwise, env)
+            } else {
+                Ok(Value::unit())
+            }
+        }
+
+        Expression::While {
+            condition, body, ..
+        } => {
+            while self::condition(condition, env)? {
+                block(body, env)?;
+            }
+
+            Ok(Value::unit())
+        }
+
+        Expression::Attributed { expr, .. } => eval(expr, env),
+
+        Expression::MacroCall { .. } => Err(error(RuntimeErrorKind::Unsupported(
+            "a macro call before expansion",
+        ))),
+    }
+}
+
+fn eval_all<'s>(
+    exprs: &[Expression<'s>],
+    env: &Environment<'s>,
+) -> Result<Vec<Value<'s>>, RuntimeError> {
+    exprs.iter().map(|e| eval(e, env)).collect()
+}
+
+fn condition<'s>(condition: &Expression<'s>, env: &Environment<'s>) -> Result<bool, RuntimeError> {
+    match eval(condition, env)? {
+        Value::Bool(b) => Ok(b),
+        v => Err(RuntimeError::new(
+            RuntimeErrorKind::type_mismatch("a bool", &v),
+            condition.span(),
+        )),
+    }
+}
+
+fn lookup<'s>(path: &Path<'s>, env: &Environment<'s>) -> Result<Value<'s>, RuntimeError> {
+    let name = path.to_string();
+
+    env.get(&name)
+        .ok_or_else(|| RuntimeError::new(RuntimeErrorKind::UnknownName(name), path.span()))
+}
+
+/// The value of a literal, integers have to fit in an `i64` and reals are
+/// rounded to the nearest `f64`, or `f32` with that suffix.
+pub fn literal<'s>(literal: &Literal, units: &UnitRegistry) -> Result<Value<'s>, RuntimeErrorKind> {
+    Ok(match literal {
+        Literal::Number(n, Some(NumberSuffix::F32)) => Value::Real(n.to_f64() as f32 as f64),
+        Literal::Number(n, Some(NumberSuffix::F64)) => Value::Real(n.to_f64()),
+        Literal::Number(n, _) if n.is_integer() => {
+            Value::Integer(i64::try_from(n).map_err(|_| RuntimeErrorKind::Overflow)?)
+        }
+        Literal::Number(n, None) => Value::Real(n.to_f64()),
+        Literal::Number(n, Some(_)) => {
+            return Err(RuntimeErrorKind::type_mismatch(
+                "an integer",
+                &Value::Real(n.to_f64()),
+            ))
+        }
+        Literal::Quantity { value, unit } => {
+            let (value, dimension) = units
+                .to_base(value.to_f64(), unit)
+                .ok_or_else(|| RuntimeErrorKind::UnknownUnit(unit.clone()))?;
+
+            Value::Quantity { value, dimension }
+        }
+        Literal::String(s) => Value::String(s.clone()),
+        Literal::Char(c) => Value::Char(*c),
+    })
+}
+
+/// The value of a literal after a minus, which is `i64::MIN` for the
+/// `9223372036854775808` too large for an `i64` on its own.
+pub fn negated_literal<'s>(
+    literal: &Literal,
+    units: &UnitRegistry,
+) -> Result<Value<'s>, RuntimeErrorKind> {
+    match literal {
+        Literal::Number(n, suffix)
+            if n.is_integer()
+                && !suffix.is_some_and(|s| s.is_real())
+                && u64::try_from(n) == Ok(i64::MIN.unsigned_abs()) =>
+        {
+            Ok(Value::Integer(i64::MIN))
+        }
+
+        literal => prefix(Operator::Minus, self::literal(literal, units)?),
+    }
+}
+
+/// The value of a literal in a pattern, which can be negated.
+fn pattern_literal<'s>(
+    literal: &PatternLiteral,
+    units: &UnitRegistry,
+) -> Result<Value<'s>, RuntimeErrorKind> {
+    if literal.is_negative() {
+        negated_literal(literal.value(), units)
+    } else {
+        self::literal(literal.value(), units)
+    }
+}
+
+/// Calls a closure or a native function, `span` is the call's for the
+/// errors that aren't in the body of a closure.
+pub fn call<'s>(
+    function: &Value<'s>,
+    args: Vec<Value<'s>>,
+    span: Span,
+) -> Result<Value<'s>, RuntimeError> {
+    let error = |kind| RuntimeError::new(kind, span);
+
+    match function {
+        Value::Native(native) => {
+            if let Some(arity) = native.arity {
+                check_arity(arity, args.len()).map_err(error)?;
+            }
+
+            (native.function)(&args).map_err(error)
+        }
+
+        Value::Closure(closure) => {
+            check_arity(closure.parameters.len(), args.len()).map_err(error)?;
+
+            let env = closure.environment.child();
+            for (name, value) in closure.parameters.iter().zip(args) {
+                env.define(*name, value);
+            }
+
+            eval(&closure.body, &env)
+        }
+
+        v => Err(error(RuntimeErrorKind::NotCallable(v.type_name()))),
+    }
+}
+
+fn check_arity(expected: usize, found: usize) -> Result<(), RuntimeErrorKind> {
+    if expected != found {
+        return Err(RuntimeErrorKind::ArityMismatch { expected, found });
+    }
+
+    Ok(())
+}
+
+fn assign<'s>(
+    target: &Expression<'s>,
+    op: Operator,
+    value: &Expression<'s>,
+    env: &Environment<'s>,
+    span: Span,
+) -> Result<Value<'s>, RuntimeError> {
+    let Expression::Name(path) = target else {
+        return Err(RuntimeError::new(
+            RuntimeErrorKind::Unsupported("an assignment to something other than a variable"),
+            target.span(),
+        ));
+    };
+
+    let mut value = eval(value, env)?;
+
+    if op != Operator::Equals {
+        let current = lookup(path, env)?;
+        value = infix(current, compound_operator(op), value)
+            .map_err(|kind| RuntimeError::new(kind, span))?;
+    }
+
+    if !env.assign(&path.to_string(), value) {
+        return Err(RuntimeError::new(
+            RuntimeErrorKind::UnknownName(path.to_string()),
+            path.span(),
+        ));
+    }
+
+    Ok(Value::unit())
+}
+
+/// The operator of a compound assignment like `+=`.
+pub(crate) fn compound_operator(op: Operator) -> Operator {
+    match op {
+        Operator::PlusEquals => Operator::Plus,
+        Operator::MinusEquals => Operator::Minus,
+        Operator::StarEquals => Operator::Star,
+        Operator::SlashEquals => Operator::Slash,
+        Operator::TildaEquals => Operator::Tilda,
+        Operator::DoubleStarEquals => Operator::DoubleStar,
+        Operator::DoubleAndEquals => Operator::DoubleAnd,
+        Operator::DoubleOrEquals => Operator::DoubleOr,
+        Operator::CaretEquals => Operator::Caret,
+        Operator::PercentEquals => Operator::Percent,
+        Operator::SingleAndEquals => Operator::SingleAnd,
+        Operator::SingleOrEquals => Operator::SingleOr,
+        Operator::LeftShiftEquals => Operator::LeftShift,
+        Operator::RightShiftEquals => Operator::RightShift,
+
+        op => op,
+    }
+}
+
+pub fn prefix(op: Operator, right: Value<'_>) -> Result<Value<'_>, RuntimeErrorKind> {
+    match (op, right) {
+        (Operator::Minus, Value::Integer(i)) => i
+            .checked_neg()
+            .map(Value::Integer)
+            .ok_or(RuntimeErrorKind::Overflow),
+        (Operator::Minus, Value::Real(r)) => Ok(Value::Real(-r)),
+        (Operator::Minus, Value::Quantity { value, dimension }) => Ok(Value::Quantity {
+            value: -value,
+            dimension,
+        }),
+        (Operator::Plus, v @ (Value::Integer(_) | Value::Real(_) | Value::Quantity { .. })) => {
+            Ok(v)
+        }
+        (Operator::Minus | Operator::Plus, v) => {
+            Err(RuntimeErrorKind::type_mismatch("a number", &v))
+        }
+
+        (Operator::Bang, Value::Bool(b)) => Ok(Value::Bool(!b)),
+        (Operator::Bang | Operator::Tilda, Value::Integer(i)) => Ok(Value::Integer(!i)),
+        (Operator::Bang | Operator::Tilda, v) => {
+            Err(RuntimeErrorKind::type_mismatch("an integer or a bool", &v))
+        }
+
+        _ => Err(RuntimeErrorKind::Unsupported("this prefix operator")),
+    }
+}
+
+/// Applies an arithmetic operator to a quantity and a quantity or a number.
+///
+/// Quantities of the same dimension add and compare, scale by numbers, and
+/// divide to the number of times one is in the other, like `90deg / 1rad`.
+fn quantity<'s>(
+    left: Value<'s>,
+    op: Operator,
+    right: Value<'s>,
+) -> Result<Value<'s>, RuntimeErrorKind> {
+    use Operator as Op;
+    use Value as V;
+
+    let mismatch = |left: &Value<'_>, right: &Value<'_>| RuntimeErrorKind::IncompatibleUnits {
+        left: left.type_name(),
+        right: right.type_name(),
+    };
+
+    Ok(match (&left, op, &right) {
+        (
+            V::Quantity {
+                value: a,
+                dimension,
+            },
+            Op::Plus | Op::Minus | Op::Slash | Op::Percent,
+            V::Quantity {
+                value: b,
+                dimension: other,
+            },
+        ) => {
+            if dimension != other {
+                return Err(mismatch(&left, &right));
+            }
+
+            let dimension = *dimension;
+            match op {
+                Op::Plus => V::Quantity {
+                    value: a + b,
+                    dimension,
+                },
+                Op::Minus => V::Quantity {
+                    value: a - b,
+                    dimension,
+                },
+                Op::Percent => V::Quantity {
+                    value: a % b,
+                    dimension,
+                },
+                _ => V::Real(a / b),
+            }
+        }
+
+        (V::Quantity { value, dimension }, Op::Star | Op::Slash, n)
+        | (n, Op::Star, V::Quantity { value, dimension }) => {
+            let Some(n) = n.as_real() else {
+                return Err(match n {
+                    V::Quantity { .. } => RuntimeErrorKind::Unsupported("a product of quantities"),
+                    n => RuntimeErrorKind::type_mismatch("a number", n),
+                });
+            };
+
+            V::Quantity {
+                value: if op == Op::Star { value * n } else { value / n },
+                dimension: *dimension,
+            }
+        }
+
+        (V::Quantity { .. }, Op::Plus | Op::Minus | Op::Percent, _)
+        | (_, Op::Plus | Op::Minus | Op::Percent, V::Quantity { .. }) => {
+            return Err(mismatch(&left, &right))
+        }
+
+        _ => return Err(RuntimeErrorKind::Unsupported("this operator on a quantity")),
+    })
+}
+
+pub fn postfix(op: Operator, left: Value<'_>) -> Result<Value<'_>, RuntimeErrorKind> {
+    match (op, left) {
+        // factorial
+        (Operator::Bang, Value::Integer(n)) if n >= 0 => (1..=n)
+            .try_fold(1i64, |acc, i| acc.checked_mul(i))
+            .map(Value::Integer)
+            .ok_or(RuntimeErrorKind::Overflow),
+        (Operator::Bang, v) => Err(RuntimeErrorKind::type_mismatch(
+            "a non negative integer",
+            &v,
+        )),
+
+        _ => Err(RuntimeErrorKind::Unsupported("this postfix operator")),
+    }
+}
+
+/// Applies an operator that evaluates both of its operands.
+pub fn infix<'s>(
+    left: Value<'s>,
+    op: Operator,
+    right: Value<'s>,
+) -> Result<Value<'s>, RuntimeErrorKind> {
+    use Operator as Op;
+    use Value as V;
+
+    match op {
+        Op::DoubleEquals => return Ok(V::Bool(left == right)),
+        Op::BangEquals => return Ok(V::Bool(left != right)),
+
+        Op::LesserThan | Op::GreaterThan | Op::LesserThanEquals | Op::GreaterThanEquals => {
+            let Some(ordering) = left.compare(&right) else {
+                return Err(RuntimeErrorKind::type_mismatch(left.type_name(), &right));
+            };
+
+            return Ok(V::Bool(match op {
+                Op::LesserThan => ordering.is_lt(),
+                Op::GreaterThan => ordering.is_gt(),
+                Op::LesserThanEquals => ordering.is_le(),
+                _ => ordering.is_ge(),
+            }));
+        }
+
+        _ => (),
+    }
+
+    Ok(match (left, right) {
+        (V::Integer(a), V::Integer(b)) => V::Integer(integer(a, op, b)?),
+
+        (a @ V::Quantity { .. }, b) | (a, b @ V::Quantity { .. }) => quantity(a, op, b)?,
+
+        // reals follow IEEE 754, dividing them by zero is infinite
+        (a @ (V::Integer(_) | V::Real(_)), b @ (V::Integer(_) | V::Real(_))) => {
+            let (a, b) = (a.as_real().unwrap(), b.as_real().unwrap());
+
+            V::Real(match op {
+                Op::Plus => a + b,
+                Op::Minus => a - b,
+                Op::Star => a * b,
+                Op::Slash => a / b,
+                Op::Percent => a % b,
+                Op::DoubleStar => a.powf(b),
+
+                _ => return Err(RuntimeErrorKind::type_mismatch("integers", &V::Real(a))),
+            })
+        }
+
+        (V::String(mut a), V::String(b)) if op == Op::Plus => {
+            a.push_str(&b);
+            V::String(a)
+        }
+        (V::String(mut a), V::Char(b)) if op == Op::Plus => {
+            a.push(b);
+            V::String(a)
+        }
+        (V::Char(a), V::String(b)) if op == Op::Plus => V::String(format!("{a}{b}")),
+
+        (V::Bool(a), V::Bool(b)) => V::Bool(match op {
+            Op::SingleAnd => a & b,
+            Op::SingleOr => a | b,
+            Op::Caret => a ^ b,
+            // from the compound assignments `&&=` and `||=`
+            Op::DoubleAnd => a && b,
+            Op::DoubleOr => a || b,
+
+            _ => return Err(RuntimeErrorKind::type_mismatch("a number", &V::Bool(a))),
+        }),
+
+        (V::Array(mut a), V::Array(b)) if op == Op::Plus => {
+            a.extend(b);
+            V::Array(a)
+        }
+
+        (a @ (V::Integer(_) | V::Real(_)), b) | (b, a @ (V::Integer(_) | V::Real(_))) => {
+            return Err(RuntimeErrorKind::type_mismatch(a.type_name(), &b))
+        }
+
+        (a, _) => return Err(RuntimeErrorKind::type_mismatch("a number", &a)),
+    })
+}
+
+fn integer(a: i64, op: Operator, b: i64) -> Result<i64, RuntimeErrorKind> {
+    use Operator as Op;
+
+    let result = match op {
+        Op::Plus => a.checked_add(b),
+        Op::Minus => a.checked_sub(b),
+        Op::Star => a.checked_mul(b),
+        Op::Slash | Op::Percent if b == 0 => return Err(RuntimeErrorKind::DivisionByZero),
+        Op::Slash => a.checked_div(b),
+        Op::Percent => a.checked_rem(b),
+        Op::DoubleStar => match u32::try_from(b) {
+            Ok(b) => a.checked_pow(b),
+            Err(_) if b < 0 => {
+                return Err(RuntimeErrorKind::type_mismatch(
+                    "a non negative exponent",
+                    &Value::Integer(b),
+                ))
+            }
+            Err(_) => None,
+        },
+
+        Op::SingleAnd => Some(a & b),
+        Op::SingleOr => Some(a | b),
+        Op::Caret => Some(a ^ b),
+        Op::LeftShift => u32::try_from(b).ok().and_then(|b| a.checked_shl(b)),
+        Op::RightShift => u32::try_from(b).ok().and_then(|b| a.checked_shr(b)),
+
+        _ => return Err(RuntimeErrorKind::Unsupported("this operator on integers")),
+    };
+
+    result.ok_or(RuntimeErrorKind::Overflow)
+}
+
+/// The most items `[value; count]` makes, a larger count is an error and
+/// not an allocation failure that aborts the process.
+pub const MAX_REPEAT: usize = 1 << 24;
+
+/// The array of `[value; count]`.
+pub fn repeat<'s>(value: Value<'s>, count: Value<'s>) -> Result<Value<'s>, RuntimeErrorKind> {
+    match count {
+        Value::Integer(n) if n < 0 => Err(RuntimeErrorKind::NegativeCount(n)),
+        Value::Integer(n) if n as u64 > MAX_REPEAT as u64 => Err(RuntimeErrorKind::TooLong {
+            len: n,
+            max: MAX_REPEAT,
+        }),
+        Value::Integer(n) => Ok(Value::Array(vec![value; n as usize])),
+
+        v => Err(RuntimeErrorKind::type_mismatch("an integer", &v)),
+    }
+}
+
+/// Indexes an array, a tuple or a string by position, or a map by key.
+pub fn index<'s>(value: Value<'s>, index: Value<'s>) -> Result<Value<'s>, RuntimeErrorKind> {
+    let position = |len: usize| match index {
+        Value::Integer(i) if i >= 0 && (i as usize) < len => Ok(i as usize),
+        Value::Integer(i) => Err(RuntimeErrorKind::IndexOutOfBounds { index: i, len }),
+        ref v => Err(RuntimeErrorKind::type_mismatch("an integer index", v)),
+    };
+
+    match value {
+        Value::Array(mut items) | Value::Tuple(mut items) => {
+            let i = position(items.len())?;
+            Ok(items.swap_remove(i))
+        }
+
+        Value::String(ref s) => {
+            let i = position(s.chars().count())?;
+            Ok(Value::Char(s.chars().nth(i).unwrap()))
+        }
+
+        Value::Map(entries) => entries
+            .into_iter()
+            .find(|(k, _)| *k == index)
+            .map(|(_, v)| v)
+            .ok_or_else(|| RuntimeErrorKind::MissingKey(index.to_string())),
+
+        v => Err(RuntimeErrorKind::type_mismatch("an indexable value", &v)),
+    }
+}
+
+/// Whether `value` matches `pattern`, collecting the bindings of the pattern.
+fn matches<'s>(
+    pattern: &Pattern<'s>,
+    value: &Value<'s>,
+    env: &Environment<'s>,
+    bindings: &mut Vec<(&'s str, Value<'s>)>,
+) -> Result<bool, RuntimeErrorKind> {
+    Ok(match pattern {
+        Pattern::Wildcard => true,
+
+        Pattern::Binding(name) => {
+            bindings.push((name, value.clone()));
+            true
+        }
+
+        Pattern::Literal(l) => *value == pattern_literal(l, env.units())?,
+
+        Pattern::Range {
+            start,
+            end,
+            inclusive,
+        } => {
+            let above_start = match start {
+                Some(start) => value
+                    .compare(&pattern_literal(start, env.units())?)
+                    .is_some_and(|o| o.is_ge()),
+                None => true,
+            };
+
+            let below_end = match end {
+                Some(end) => value
+                    .compare(&pattern_literal(end, env.units())?)
+                    .is_some_and(|o| if *inclusive { o.is_le() } else { o.is_lt() }),
+                None => true,
+            };
+
+            above_start && below_end
+        }
+
+        Pattern::Tuple(patterns) => match value {
+            Value::Tuple(items) if items.len() == patterns.len() => {
+                for (p, v) in patterns.iter().zip(items) {
+                    if !matches(p, v, env, bindings)? {
+                        return Ok(false);
+                    }
+                }
+
+                true
+            }
+
+            _ => false,
+        },
+
+        // a constant like `None`
+        Pattern::Path(path) => {
+            let name = path.to_string();
+            match env.get(&name) {
+                Some(constant) => *value == constant,
+                None => return Err(RuntimeErrorKind::UnknownName(name)),
+            }
+        }
+
+        Pattern::TupleStruct { .. } => {
+            return Err(RuntimeErrorKind::Unsupported("a tuple struct pattern"))
+        }
+
+        Pattern::Or(alternatives) => {
+            for p in alternatives {
+                let mut alternative = vec![];
+                if matches(p, value, env, &mut alternative)? {
+                    bindings.extend(alternative);
+                    return Ok(true);
+                }
+            }
+
+            false
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeat_counts() {
+        assert_eq!(
+            repeat(Value::Integer(0), Value::Integer(3)),
+            Ok(Value::Array(vec![Value::Integer(0); 3]))
+        );
+
+        assert_eq!(
+            repeat(Value::Integer(0), Value::Integer(-1)),
+            Err(RuntimeErrorKind::NegativeCount(-1))
+        );
+
+        for len in [MAX_REPEAT as i64 + 1, 100000000000000, i64::MAX] {
+            assert_eq!(
+                repeat(Value::Integer(0), Value::Integer(len)),
+                Err(RuntimeErrorKind::TooLong {
+                    len,
+                    max: MAX_REPEAT
+                })
+            );
+        }
+    }
+}
@@ -0,0 +1,246 @@
+use std::{cmp::Ordering, fmt::Display, rc::Rc};
+
+use crate::{
+    parser::{expression::Expression, write_separated},
+    units::Dimension,
+};
+
+use super::{Environment, RuntimeErrorKind};
+
+/// The signature of the functions implemented in Rust, see [`Environment::define_native`].
+pub type NativeFn<'s> = dyn Fn(&[Value<'s>]) -> Result<Value<'s>, RuntimeErrorKind> + 's;
+
+#[derive(Clone)]
+pub struct NativeFunction<'s> {
+    pub(crate) name: String,
+    /// `None` for functions taking any number of arguments.
+    pub(crate) arity: Option<usize>,
+    pub(crate) function: Rc<NativeFn<'s>>,
+}
+
+impl<'s> NativeFunction<'s> {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn arity(&self) -> Option<usize> {
+        self.arity
+    }
+}
+
+impl std::fmt::Debug for NativeFunction<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NativeFunction")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A lambda with the variables it was defined with.
+#[derive(Debug, Clone)]
+pub struct Closure<'s> {
+    pub(crate) parameters: Vec<&'s str>,
+    pub(crate) body: Rc<Expression<'s>>,
+    pub(crate) environment: Environment<'s>,
+}
+
+impl<'s> Closure<'s> {
+    pub fn parameters(&self) -> &[&'s str] {
+        &self.parameters
+    }
+
+    pub fn body(&self) -> &Expression<'s> {
+        &self.body
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Value<'s> {
+    Integer(i64),
+    Real(f64),
+    String(String),
+    Char(char),
+    Bool(bool),
+    /// A number with a unit, by its value in the base unit of its dimension.
+    Quantity {
+        value: f64,
+        dimension: Dimension,
+    },
+
+    /// The empty tuple is the value of statements and of loops.
+    Tuple(Vec<Value<'s>>),
+    Array(Vec<Value<'s>>),
+    /// The entries in the order they were inserted, keys are unique.
+    Map(Vec<(Value<'s>, Value<'s>)>),
+    Struct {
+        name: String,
+        fields: Vec<(&'s str, Value<'s>)>,
+    },
+
+    Closure(Closure<'s>),
+    Native(NativeFunction<'s>),
+}
+
+impl<'s> Value<'s> {
+    pub fn unit() -> Self {
+        Self::Tuple(vec![])
+    }
+
+    /// The name of the kind of value, for error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Integer(_) => "integer",
+            Self::Real(_) => "real",
+            Self::String(_) => "string",
+            Self::Char(_) => "char",
+            Self::Bool(_) => "bool",
+            Self::Quantity { dimension, .. } => dimension.name(),
+            Self::Tuple(items) if items.is_empty() => "unit",
+            Self::Tuple(_) => "tuple",
+            Self::Array(_) => "array",
+            Self::Map(_) => "map",
+            Self::Struct { .. } => "struct",
+            Self::Closure(_) | Self::Native(_) => "function",
+        }
+    }
+
+    /// The value as a real number, if it's a number.
+    pub fn as_real(&self) -> Option<f64> {
+        match self {
+            Self::Integer(i) => Some(*i as f64),
+            Self::Real(r) => Some(*r),
+
+            _ => None,
+        }
+    }
+
+    /// Orders numbers, strings and chars, and `None` for the other values.
+    ///
+    /// Integers and reals are compared as reals, and quantities only with
+    /// the quantities of the same dimension.
+    pub fn compare(&self, other: &Value<'s>) -> Option<Ordering> {
+        match (self, other) {
+            (
+                Self::Quantity { value, dimension },
+                Self::Quantity {
+                    value: other_value,
+                    dimension: other_dimension,
+                },
+            ) if dimension == other_dimension => value.partial_cmp(other_value),
+            (Self::Integer(a), Self::Integer(b)) => Some(a.cmp(b)),
+            (Self::String(a), Self::String(b)) => Some(a.cmp(b)),
+            (Self::Char(a), Self::Char(b)) => Some(a.cmp(b)),
+            (Self::Bool(a), Self::Bool(b)) => Some(a.cmp(b)),
+
+            (a, b) => a.as_real()?.partial_cmp(&b.as_real()?),
+        }
+    }
+}
+
+impl PartialEq for Value<'_> {
+    /// Functions are only equal to themselves.
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::String(a), Self::String(b)) => a == b,
+            (Self::Char(a), Self::Char(b)) => a == b,
+            (Self::Bool(a), Self::Bool(b)) => a == b,
+            (Self::Tuple(a), Self::Tuple(b)) | (Self::Array(a), Self::Array(b)) => a == b,
+            (Self::Map(a), Self::Map(b)) => {
+                a.len() == b.len()
+                    && a.iter()
+                        .all(|(k, v)| b.iter().any(|(bk, bv)| k == bk && v == bv))
+            }
+            (
+                Self::Struct { name, fields },
+                Self::Struct {
+                    name: other_name,
+                    fields: other_fields,
+                },
+            ) => {
+                name == other_name
+                    && fields.len() == other_fields.len()
+                    && fields
+                        .iter()
+                        .all(|(n, v)| other_fields.iter().any(|(on, ov)| n == on && v == ov))
+            }
+
+            (Self::Closure(a), Self::Closure(b)) => Rc::ptr_eq(&a.body, &b.body),
+            (Self::Native(a), Self::Native(b)) => Rc::ptr_eq(&a.function, &b.function),
+
+            (a, b) => matches!(a.compare(b), Some(Ordering::Equal)),
+        }
+    }
+}
+
+impl From<i64> for Value<'_> {
+    fn from(value: i64) -> Self {
+        Self::Integer(value)
+    }
+}
+impl From<f64> for Value<'_> {
+    fn from(value: f64) -> Self {
+        Self::Real(value)
+    }
+}
+impl From<bool> for Value<'_> {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+impl From<String> for Value<'_> {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl Display for Value<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Integer(i) => write!(f, "{i}"),
+            // keeps the `.0` of whole numbers, to tell them from integers
+            Self::Real(r) => write!(f, "{r:?}"),
+            Self::String(s) => write!(f, "\"{s}\""),
+            Self::Char(c) => write!(f, "'{c}'"),
+            Self::Bool(b) => write!(f, "{b}"),
+            Self::Quantity { value, dimension } => write!(f, "{value:?}{}", dimension.base_unit()),
+
+            Self::Tuple(items) => {
+                write!(f, "(")?;
+                write_separated(f, items, ", ")?;
+
+                if items.len() == 1 {
+                    write!(f, ",")?;
+                }
+
+                write!(f, ")")
+            }
+
+            Self::Array(items) => {
+                write!(f, "[")?;
+                write_separated(f, items, ", ")?;
+                write!(f, "]")
+            }
+
+            Self::Map(entries) => {
+                write!(f, "{{")?;
+                write_separated(f, entries.iter().map(|(k, v)| format!("{k}: {v}")), ", ")?;
+                write!(f, "}}")
+            }
+
+            Self::Struct { name, fields } => {
+                write!(f, "{name} {{ ")?;
+                write_separated(f, fields.iter().map(|(n, v)| format!("{n}: {v}")), ", ")?;
+                write!(f, " }}")
+            }
+
+            Self::Closure(c) => {
+                write!(f, "(")?;
+                write_separated(f, &c.parameters, ", ")?;
+                write!(f, ") => {}", c.body)
+            }
+
+            Self::Native(n) => write!(f, "<native {}>", n.name),
+        }
+    }
+}
@@ -0,0 +1,680 @@
+//! Macro expansion. Runs on the parsed program before any later pass, and
+//! replaces every [`Expression::MacroCall`] with the parsed result of the
+//! first matching rule of its macro.
+//!
+//! Macros are collected from the top level of the program, so they can be
+//! called before their definition.
+
+use std::collections::HashMap;
+
+use crate::{
+    parser::{
+        expression::Expression,
+        macros::{FragmentKind, MacroDefinition, Matcher, RepetitionKind, TokenTree},
+        operator::OperatorTable,
+        statement::{Block, Statement},
+        visit::{walk_expression, VisitorMut},
+        Parser,
+    },
+    tokenizer::{
+        Delimeter, DelimeterSide, DelimeterType, Operator, Punctuation, Span, Token, TokenPosition,
+        TokenType as Ty,
+    },
+    Diagnostic, DiagnosticType,
+};
+
+pub const DEFAULT_RECURSION_LIMIT: usize = 64;
+
+pub struct Expander<'s, 'd, D: Extend<Diagnostic<'s>>> {
+    macros: HashMap<&'s str, MacroDefinition<'s>>,
+    diagnostics: &'d mut D,
+
+    depth: usize,
+    pub recursion_limit: usize,
+}
+
+impl<'s, 'd, D: Extend<Diagnostic<'s>>> Expander<'s, 'd, D> {
+    pub fn new(diagnostics: &'d mut D) -> Self {
+        Self {
+            macros: HashMap::new(),
+            diagnostics,
+
+            depth: 0,
+            recursion_limit: DEFAULT_RECURSION_LIMIT,
+        }
+    }
+
+    pub fn expand(&mut self, statements: &mut [Statement<'s>]) {
+        for s in statements.iter() {
+            if let Statement::Macro(definition) = s.inner() {
+                self.macros.insert(definition.name, definition.clone());
+            }
+        }
+
+        for s in statements {
+            self.visit_statement(s);
+        }
+    }
+
+    fn report(&mut self, ty: DiagnosticType, position: TokenPosition<'s>) {
+        self.diagnostics.extend([Diagnostic::new(ty, position)]);
+    }
+
+    /// Expands a single call, without expanding the calls in the result.
+    fn expand_call(
+        &mut self,
+        name: &str,
+        tokens: &[TokenTree<'s>],
+        position: TokenPosition<'s>,
+    ) -> Option<Expression<'s>> {
+        let Some(definition) = self.macros.get(name) else {
+            self.report(DiagnosticType::UndefinedMacro, position);
+            return None;
+        };
+
+        let expansion = definition.rules.iter().find_map(|rule| {
+            let mut bindings = Bindings::new();
+            let consumed = match_sequence(&rule.matchers, tokens, None, &mut bindings)?;
+
+            if consumed != tokens.len() {
+                return None;
+            }
+
+            let mut out = vec![];
+            Some(transcribe(&rule.transcriber, &bindings, &mut out).map(|()| out))
+        });
+
+        let trees = match expansion {
+            Some(Some(trees)) => trees,
+
+            Some(None) => {
+                self.report(DiagnosticType::InvalidMacroExpansion, position);
+                return None;
+            }
+
+            None => {
+                self.report(DiagnosticType::NoMatchingMacroRule, position);
+                return None;
+            }
+        };
+
+        let mut tokens = vec![];
+        for t in &trees {
+            t.flatten_into(&mut tokens);
+        }
+
+        let mut diagnostics = vec![];
+        let statements = Parser::new(
+            tokens.into_iter().map(Ok),
+            &mut diagnostics,
+            OperatorTable::default(),
+        )
+        .program();
+        self.diagnostics.extend(diagnostics);
+
+        let Ok(mut statements) = statements else {
+            self.report(DiagnosticType::InvalidMacroExpansion, position);
+            return None;
+        };
+
+        Some(match statements.as_slice() {
+            [Statement::Expression {
+                semicolon: false, ..
+            }] => {
+                let Some(Statement::Expression { expr, .. }) = statements.pop() else {
+                    unreachable!()
+                };
+
+                expr
+            }
+
+            _ => Expression::Block(Block::new(statements, Span::from(position))),
+        })
+    }
+}
+
+impl<'s, 'd, D: Extend<Diagnostic<'s>>> VisitorMut<'s> for Expander<'s, 'd, D> {
+    fn visit_expression(&mut self, expr: &mut Expression<'s>) {
+        let Expression::MacroCall {
+            path,
+            tokens,
+            position,
+            ..
+        } = expr
+        else {
+            return walk_expression(self, expr);
+        };
+
+        let position = *position;
+
+        if self.depth >= self.recursion_limit {
+            self.report(DiagnosticType::MacroRecursionLimit, position);
+            return;
+        }
+
+        let Some(expanded) = self.expand_call(&path.to_string(), &tokens.clone(), position) else {
+            return;
+        };
+
+        *expr = expanded;
+
+        self.depth += 1;
+        self.visit_expression(expr);
+        self.depth -= 1;
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Binding<'s> {
+    Fragment {
+        kind: FragmentKind,
+        trees: Vec<TokenTree<'s>>,
+    },
+
+    /// One binding per repetition.
+    Repeated(Vec<Binding<'s>>),
+}
+
+type Bindings<'s> = HashMap<&'s str, Binding<'s>>;
+
+fn same_token(a: &Token<'_>, b: &Token<'_>) -> bool {
+    a.ty == b.ty && a.text() == b.text()
+}
+
+fn is_token(tree: Option<&TokenTree<'_>>, token: &Token<'_>) -> bool {
+    matches!(tree, Some(TokenTree::Token(t)) if same_token(t, token))
+}
+
+/// Matches a prefix of `trees`, returning how many trees were consumed.
+///
+/// `follow` is the token after the sequence in the enclosing matcher,
+/// which is where an `expr`, `ty` or `pat` fragment at its end stops.
+fn match_sequence<'s>(
+    matchers: &[Matcher<'s>],
+    trees: &[TokenTree<'s>],
+    follow: Option<&Token<'s>>,
+    bindings: &mut Bindings<'s>,
+) -> Option<usize> {
+    let mut pos = 0;
+
+    for (i, matcher) in matchers.iter().enumerate() {
+        let next_follow = match matchers.get(i + 1) {
+            Some(Matcher::Token(t)) => Some(t),
+            Some(Matcher::Repetition { matchers, .. }) => match matchers.first() {
+                Some(Matcher::Token(t)) => Some(t),
+                _ => None,
+            },
+            Some(_) => None,
+            None => follow,
+        };
+
+        match matcher {
+            Matcher::Token(t) => {
+                if !is_token(trees.get(pos), t) {
+                    return None;
+                }
+
+                pos += 1;
+            }
+
+            Matcher::Group {
+                delimiter,
+                matchers,
+            } => {
+                let Some(TokenTree::Group {
+                    delimiter: d,
+                    trees: inner,
+                    ..
+                }) = trees.get(pos)
+                else {
+                    return None;
+                };
+
+                if d != delimiter || match_sequence(matchers, inner, None, bindings)? != inner.len()
+                {
+                    return None;
+                }
+
+                pos += 1;
+            }
+
+            Matcher::Fragment { name, kind } => {
+                let len = match_fragment(*kind, &trees[pos..], next_follow)?;
+
+                bindings.insert(
+                    name,
+                    Binding::Fragment {
+                        kind: *kind,
+                        trees: trees[pos..pos + len].to_vec(),
+                    },
+                );
+
+                pos += len;
+            }
+
+            Matcher::Repetition {
+                matchers,
+                separator,
+                kind,
+            } => {
+                let mut iterations = vec![];
+
+                loop {
+                    if *kind == RepetitionKind::ZeroOrOne && iterations.len() == 1 {
+                        break;
+                    }
+
+                    let mut start = pos;
+                    if let (Some(separator), false) = (separator, iterations.is_empty()) {
+                        if !is_token(trees.get(start), separator) {
+                            break;
+                        }
+
+                        start += 1;
+                    }
+
+                    let mut iteration = Bindings::new();
+                    // the iteration ends where the next one would start
+                    let inner_follow = separator
+                        .as_ref()
+                        .or(match matchers.first() {
+                            Some(Matcher::Token(t)) => Some(t),
+                            _ => None,
+                        })
+                        .or(next_follow);
+
+                    match match_sequence(matchers, &trees[start..], inner_follow, &mut iteration) {
+                        Some(len) if len > 0 => {
+                            pos = start + len;
+                            iterations.push(iteration);
+                        }
+
+                        _ => break,
+                    }
+                }
+
+                if *kind == RepetitionKind::OneOrMore && iterations.is_empty() {
+                    return None;
+                }
+
+                let mut names = vec![];
+                matcher_names(matchers, &mut names);
+
+                for name in names {
+                    let repeated = iterations
+                        .iter_mut()
+                        .filter_map(|b| b.remove(name))
+                        .collect();
+
+                    bindings.insert(name, Binding::Repeated(repeated));
+                }
+            }
+        }
+    }
+
+    Some(pos)
+}
+
+/// Returns how many trees the fragment matches at the start of `trees`.
+fn match_fragment(
+    kind: FragmentKind,
+    trees: &[TokenTree<'_>],
+    follow: Option<&Token<'_>>,
+) -> Option<usize> {
+    let first = trees.first()?;
+
+    match kind {
+        FragmentKind::Tt => return Some(1),
+
+        FragmentKind::Ident => {
+            return matches!(
+                first,
+                TokenTree::Token(Token {
+                    ty: Ty::Identifier,
+                    ..
+                })
+            )
+            .then_some(1)
+        }
+
+        FragmentKind::Literal => {
+            return matches!(
+                first,
+                TokenTree::Token(Token {
+                    ty: Ty::Literal(_),
+                    ..
+                })
+            )
+            .then_some(1)
+        }
+
+        FragmentKind::Expr | FragmentKind::Ty | FragmentKind::Pat => (),
+    }
+
+    let len = match follow {
+        Some(follow) => trees
+            .iter()
+            .position(|t| is_token(Some(t), follow))
+            .unwrap_or(trees.len()),
+        None => trees.len(),
+    };
+
+    if len == 0 {
+        return None;
+    }
+
+    let mut tokens = vec![];
+    for t in &trees[..len] {
+        t.flatten_into(&mut tokens);
+    }
+
+    let mut diagnostics = vec![];
+    let mut parser = Parser::new(
+        tokens.into_iter().map(Ok),
+        &mut diagnostics,
+        OperatorTable::default(),
+    );
+
+    let parsed = match kind {
+        FragmentKind::Expr => parser.expr().is_ok(),
+        FragmentKind::Ty => parser.ty().is_ok(),
+        FragmentKind::Pat => parser.pattern().is_ok(),
+        _ => unreachable!(),
+    };
+
+    (parsed && matches!(parser.at_end(), Ok(true))).then_some(len)
+}
+
+fn matcher_names<'s>(matchers: &[Matcher<'s>], out: &mut Vec<&'s str>) {
+    for m in matchers {
+        match m {
+            Matcher::Token(_) => (),
+            Matcher::Fragment { name, .. } => out.push(name),
+            Matcher::Group { matchers, .. } | Matcher::Repetition { matchers, .. } => {
+                matcher_names(matchers, out)
+            }
+        }
+    }
+}
+
+/// The names of the `$name` metavariables used in a transcriber.
+fn transcriber_names<'s>(trees: &[TokenTree<'s>], out: &mut Vec<&'s str>) {
+    for (i, t) in trees.iter().enumerate() {
+        match t {
+            TokenTree::Token(t) if t.ty == Ty::Punctuation(Punctuation::Dollar) => {
+                if let Some(TokenTree::Token(
+                    name @ Token {
+                        ty: Ty::Identifier, ..
+                    },
+                )) = trees.get(i + 1)
+                {
+                    out.push(name.text());
+                }
+            }
+
+            TokenTree::Group { trees, .. } => transcriber_names(trees, out),
+
+            TokenTree::Token(_) => (),
+        }
+    }
+}
+
+fn synthetic_paren<'s>(side: DelimeterSide, position: TokenPosition<'s>) -> Token<'s> {
+    Token {
+        ty: Ty::Delimeter(Delimeter {
+            ty: DelimeterType::Parentheses,
+            side,
+        }),
+        position: TokenPosition {
+            text: if side == DelimeterSide::Left {
+                "("
+            } else {
+                ")"
+            },
+            ..position
+        },
+    }
+}
+
+fn repetition_kind(tree: Option<&TokenTree<'_>>) -> Option<RepetitionKind> {
+    match tree {
+        Some(TokenTree::Token(Token {
+            ty: Ty::Operator(Operator::Star),
+            ..
+        })) => Some(RepetitionKind::ZeroOrMore),
+        Some(TokenTree::Token(Token {
+            ty: Ty::Operator(Operator::Plus),
+            ..
+        })) => Some(RepetitionKind::OneOrMore),
+        Some(TokenTree::Token(Token {
+            ty: Ty::Punctuation(Punctuation::QuestionMark),
+            ..
+        })) => Some(RepetitionKind::ZeroOrOne),
+
+        _ => None,
+    }
+}
+
+/// Substitutes the bindings into the transcriber. Returns `None` when a
+/// metavariable is unbound or used at the wrong repetition depth.
+fn transcribe<'s>(
+    trees: &[TokenTree<'s>],
+    bindings: &Bindings<'s>,
+    out: &mut Vec<TokenTree<'s>>,
+) -> Option<()> {
+    let mut i = 0;
+
+    while let Some(tree) = trees.get(i) {
+        i += 1;
+
+        let dollar = match tree {
+            TokenTree::Token(t) if t.ty == Ty::Punctuation(Punctuation::Dollar) => t,
+
+            // `$$` is an escaped dollar sign
+            TokenTree::Token(t) if t.ty == Ty::Punctuation(Punctuation::DoubleDollar) => {
+                out.push(TokenTree::Token(Token {
+                    ty: Ty::Punctuation(Punctuation::Dollar),
+                    position: TokenPosition {
+                        text: &t.position.text[..1],
+                        ..t.position
+                    },
+                }));
+                continue;
+            }
+
+            TokenTree::Token(_) => {
+                out.push(tree.clone());
+                continue;
+            }
+
+            TokenTree::Group {
+                delimiter,
+                open,
+                trees,
+                close,
+            } => {
+                let mut inner = vec![];
+                transcribe(trees, bindings, &mut inner)?;
+
+                out.push(TokenTree::Group {
+                    delimiter: *delimiter,
+                    open: open.clone(),
+                    trees: inner,
+                    close: close.clone(),
+                });
+                continue;
+            }
+        };
+
+        match trees.get(i) {
+            Some(TokenTree::Token(
+                name @ Token {
+                    ty: Ty::Identifier, ..
+                },
+            )) => {
+                i += 1;
+
+                let Binding::Fragment { kind, trees } = bindings.get(name.text())? else {
+                    return None;
+                };
+
+                // keep the precedence of expressions, like `$a * 2` with `$a = 1 + 1`
+                if *kind == FragmentKind::Expr && trees.len() > 1 {
+                    let position = *trees[0].position();
+
+                    out.push(TokenTree::Group {
+                        delimiter: DelimeterType::Parentheses,
+                        open: synthetic_paren(DelimeterSide::Left, position),
+                        trees: trees.clone(),
+                        close: synthetic_paren(DelimeterSide::Right, position),
+                    });
+                } else {
+                    out.extend(trees.iter().cloned());
+                }
+            }
+
+            Some(TokenTree::Group {
+                delimiter: DelimeterType::Parentheses,
+                trees: inner,
+                ..
+            }) => {
+                i += 1;
+
+                let separator = match trees.get(i) {
+                    Some(t @ TokenTree::Token(_)) if repetition_kind(Some(t)).is_none() => {
+                        i += 1;
+                        Some(t)
+                    }
+
+                    _ => None,
+                };
+
+                repetition_kind(trees.get(i))?;
+                i += 1;
+
+                let mut names = vec![];
+                transcriber_names(inner, &mut names);
+
+                let mut count = None;
+                for name in &names {
+                    if let Some(Binding::Repeated(r)) = bindings.get(name) {
+                        match count {
+                            Some(c) if c != r.len() => return None,
+                            _ => count = Some(r.len()),
+                        }
+                    }
+                }
+
+                for n in 0..count? {
+                    if n > 0 {
+                        if let Some(separator) = separator {
+                            out.push(separator.clone());
+                        }
+                    }
+
+                    let mut iteration = bindings.clone();
+                    for name in &names {
+                        if let Some(Binding::Repeated(r)) = bindings.get(name) {
+                            iteration.insert(name, r[n].clone());
+                        }
+                    }
+
+                    transcribe(inner, &iteration, out)?;
+                }
+            }
+
+            // a lone dollar sign is kept as is
+            _ => out.push(TokenTree::Token(dollar.clone())),
+        }
+    }
+
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tokenizer::Tokenizer, Source};
+
+    /// The expanded statements, and the diagnostics with the text they're about.
+    fn expand(text: &str, recursion_limit: usize) -> (String, Vec<(DiagnosticType, &str)>) {
+        let source = Source::new("test", text);
+        let (mut tokenizer_diagnostics, mut parser_diagnostics) = (vec![], vec![]);
+
+        let tokenizer = Tokenizer::new(source, &mut tokenizer_diagnostics);
+        let mut statements = Parser::new(tokenizer, &mut parser_diagnostics, Default::default())
+            .program()
+            .unwrap();
+
+        let mut diagnostics = vec![];
+        let mut expander = Expander::new(&mut diagnostics);
+        expander.recursion_limit = recursion_limit;
+        expander.expand(&mut statements);
+
+        let expanded = statements
+            .iter()
+            .filter(|s| !matches!(s, Statement::Macro(_)))
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let diagnostics = diagnostics
+            .iter()
+            .map(|d| (d.ty().clone(), d.position().text))
+            .collect();
+
+        (expanded, diagnostics)
+    }
+
+    #[test]
+    fn matching_rules() {
+        let (expanded, diagnostics) = expand(
+            "macro double { ($x:expr) => { $x * 2 } } double!(1 + 2);",
+            DEFAULT_RECURSION_LIMIT,
+        );
+        assert_eq!(expanded, "((1 + 2) * 2);");
+        assert_eq!(diagnostics, []);
+
+        // the first rule that matches, and the calls in the expansion
+        let (expanded, diagnostics) = expand(
+            "macro m { (a) => { 1 }; (b) => { m!(a) + 2 } } m!(b);",
+            DEFAULT_RECURSION_LIMIT,
+        );
+        assert_eq!(expanded, "(1 + 2);");
+        assert_eq!(diagnostics, []);
+
+        let (expanded, diagnostics) = expand(
+            "macro sum { ($($x:expr),*) => { 0 $(+ $x)* } } sum!(1, 2, 3);",
+            DEFAULT_RECURSION_LIMIT,
+        );
+        assert_eq!(expanded, "(((0 + 1) + 2) + 3);");
+        assert_eq!(diagnostics, []);
+    }
+
+    #[test]
+    fn errors() {
+        for (text, diagnostic) in [
+            (
+                "macro m { (a) => { 1 } } m!(b);",
+                DiagnosticType::NoMatchingMacroRule,
+            ),
+            ("m!(1);", DiagnosticType::UndefinedMacro),
+            (
+                "macro m { () => { m!() } } m!();",
+                DiagnosticType::MacroRecursionLimit,
+            ),
+            // unbound in the transcriber
+            (
+                "macro m { ($x:expr) => { $y } } m!(1);",
+                DiagnosticType::InvalidMacroExpansion,
+            ),
+            // not an expression or statements
+            (
+                "macro m { () => { 1 + } } m!();",
+                DiagnosticType::InvalidMacroExpansion,
+            ),
+        ] {
+            let (_, diagnostics) = expand(text, 4);
+            assert_eq!(diagnostics, [(diagnostic, "m")], "{text}");
+        }
+    }
+}
@@ -0,0 +1,185 @@
+//! Wadler style pretty printing documents.
+//!
+//! A [`Doc`] describes text and the places where it can be broken into
+//! lines. A [`Doc::Group`] is printed on a single line when it fits in the
+//! width, otherwise every line break directly in it becomes a newline.
+
+#[derive(Debug, Clone)]
+pub enum Doc {
+    Nil,
+    Text(String),
+
+    /// A space, or a newline when the enclosing group is broken.
+    Line,
+    /// Nothing, or a newline when the enclosing group is broken.
+    SoftLine,
+    /// Always a newline, and breaks every enclosing group.
+    HardLine,
+    /// Nothing, but breaks every enclosing group like a [`Doc::HardLine`].
+    BreakParent,
+
+    Concat(Vec<Doc>),
+    /// Indents the lines started in the document by more columns.
+    Nest(usize, Box<Doc>),
+    Group(Box<Doc>),
+
+    /// Chooses a document depending on whether the enclosing group is broken.
+    IfBreak {
+        broken: Box<Doc>,
+        flat: Box<Doc>,
+    },
+}
+
+impl Doc {
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::Text(text.into())
+    }
+
+    pub fn concat(docs: impl IntoIterator<Item = Doc>) -> Self {
+        Self::Concat(docs.into_iter().collect())
+    }
+
+    pub fn nest(indent: usize, doc: Doc) -> Self {
+        Self::Nest(indent, Box::new(doc))
+    }
+
+    pub fn group(doc: Doc) -> Self {
+        Self::Group(Box::new(doc))
+    }
+
+    pub fn if_break(broken: Doc, flat: Doc) -> Self {
+        Self::IfBreak {
+            broken: Box::new(broken),
+            flat: Box::new(flat),
+        }
+    }
+
+    /// Renders the document, breaking groups that don't fit in `width` columns.
+    pub fn pretty(&self, width: usize) -> String {
+        let mut out = String::new();
+        let mut column = 0;
+
+        let mut stack = vec![(0, Mode::Break, self)];
+        while let Some((indent, mode, doc)) = stack.pop() {
+            match doc {
+                Doc::Nil | Doc::BreakParent => (),
+                Doc::Text(text) => {
+                    out.push_str(text);
+                    column += text.chars().count();
+                }
+
+                Doc::Line | Doc::SoftLine if mode == Mode::Flat => {
+                    if matches!(doc, Doc::Line) {
+                        out.push(' ');
+                        column += 1;
+                    }
+                }
+
+                Doc::Line | Doc::SoftLine | Doc::HardLine => {
+                    newline(&mut out, indent);
+                    column = indent;
+                }
+
+                Doc::Concat(docs) => {
+                    for d in docs.iter().rev() {
+                        stack.push((indent, mode, d));
+                    }
+                }
+
+                Doc::Nest(more, doc) => stack.push((indent + more, mode, doc)),
+
+                Doc::Group(doc) => {
+                    let mode = match mode {
+                        Mode::Flat => Mode::Flat,
+                        Mode::Break if fits(width.saturating_sub(column), doc, &stack) => {
+                            Mode::Flat
+                        }
+                        Mode::Break => Mode::Break,
+                    };
+
+                    stack.push((indent, mode, doc));
+                }
+
+                Doc::IfBreak { broken, flat } => match mode {
+                    Mode::Flat => stack.push((indent, mode, flat)),
+                    Mode::Break => stack.push((indent, mode, broken)),
+                },
+            }
+        }
+
+        out
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+/// Starts a line, without leaving trailing whitespace on the previous one.
+fn newline(out: &mut String, indent: usize) {
+    let trimmed = out.trim_end_matches(' ').len();
+    out.truncate(trimmed);
+
+    out.push('\n');
+    out.extend(std::iter::repeat_n(' ', indent));
+}
+
+/// Whether `doc` fits on the rest of the line when printed flat, together
+/// with what follows it up to the next possible line break.
+fn fits(mut width: usize, doc: &Doc, rest: &[(usize, Mode, &Doc)]) -> bool {
+    let mut rest = rest.iter().rev();
+    let mut stack = vec![(Mode::Flat, doc)];
+
+    loop {
+        let (mode, doc) = match stack.pop() {
+            Some(d) => d,
+            None => match rest.next() {
+                Some(&(_, mode, doc)) => (mode, doc),
+                None => return true,
+            },
+        };
+
+        match doc {
+            Doc::Nil => (),
+            Doc::Text(text) => {
+                let len = text.chars().count();
+                if len > width {
+                    return false;
+                }
+
+                width -= len;
+            }
+
+            Doc::Line | Doc::SoftLine if mode == Mode::Flat => {
+                if matches!(doc, Doc::Line) {
+                    if width == 0 {
+                        return false;
+                    }
+
+                    width -= 1;
+                }
+            }
+
+            // the line ends here in the document after the group
+            Doc::Line | Doc::SoftLine => return true,
+            Doc::HardLine => return mode == Mode::Break,
+            Doc::BreakParent if mode == Mode::Flat => return false,
+            Doc::BreakParent => (),
+
+            Doc::Concat(docs) => {
+                for d in docs.iter().rev() {
+                    stack.push((mode, d));
+                }
+            }
+
+            Doc::Nest(_, doc) | Doc::Group(doc) => stack.push((mode, doc)),
+
+            Doc::IfBreak { broken, flat } => match mode {
+                Mode::Flat => stack.push((mode, flat)),
+                Mode::Break => stack.push((mode, broken)),
+            },
+        }
+    }
+}
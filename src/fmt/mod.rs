@@ -0,0 +1,841 @@
+//! Source code formatting.
+//!
+//! Formats the concrete syntax tree rather than the AST, so comments are kept
+//! where they were. Spacing between tokens is normalized, statements get a
+//! line each, and long argument lists and operator chains are broken to fit
+//! in the line width with the [`doc`] algebra.
+
+pub mod doc;
+
+use crate::{
+    cst::{
+        self,
+        red::{SyntaxElement, SyntaxNode, SyntaxToken},
+        NodeKind,
+    },
+    dialect::Dialect,
+    parser::ParserError,
+    tokenizer::{
+        Comment, Delimeter, DelimeterSide, DelimeterType, Keyword, Operator, Punctuation,
+        TokenType as Ty,
+    },
+    Diagnostic, Source,
+};
+
+use self::doc::Doc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// The column lines are broken at, when they can be.
+    pub width: usize,
+    /// Spaces per level of indentation.
+    pub indent: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            width: 100,
+            indent: 4,
+        }
+    }
+}
+
+/// Parses and formats a whole source.
+pub fn format<'s, D: Extend<Diagnostic<'s>>>(
+    source: Source<'_, 's>,
+    dialect: &Dialect,
+    options: FormatOptions,
+    diagnostics: &mut D,
+) -> Result<String, ParserError> {
+    let (root, _) = cst::parse(source, dialect, diagnostics)?;
+    Ok(Formatter::new(options).format(&root))
+}
+
+/// Whether formatting the source wouldn't change it, for `--check`.
+pub fn is_formatted<'s, D: Extend<Diagnostic<'s>>>(
+    source: Source<'_, 's>,
+    dialect: &Dialect,
+    options: FormatOptions,
+    diagnostics: &mut D,
+) -> Result<bool, ParserError> {
+    Ok(format(source, dialect, options, diagnostics)? == source.text())
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Formatter {
+    options: FormatOptions,
+}
+
+impl Formatter {
+    pub fn new(options: FormatOptions) -> Self {
+        Self { options }
+    }
+
+    pub fn options(&self) -> &FormatOptions {
+        &self.options
+    }
+
+    /// Formats a tree, usually a whole program.
+    pub fn format(&self, node: &SyntaxNode<'_>) -> String {
+        let mut out = self.doc(node).pretty(self.options.width);
+
+        out.truncate(out.trim_end().len());
+        if !out.is_empty() {
+            out.push('\n');
+        }
+
+        out
+    }
+
+    /// The document of a node.
+    pub fn doc(&self, node: &SyntaxNode<'_>) -> Doc {
+        let elements = node.children_with_tokens();
+
+        match node.kind() {
+            NodeKind::Program => self.statements(&elements),
+            NodeKind::Block => self.block(&elements),
+
+            NodeKind::InfixExpr | NodeKind::CustomInfixExpr => {
+                let (first, rest) = self.infix_chain(node);
+
+                let rest = rest
+                    .into_iter()
+                    .flat_map(|(op, rhs)| [Doc::text(" "), Doc::text(op), Doc::Line, rhs]);
+
+                Doc::group(Doc::concat([
+                    first,
+                    Doc::nest(self.options.indent, Doc::concat(rest)),
+                ]))
+            }
+
+            NodeKind::CallExpr => self.list(node.kind(), &elements, DelimeterType::Parentheses),
+            NodeKind::ArrayExpr => self.list(node.kind(), &elements, DelimeterType::Square),
+            NodeKind::MapExpr | NodeKind::StructExpr => {
+                self.list(node.kind(), &elements, DelimeterType::Curly)
+            }
+
+            NodeKind::MatchExpr => self.match_expr(&elements),
+
+            // token trees and operator symbols, where spacing can matter
+            NodeKind::MacroDefinition | NodeKind::MacroCallExpr | NodeKind::FixityDeclaration => {
+                self.verbatim(&elements)
+            }
+
+            kind => self.join(kind, &elements),
+        }
+    }
+
+    /// Statements and the comments between them, each on its own line, with
+    /// at most one blank line kept between them.
+    fn statements(&self, elements: &[SyntaxElement<'_>]) -> Doc {
+        let mut docs = vec![];
+        let mut newlines = 0;
+        let mut first = true;
+
+        for e in elements {
+            let separator = match newlines {
+                _ if first => Doc::Nil,
+                0 | 1 => Doc::HardLine,
+                _ => Doc::concat([Doc::HardLine, Doc::HardLine]),
+            };
+
+            match e {
+                SyntaxElement::Token(t) if is_space(t) => {
+                    newlines += t.text().matches('\n').count();
+                    continue;
+                }
+
+                // a comment after a statement on the same line stays there
+                SyntaxElement::Token(t) if is_comment(t) => match newlines {
+                    0 if !first => docs.extend([Doc::text(" "), Doc::text(t.text())]),
+                    _ => docs.extend([separator, Doc::text(t.text())]),
+                },
+
+                // a stray token, like the semicolon of an empty statement
+                SyntaxElement::Token(t) => docs.push(Doc::text(t.text())),
+
+                SyntaxElement::Node(n) => docs.extend([separator, self.doc(n)]),
+            }
+
+            newlines = 0;
+            first = false;
+        }
+
+        Doc::concat(docs)
+    }
+
+    /// A block in curly braces, or an indented one.
+    fn block(&self, elements: &[SyntaxElement<'_>]) -> Doc {
+        let braced = matches!(
+            first_significant(elements),
+            Some(Ty::Delimeter(Delimeter {
+                ty: DelimeterType::Curly,
+                side: DelimeterSide::Left,
+            }))
+        );
+
+        let (open, inner, close) = match braced {
+            true => split_delimited(elements, DelimeterType::Curly),
+
+            // the colon of `if x:`, if there is one
+            false => match elements.iter().position(|e| !is_trivia(e)) {
+                Some(i) if is_punctuation(&elements[i], Punctuation::Colon) => {
+                    (&elements[..=i], &elements[i + 1..], &elements[..0])
+                }
+                _ => (&elements[..0], elements, &elements[..0]),
+            },
+        };
+
+        let text = |elements: &[SyntaxElement<'_>]| {
+            let tokens = elements.iter().filter_map(element_token);
+            Doc::text(
+                tokens
+                    .filter(|t| !t.is_trivia())
+                    .map(|t| t.text())
+                    .collect::<String>(),
+            )
+        };
+        let (open, close) = (text(open), text(close));
+
+        if braced && inner.iter().all(is_whitespace) {
+            return Doc::concat([open, close]);
+        }
+
+        let body = Doc::nest(
+            self.options.indent,
+            Doc::concat([Doc::HardLine, self.statements(inner)]),
+        );
+
+        match braced {
+            true => Doc::concat([open, body, Doc::HardLine, close]),
+            false => Doc::concat([open, body]),
+        }
+    }
+
+    /// Flattens a chain of the same infix operator, like `a + b + c`, into
+    /// its first operand and the operators with the operands after them.
+    fn infix_chain(&self, node: &SyntaxNode<'_>) -> (Doc, Vec<(String, Doc)>) {
+        let elements = node.children_with_tokens();
+        let op = infix_operator(node);
+
+        let lhs = elements
+            .iter()
+            .position(|e| matches!(e, SyntaxElement::Node(_)));
+        let rhs = elements
+            .iter()
+            .rposition(|e| matches!(e, SyntaxElement::Node(_)));
+
+        let (Some(lhs), Some(rhs)) = (lhs, rhs) else {
+            return (self.join(node.kind(), &elements), vec![]);
+        };
+
+        let SyntaxElement::Node(lhs_node) = &elements[lhs] else {
+            unreachable!()
+        };
+
+        let (first, mut rest) = match lhs_node.kind() {
+            NodeKind::InfixExpr | NodeKind::CustomInfixExpr if infix_operator(lhs_node) == op => {
+                self.infix_chain(lhs_node)
+            }
+
+            _ => (self.doc(lhs_node), vec![]),
+        };
+
+        // comments around the operator go before the right operand
+        let comments = elements[lhs + 1..rhs].iter().filter_map(element_token);
+        let mut operand: Vec<Doc> = comments
+            .filter(is_comment)
+            .flat_map(|t| [Doc::text(t.text()), comment_break(&t)])
+            .collect();
+        operand.push(self.join(node.kind(), &elements[rhs..]));
+
+        rest.push((op, Doc::concat(operand)));
+        (first, rest)
+    }
+
+    /// A delimited list separated by commas, like arguments, broken into a
+    /// line for each item with a trailing comma when it's too long.
+    fn list(
+        &self,
+        kind: NodeKind,
+        elements: &[SyntaxElement<'_>],
+        delimiter: DelimeterType,
+    ) -> Doc {
+        let (open, inner, close) = split_delimited(elements, delimiter);
+        let open = self.join(kind, open);
+        let close = self.join(kind, close);
+
+        if inner.iter().all(is_whitespace) {
+            return Doc::concat([open, close]);
+        }
+
+        let items = split_items(inner);
+
+        // the base of a struct literal can't be followed by a comma
+        let last_is_base = items.last().is_some_and(|(item, _)| {
+            matches!(
+                first_significant(item),
+                Some(Ty::Punctuation(Punctuation::DoubleDot))
+            )
+        });
+        let trailing_comma = match last_is_base {
+            true => Doc::Nil,
+            false => Doc::if_break(Doc::text(","), Doc::Nil),
+        };
+
+        let mut docs = vec![];
+        for (i, (item, comments)) in items.iter().enumerate() {
+            if i > 0 {
+                docs.push(Doc::Line);
+            }
+
+            docs.push(self.join(kind, item));
+            docs.push(match i + 1 == items.len() {
+                true => trailing_comma.clone(),
+                false => Doc::text(","),
+            });
+
+            for c in comments {
+                docs.push(Doc::text(" "));
+                docs.push(Doc::text(c.text()));
+
+                if is_line_comment(c) {
+                    docs.push(Doc::BreakParent);
+                }
+            }
+        }
+
+        // struct literals and maps have spaces inside the braces
+        let line = match delimiter {
+            DelimeterType::Curly => Doc::Line,
+            _ => Doc::SoftLine,
+        };
+
+        Doc::concat([
+            open,
+            Doc::group(Doc::concat([
+                Doc::nest(
+                    self.options.indent,
+                    Doc::concat([line.clone(), Doc::concat(docs)]),
+                ),
+                line,
+                close,
+            ])),
+        ])
+    }
+
+    /// A match with an arm on each line.
+    fn match_expr(&self, elements: &[SyntaxElement<'_>]) -> Doc {
+        let (open, inner, close) = split_delimited(elements, DelimeterType::Curly);
+        let open = self.join(NodeKind::MatchExpr, open);
+        let close = self.join(NodeKind::MatchExpr, close);
+
+        if inner.iter().all(is_whitespace) {
+            return Doc::concat([open, close]);
+        }
+
+        let mut arms = vec![];
+        for (arm, comments) in split_items(inner) {
+            arms.push(Doc::HardLine);
+            arms.push(self.join(NodeKind::MatchExpr, arm));
+            arms.push(Doc::text(","));
+
+            for c in comments {
+                arms.push(Doc::text(" "));
+                arms.push(Doc::text(c.text()));
+            }
+        }
+
+        Doc::concat([
+            open,
+            Doc::nest(self.options.indent, Doc::concat(arms)),
+            Doc::HardLine,
+            close,
+        ])
+    }
+
+    /// Tokens joined with normalized spacing, nodes formatted recursively.
+    fn join(&self, parent: NodeKind, elements: &[SyntaxElement<'_>]) -> Doc {
+        let mut docs = vec![];
+        let mut spacing = Spacing::Start;
+        let mut newline = false;
+
+        for e in elements {
+            let (first, last) = match e {
+                SyntaxElement::Token(t) if is_space(t) => {
+                    newline |= t.text().contains('\n');
+                    continue;
+                }
+
+                SyntaxElement::Token(t) => (Some(t.kind().clone()), Some(t.kind().clone())),
+                SyntaxElement::Node(n) => {
+                    let tokens: Vec<_> =
+                        n.tokens().into_iter().filter(|t| !t.is_trivia()).collect();
+                    let first = tokens.first().map(|t| t.kind().clone());
+                    let last = tokens.last().map(|t| t.kind().clone());
+                    (first, last)
+                }
+            };
+
+            let indented_block = matches!(e, SyntaxElement::Node(n) if is_indented_block(n));
+
+            match (&spacing, &first) {
+                (Spacing::Start, _) => (),
+                (Spacing::Line, _) => docs.push(Doc::HardLine),
+                (Spacing::KeepLine, _) if newline => docs.push(Doc::HardLine),
+                _ if indented_block => (),
+
+                (Spacing::After(prev), Some(next)) if !space_between(prev, next, parent) => {}
+
+                _ => docs.push(Doc::text(" ")),
+            }
+
+            newline = false;
+
+            match e {
+                SyntaxElement::Token(t) => {
+                    docs.push(Doc::text(t.text()));
+
+                    spacing = match is_comment(t) {
+                        true if is_line_comment(t) => {
+                            docs.push(Doc::HardLine);
+                            Spacing::Start
+                        }
+                        true => Spacing::KeepLine,
+                        false => Spacing::After(t.kind().clone()),
+                    };
+                }
+
+                SyntaxElement::Node(n) => {
+                    docs.push(self.doc(n));
+
+                    spacing = match n.kind() {
+                        _ if ends_with_indented_block(n) => Spacing::Line,
+                        NodeKind::Attribute => Spacing::KeepLine,
+                        _ => match last {
+                            Some(last) => Spacing::After(last),
+                            None => Spacing::KeepLine,
+                        },
+                    };
+                }
+            }
+        }
+
+        Doc::concat(docs)
+    }
+
+    /// Tokens with the spacing of the source, except that indentation is
+    /// normalized.
+    fn verbatim(&self, elements: &[SyntaxElement<'_>]) -> Doc {
+        let mut docs = vec![];
+
+        for e in elements {
+            match e {
+                SyntaxElement::Token(t) if is_space(t) && t.text().contains('\n') => {
+                    docs.push(Doc::HardLine);
+                }
+                SyntaxElement::Token(t) if is_space(t) => docs.push(Doc::text(" ")),
+                SyntaxElement::Token(t) => {
+                    docs.push(Doc::text(t.text()));
+
+                    if is_line_comment(t) {
+                        docs.push(Doc::BreakParent);
+                    }
+                }
+
+                SyntaxElement::Node(n) => docs.push(self.doc(n)),
+            }
+        }
+
+        Doc::concat(docs)
+    }
+}
+
+/// What comes before the next element of [`Formatter::join`].
+enum Spacing {
+    /// The start of the line, nothing to separate from.
+    Start,
+    /// A line break, after an indented block.
+    Line,
+    /// A line break if the source had one, a space otherwise.
+    KeepLine,
+    After(Ty),
+}
+
+/// Whether a space goes between two tokens directly in a `parent` node.
+fn space_between(prev: &Ty, next: &Ty, parent: NodeKind) -> bool {
+    // `<` and `>` are brackets in paths and types
+    let generics = matches!(
+        parent,
+        NodeKind::Type
+            | NodeKind::Pattern
+            | NodeKind::CallExpr
+            | NodeKind::NameExpr
+            | NodeKind::StructExpr
+    );
+
+    match (prev, next) {
+        (
+            _,
+            Ty::Punctuation(
+                Punctuation::Comma
+                | Punctuation::Semicolon
+                | Punctuation::Colon
+                | Punctuation::DoubleColon
+                | Punctuation::Dot
+                | Punctuation::QuestionMark,
+            ),
+        ) => false,
+
+        (
+            Ty::Punctuation(
+                Punctuation::DoubleColon
+                | Punctuation::Dot
+                | Punctuation::HashSymbol
+                | Punctuation::AtSign
+                | Punctuation::Dollar
+                | Punctuation::DoubleDollar,
+            ),
+            _,
+        ) => false,
+
+        (
+            Ty::Delimeter(Delimeter {
+                ty: DelimeterType::Parentheses | DelimeterType::Square,
+                side: DelimeterSide::Left,
+            }),
+            _,
+        ) => false,
+
+        (
+            _,
+            Ty::Delimeter(Delimeter {
+                ty: DelimeterType::Parentheses | DelimeterType::Square,
+                side: DelimeterSide::Right,
+            }),
+        ) => false,
+
+        // ranges, except the base of a struct literal after a comma
+        (
+            Ty::Punctuation(
+                Punctuation::DoubleDot | Punctuation::DoubleDotEquals | Punctuation::TripleDot,
+            ),
+            _,
+        ) => false,
+        (
+            Ty::Punctuation(Punctuation::Comma)
+            | Ty::Delimeter(Delimeter {
+                side: DelimeterSide::Left,
+                ..
+            }),
+            Ty::Punctuation(Punctuation::DoubleDot),
+        ) => true,
+        (
+            _,
+            Ty::Punctuation(
+                Punctuation::DoubleDot | Punctuation::DoubleDotEquals | Punctuation::TripleDot,
+            ),
+        ) => false,
+
+        // calls, indexing and tuple struct patterns
+        (
+            Ty::Identifier
+            | Ty::Keyword(Keyword::Fn | Keyword::SelfValue | Keyword::Super)
+            | Ty::Operator(Operator::GreaterThan | Operator::RightShift | Operator::Bang)
+            | Ty::Delimeter(Delimeter {
+                ty: DelimeterType::Parentheses | DelimeterType::Square,
+                side: DelimeterSide::Right,
+            }),
+            Ty::Delimeter(Delimeter {
+                ty: DelimeterType::Parentheses | DelimeterType::Square,
+                side: DelimeterSide::Left,
+            }),
+        ) => false,
+
+        (Ty::Operator(Operator::LesserThan), _) if generics => false,
+        (_, Ty::Operator(Operator::LesserThan | Operator::GreaterThan | Operator::RightShift))
+            if generics =>
+        {
+            false
+        }
+
+        (Ty::Operator(_), _) if parent == NodeKind::PrefixExpr => false,
+        // negative literal patterns
+        (Ty::Operator(Operator::Minus), Ty::Literal(_)) if parent == NodeKind::Pattern => false,
+        (_, Ty::Operator(_)) if parent == NodeKind::PostfixExpr => false,
+
+        _ => true,
+    }
+}
+
+/// The operator of an infix expression, joined if it's made of several tokens.
+fn infix_operator(node: &SyntaxNode<'_>) -> String {
+    node.child_tokens()
+        .filter(|t| !t.is_trivia())
+        .map(|t| t.text())
+        .collect()
+}
+
+/// Splits the elements of a node at its first opening delimiter of a type
+/// and its last closing one.
+fn split_delimited<'a, 's>(
+    elements: &'a [SyntaxElement<'s>],
+    delimiter: DelimeterType,
+) -> (
+    &'a [SyntaxElement<'s>],
+    &'a [SyntaxElement<'s>],
+    &'a [SyntaxElement<'s>],
+) {
+    let is = |e: &SyntaxElement<'_>, side| {
+        matches!(
+            element_token(e).as_ref().map(SyntaxToken::kind),
+            Some(Ty::Delimeter(d)) if d.ty == delimiter && d.side == side
+        )
+    };
+
+    let open = elements.iter().position(|e| is(e, DelimeterSide::Left));
+    let close = elements.iter().rposition(|e| is(e, DelimeterSide::Right));
+
+    match (open, close) {
+        (Some(open), Some(close)) if open < close => (
+            &elements[..=open],
+            &elements[open + 1..close],
+            &elements[close..],
+        ),
+        _ => (elements, &elements[..0], &elements[..0]),
+    }
+}
+
+/// Splits the inside of a list at its commas, into its items and the
+/// comments after each item and its comma on the same line.
+fn split_items<'a, 's>(
+    inner: &'a [SyntaxElement<'s>],
+) -> Vec<(&'a [SyntaxElement<'s>], Vec<SyntaxToken<'s>>)> {
+    let mut items = vec![];
+    let mut start = 0;
+
+    for (i, e) in inner.iter().enumerate() {
+        if is_punctuation(e, Punctuation::Comma) {
+            items.push((&inner[start..i], vec![]));
+            start = i + 1;
+        }
+    }
+
+    if inner[start..].iter().any(|e| !is_whitespace(e)) {
+        items.push((&inner[start..], vec![]));
+    }
+
+    // move the comments that end an item's line to its end
+    for i in 0..items.len() {
+        let (item, _) = items[i];
+
+        let mut end = item.len();
+        while end > 0 && is_trivia(&item[end - 1]) {
+            end -= 1;
+        }
+
+        let trailing: Vec<_> = item[end..]
+            .iter()
+            .filter_map(element_token)
+            .filter(is_comment)
+            .collect();
+
+        if !trailing.is_empty() {
+            items[i] = (&item[..end], trailing);
+            continue;
+        }
+
+        // a comment after the comma is at the start of the next item
+        let Some(next) = items.get(i + 1).map(|(next, _)| *next) else {
+            continue;
+        };
+
+        let mut split = 0;
+        let mut comments = vec![];
+        for (j, e) in next.iter().enumerate() {
+            match element_token(e) {
+                Some(t) if is_space(&t) && t.text().contains('\n') => break,
+                Some(t) if is_space(&t) => (),
+                Some(t) if is_comment(&t) => {
+                    comments.push(t);
+                    split = j + 1;
+                }
+                _ => break,
+            }
+        }
+
+        if !comments.is_empty() {
+            items[i].1 = comments;
+            items[i + 1].0 = &next[split..];
+        }
+    }
+
+    items
+}
+
+fn is_indented_block(node: &SyntaxNode<'_>) -> bool {
+    node.kind() == NodeKind::Block
+        && !matches!(
+            node.tokens()
+                .into_iter()
+                .find(|t| !t.is_trivia())
+                .map(|t| t.kind().clone()),
+            Some(Ty::Delimeter(Delimeter {
+                ty: DelimeterType::Curly,
+                side: DelimeterSide::Left,
+            }))
+        )
+}
+
+/// Whether the node ends with an indented block, so whatever comes after it
+/// has to go on the next line.
+fn ends_with_indented_block(node: &SyntaxNode<'_>) -> bool {
+    if is_indented_block(node) {
+        return true;
+    }
+
+    let last = node
+        .children_with_tokens()
+        .into_iter()
+        .rev()
+        .find(|e| !is_trivia(e));
+
+    match last {
+        Some(SyntaxElement::Node(n)) => ends_with_indented_block(&n),
+        _ => false,
+    }
+}
+
+fn first_significant(elements: &[SyntaxElement<'_>]) -> Option<Ty> {
+    elements.iter().find_map(|e| match e {
+        SyntaxElement::Token(t) if t.is_trivia() => None,
+        SyntaxElement::Token(t) => Some(t.kind().clone()),
+        SyntaxElement::Node(n) => n
+            .tokens()
+            .into_iter()
+            .find(|t| !t.is_trivia())
+            .map(|t| t.kind().clone()),
+    })
+}
+
+fn element_token<'s>(element: &SyntaxElement<'s>) -> Option<SyntaxToken<'s>> {
+    element.clone().into_token()
+}
+
+fn is_punctuation(element: &SyntaxElement<'_>, punctuation: Punctuation) -> bool {
+    matches!(element, SyntaxElement::Token(t) if *t.kind() == Ty::Punctuation(punctuation))
+}
+
+/// Whitespace and newlines.
+fn is_space(token: &SyntaxToken<'_>) -> bool {
+    matches!(token.kind(), Ty::Whitespace | Ty::Newline)
+}
+
+fn is_whitespace(element: &SyntaxElement<'_>) -> bool {
+    matches!(element, SyntaxElement::Token(t) if is_space(t))
+}
+
+fn is_trivia(element: &SyntaxElement<'_>) -> bool {
+    matches!(element, SyntaxElement::Token(t) if t.is_trivia())
+}
+
+fn is_comment(token: &SyntaxToken<'_>) -> bool {
+    matches!(token.kind(), Ty::Comment(_))
+}
+
+/// Comments running to the end of the line, which must be followed by a break.
+fn is_line_comment(token: &SyntaxToken<'_>) -> bool {
+    matches!(
+        token.kind(),
+        Ty::Comment(Comment::SingleLine | Comment::DocLine)
+    )
+}
+
+fn comment_break(token: &SyntaxToken<'_>) -> Doc {
+    match is_line_comment(token) {
+        true => Doc::HardLine,
+        false => Doc::text(" "),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UNFORMATTED: &str =
+        "let  x=1;// one\n/* two */ let y = x+2 ;\n\n\n\n// three\nf(x, /* four */ y)\n";
+
+    fn formatted(text: &str, width: usize) -> String {
+        let options = FormatOptions {
+            width,
+            ..FormatOptions::default()
+        };
+
+        format(
+            Source::new("test", text),
+            &Dialect::default(),
+            options,
+            &mut vec![],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn idempotence() {
+        for text in [
+            UNFORMATTED,
+            "match x{1=>\"one\",_=>{let a=1;a}}",
+            "let f = (a,b)=>a*(b+1); [f(1,2), f(3, 4)][0]",
+            "if a {b} else if c {d} else {e}",
+        ] {
+            let once = formatted(text, 40);
+            assert_eq!(formatted(&once, 40), once, "{text}");
+        }
+    }
+
+    #[test]
+    fn comments_are_kept() {
+        assert_eq!(
+            formatted(UNFORMATTED, 100),
+            "let x = 1; // one\n/* two */\nlet y = x + 2;\n\n// three\nf(x, /* four */ y)\n"
+        );
+    }
+
+    #[test]
+    fn long_lines_wrap() {
+        let text = "let result = some_function(first_argument, second_argument, third_argument) + another_value * yet_another_value;";
+
+        let wrapped = formatted(text, 40);
+        assert_eq!(
+            wrapped,
+            "let result = some_function(\n    first_argument,\n    second_argument,\n    third_argument,\n) +\n    another_value * yet_another_value;\n"
+        );
+        assert!(wrapped.lines().all(|l| l.len() <= 40), "{wrapped}");
+
+        // only the operator chain is broken at the default width
+        assert_eq!(
+            formatted(text, 100),
+            "let result = some_function(first_argument, second_argument, third_argument) +\n    another_value * yet_another_value;\n"
+        );
+    }
+
+    #[test]
+    fn check() {
+        let is_formatted = |text| {
+            is_formatted(
+                Source::new("test", text),
+                &Dialect::default(),
+                FormatOptions::default(),
+                &mut vec![],
+            )
+            .unwrap()
+        };
+
+        assert!(!is_formatted(UNFORMATTED));
+        let once = formatted(UNFORMATTED, 100);
+        assert!(is_formatted(&once));
+        // without the final line break
+        assert!(!is_formatted("let x = 1;"));
+    }
+}
@@ -0,0 +1,485 @@
+//! Constant folding and algebraic simplification.
+//!
+//! Operators with constant operands are applied with the semantics of
+//! [`eval`], and left in the tree when they would fail at runtime. The
+//! nodes that survive keep their spans, the new ones cover their operands.
+//!
+//! Identities like `a + 0` and the reassociation of `(a + 1) + 2` only apply
+//! when `a` is arithmetic on constants. A variable could be a string, for
+//! which they would hide a runtime error, or a real they would round.
+
+use crate::{
+    eval::{self, Value},
+    parser::{
+        expression::Expression,
+        statement::Statement,
+        visit::{walk_expression, VisitorMut},
+    },
+    tokenizer::{Literal, Operator, Span},
+    units::UnitRegistry,
+    Diagnostic, DiagnosticType, Source,
+};
+
+pub struct Folder<'n, 's, 'd, D: Extend<Diagnostic<'s>>> {
+    source: Source<'n, 's>,
+    diagnostics: &'d mut D,
+    units: UnitRegistry,
+}
+
+impl<'n, 's, 'd, D: Extend<Diagnostic<'s>>> Folder<'n, 's, 'd, D> {
+    /// `source` is the text the statements were parsed from, for the
+    /// positions of the diagnostics.
+    pub fn new(source: Source<'n, 's>, diagnostics: &'d mut D) -> Self {
+        Self::with_units(source, diagnostics, UnitRegistry::standard())
+    }
+
+    /// Folds the quantity literals in the given units.
+    pub fn with_units(
+        source: Source<'n, 's>,
+        diagnostics: &'d mut D,
+        units: &UnitRegistry,
+    ) -> Self {
+        Self {
+            source,
+            diagnostics,
+            units: units.clone(),
+        }
+    }
+
+    pub fn fold(&mut self, statements: &mut [Statement<'s>]) {
+        for s in statements {
+            self.visit_statement(s);
+        }
+    }
+
+    fn report(&mut self, ty: DiagnosticType, span: Span) {
+        let position = self.source.position(span);
+        self.diagnostics.extend([Diagnostic::new(ty, position)]);
+    }
+
+    /// Simplifies an expression whose operands are already simplified.
+    fn simplify(&mut self, expr: Expression<'s>) -> Expression<'s> {
+        match expr {
+            // `--x` without a space is the decrement operator, not this
+            Expression::Prefix {
+                op: Operator::Minus,
+                right,
+                span,
+            } => match *right {
+                Expression::Prefix {
+                    op: Operator::Minus,
+                    right,
+                    ..
+                } => *right,
+
+                // already the simplest form of a negative number
+                right @ Expression::Lit {
+                    value: Literal::Number(..) | Literal::Quantity { .. },
+                    ..
+                } => Expression::Prefix {
+                    op: Operator::Minus,
+                    right: Box::new(right),
+                    span,
+                },
+
+                right => fold_prefix(Operator::Minus, right, span, &self.units),
+            },
+
+            Expression::Prefix { op, right, span } => fold_prefix(op, *right, span, &self.units),
+
+            Expression::Postfix { left, op, span } => {
+                let folded = constant(&left, &self.units)
+                    .and_then(|v| eval::postfix(op, v).ok())
+                    .and_then(|v| constant_expression(v, span));
+
+                folded.unwrap_or(Expression::Postfix { left, op, span })
+            }
+
+            Expression::Infix {
+                left,
+                op,
+                right,
+                span,
+            } => self.infix(*left, op, *right, span),
+
+            e => e,
+        }
+    }
+
+    fn infix(
+        &mut self,
+        left: Expression<'s>,
+        op: Operator,
+        right: Expression<'s>,
+        span: Span,
+    ) -> Expression<'s> {
+        let rebuild = |left, right| Expression::Infix {
+            left: Box::new(left),
+            op,
+            right: Box::new(right),
+            span,
+        };
+
+        if op.is_assignment() {
+            return rebuild(left, right);
+        }
+
+        let (a, b) = (constant(&left, &self.units), constant(&right, &self.units));
+
+        // a real divided by zero is infinite instead
+        if matches!(op, Operator::Slash | Operator::Percent)
+            && b == Some(Value::Integer(0))
+            && !matches!(a, Some(Value::Real(_)))
+        {
+            self.report(DiagnosticType::ConstantDivisionByZero, span);
+            return rebuild(left, right);
+        }
+
+        if let (Some(a), Some(b)) = (a, b) {
+            return match eval::infix(a, op, b)
+                .ok()
+                .and_then(|v| constant_expression(v, span))
+            {
+                Some(folded) => folded,
+                None => rebuild(left, right),
+            };
+        }
+
+        // an integer identity keeps the other operand's value and type, but
+        // on a string it hides the runtime error, so that has to be a number
+        let is = |e: &Expression<'s>, n| matches!(number(e), Some(Value::Integer(i)) if i == n);
+        let number = |e: &Expression<'s>| {
+            is_arithmetic(e, &|v| matches!(v, Value::Integer(_) | Value::Real(_)))
+        };
+        match op {
+            Operator::Plus if is(&right, 0) && number(&left) => return left,
+            Operator::Plus if is(&left, 0) && number(&right) => return right,
+            Operator::Minus if is(&right, 0) && number(&left) => return left,
+            Operator::Star if is(&right, 1) && number(&left) => return left,
+            Operator::Star if is(&left, 1) && number(&right) => return right,
+            Operator::Slash | Operator::DoubleStar if is(&right, 1) && number(&left) => {
+                return left
+            }
+
+            _ => (),
+        }
+
+        let expr = rebuild(left, right);
+        if matches!(op, Operator::Plus | Operator::Star) {
+            reassociate(expr, op)
+        } else {
+            expr
+        }
+    }
+}
+
+impl<'s, D: Extend<Diagnostic<'s>>> VisitorMut<'s> for Folder<'_, 's, '_, D> {
+    fn visit_expression(&mut self, expr: &mut Expression<'s>) {
+        walk_expression(self, expr);
+
+        let placeholder = Expression::Tuple {
+            items: vec![],
+            span: Span::default(),
+        };
+        let e = std::mem::replace(expr, placeholder);
+        *expr = self.simplify(e);
+    }
+}
+
+fn fold_prefix<'s>(
+    op: Operator,
+    right: Expression<'s>,
+    span: Span,
+    units: &UnitRegistry,
+) -> Expression<'s> {
+    let folded = constant(&right, units)
+        .and_then(|v| eval::prefix(op, v).ok())
+        .and_then(|v| constant_expression(v, span));
+
+    folded.unwrap_or(Expression::Prefix {
+        op,
+        right: Box::new(right),
+        span,
+    })
+}
+
+/// The value of a literal or of a negated number literal.
+pub(crate) fn constant<'s>(expr: &Expression<'s>, units: &UnitRegistry) -> Option<Value<'s>> {
+    match expr {
+        Expression::Lit { value, .. } => eval::literal(value, units).ok(),
+        Expression::Prefix {
+            op: Operator::Minus,
+            right,
+            ..
+        } => match right.as_ref() {
+            Expression::Lit {
+                value: value @ (Literal::Number(..) | Literal::Quantity { .. }),
+                ..
+            } => eval::prefix(Operator::Minus, eval::literal(value, units).ok()?).ok(),
+
+            _ => None,
+        },
+
+        _ => None,
+    }
+}
+
+/// The value of a number literal or of a negated one, which doesn't
+/// depend on the units.
+pub(crate) fn number<'s>(expr: &Expression<'s>) -> Option<Value<'s>> {
+    constant(expr, &UnitRegistry::new()).filter(|v| matches!(v, Value::Integer(_) | Value::Real(_)))
+}
+
+/// The expression for a constant, if there is a literal for it.
+pub(crate) fn constant_expression<'s>(value: Value<'s>, span: Span) -> Option<Expression<'s>> {
+    let (literal, negative) = match value {
+        Value::Integer(i) => (Literal::Number(i.unsigned_abs().into(), None), i < 0),
+        Value::Real(r) if r.is_finite() => {
+            (Literal::Number(r.abs().into(), None), r.is_sign_negative())
+        }
+        // in the base unit, `1s + 500ms` is `1.5s`
+        Value::Quantity { value, dimension } if value.is_finite() => (
+            Literal::Quantity {
+                value: value.abs().into(),
+                unit: dimension.base_unit().to_string(),
+            },
+            value.is_sign_negative(),
+        ),
+        Value::String(s) => (Literal::String(s), false),
+        Value::Char(c) => (Literal::Char(c), false),
+
+        _ => return None,
+    };
+
+    let lit = Expression::Lit {
+        value: literal,
+        span,
+    };
+
+    Some(if negative {
+        Expression::Prefix {
+            op: Operator::Minus,
+            right: Box::new(lit),
+            span,
+        }
+    } else {
+        lit
+    })
+}
+
+/// Whether the expression is arithmetic on constants for which `constant`
+/// holds, so it evaluates to a number or fails whatever the variables are.
+fn is_arithmetic(expr: &Expression<'_>, constant: &dyn Fn(&Value<'_>) -> bool) -> bool {
+    match expr {
+        Expression::Prefix {
+            op: Operator::Minus | Operator::Plus,
+            right,
+            ..
+        } if !matches!(right.as_ref(), Expression::Lit { .. }) => is_arithmetic(right, constant),
+
+        Expression::Infix {
+            left,
+            op:
+                Operator::Plus
+                | Operator::Minus
+                | Operator::Star
+                | Operator::Slash
+                | Operator::Percent
+                | Operator::DoubleStar,
+            right,
+            ..
+        } => is_arithmetic(left, constant) && is_arithmetic(right, constant),
+
+        e => number(e).is_some_and(|v| constant(&v)),
+    }
+}
+
+/// The operands of a chain of `op`, like `a`, `b` and `c` in `(a + b) + c`.
+fn operands<'s>(expr: Expression<'s>, op: Operator, out: &mut Vec<Expression<'s>>) {
+    match expr {
+        Expression::Infix {
+            left, op: o, right, ..
+        } if o == op => {
+            operands(*left, op, out);
+            operands(*right, op, out);
+        }
+
+        e => out.push(e),
+    }
+}
+
+/// The integer constants among the operands of a chain of `op`.
+fn integer_constants<'s>(expr: &Expression<'s>, op: Operator, out: &mut Vec<Value<'s>>) {
+    match expr {
+        Expression::Infix {
+            left, op: o, right, ..
+        } if *o == op => {
+            integer_constants(left, op, out);
+            integer_constants(right, op, out);
+        }
+
+        e => out.extend(number(e).filter(|v| matches!(v, Value::Integer(_)))),
+    }
+}
+
+/// Gathers the integer constants of a chain of `+` or `*` into one at its
+/// end, like `(a + 1) + 2` to `a + 3`. The other operands have to be
+/// integers too, reals would round differently and strings fail
+/// differently.
+fn reassociate<'s>(expr: Expression<'s>, op: Operator) -> Expression<'s> {
+    if !is_arithmetic(&expr, &|v| matches!(v, Value::Integer(_))) {
+        return expr;
+    }
+
+    let mut values = vec![];
+    integer_constants(&expr, op, &mut values);
+
+    if values.len() < 2 {
+        return expr;
+    }
+
+    let mut values = values.into_iter();
+    let mut value = values.next().unwrap();
+    for v in values {
+        match eval::infix(value, op, v) {
+            Ok(v) => value = v,
+            Err(_) => return expr,
+        }
+    }
+
+    let mut terms = vec![];
+    operands(expr, op, &mut terms);
+
+    let (constants, others): (Vec<_>, Vec<_>) = terms
+        .into_iter()
+        .partition(|t| matches!(number(t), Some(Value::Integer(_))));
+
+    let join = |left: Expression<'s>, right: Expression<'s>| Expression::Infix {
+        span: left.span().to(right.span()),
+        left: Box::new(left),
+        op,
+        right: Box::new(right),
+    };
+
+    let span = constants
+        .iter()
+        .map(|c| c.span())
+        .reduce(|a, b| a.to(b))
+        .unwrap();
+
+    let identity = match op {
+        Operator::Plus => Value::Integer(0),
+        _ => Value::Integer(1),
+    };
+
+    // an integer always has a literal
+    let folded = constant_expression(value.clone(), span).unwrap();
+
+    let mut others = others.into_iter();
+    let Some(mut result) = others.next() else {
+        return folded;
+    };
+
+    for o in others {
+        result = join(result, o);
+    }
+
+    if value != identity {
+        result = join(result, folded);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, tokenizer::Tokenizer};
+
+    /// The folded statements, one per line, and the diagnostics.
+    fn fold(text: &str) -> (String, Vec<Diagnostic<'_>>) {
+        let source = Source::new("test", text);
+        let (mut tokenizer_diagnostics, mut parser_diagnostics, mut diagnostics) =
+            (vec![], vec![], vec![]);
+
+        let tokenizer = Tokenizer::new(source, &mut tokenizer_diagnostics);
+        let mut statements = Parser::new(tokenizer, &mut parser_diagnostics, Default::default())
+            .program()
+            .unwrap();
+        Folder::new(source, &mut diagnostics).fold(&mut statements);
+
+        let folded = statements
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        (folded, diagnostics)
+    }
+
+    fn folded(text: &str) -> String {
+        fold(text).0
+    }
+
+    #[test]
+    fn constants() {
+        assert_eq!(folded("1 + 2 * 3;"), "7;");
+        assert_eq!(folded("-(2 - 5);"), "3;");
+        assert_eq!(folded("1.5 * 2;"), "3.0;");
+        assert_eq!(folded("\"a\" + \"b\";"), "\"ab\";");
+        assert_eq!(folded("1s + 500ms;"), "1.5s;");
+        assert_eq!(folded("- -x;"), "x;");
+    }
+
+    #[test]
+    fn identities_of_numbers() {
+        assert_eq!(folded("(1 / 0) + 0;"), "(1 / 0);");
+        assert_eq!(folded("1 * (2.5 / 0);"), "(2.5 / 0);");
+        assert_eq!(folded("(1 / 0) * 1;"), "(1 / 0);");
+        assert_eq!(folded("(1 % 0) ** 1;"), "(1 % 0);");
+        assert_eq!(folded("0 + (1 / 0);"), "(1 / 0);");
+    }
+
+    #[test]
+    fn identities_keep_the_type_and_the_errors() {
+        // `x` could be a string, and `x * 1.0` a real
+        assert_eq!(folded("x + 0;"), "(x + 0);");
+        assert_eq!(folded("x * 1.0;"), "(x * 1.0);");
+        assert_eq!(folded("x + 0.0;"), "(x + 0.0);");
+        assert_eq!(folded("\"s\" + 0;"), "(\"s\" + 0);");
+        assert_eq!(folded("(1 / 0) * 1.0;"), "((1 / 0) * 1.0);");
+    }
+
+    #[test]
+    fn reassociation() {
+        assert_eq!(folded("(1 / 0) + 1 + 2;"), "((1 / 0) + 3);");
+        assert_eq!(folded("2 * (1 / 0) * 3;"), "((1 / 0) * 6);");
+        assert_eq!(folded("(1 / 0) + 1 + -1;"), "(1 / 0);");
+
+        // `x` could be a real that rounds differently
+        assert_eq!(folded("x + 1 + 2;"), "((x + 1) + 2);");
+        assert_eq!(
+            folded("(1 / 0) + 9223372036854775807 + 1;"),
+            "(((1 / 0) + 9223372036854775807) + 1);"
+        );
+    }
+
+    #[test]
+    fn division_by_zero() {
+        let (folded, diagnostics) = fold("1 / 0; x % 0; 1.0 / 0; 1 / 2;");
+
+        assert_eq!(folded, "(1 / 0);\n(x % 0);\n(1.0 / 0);\n0;");
+
+        let reported: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.ty().clone(), d.position().text))
+            .collect();
+        assert!(matches!(
+            reported.as_slice(),
+            [
+                (DiagnosticType::ConstantDivisionByZero, "1 / 0"),
+                (DiagnosticType::ConstantDivisionByZero, "x % 0"),
+            ]
+        ));
+    }
+}
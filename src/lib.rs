@@ -0,0 +1,155 @@
+//! Tokenizing, parsing and running a small expression language, with the
+//! passes over its syntax tree: macro expansion, constant folding, name
+//! resolution, type checking, formatting and a bytecode virtual machine.
+
+use tokenizer::{Span, TokenPosition};
+
+pub mod cst;
+pub mod dialect;
+pub mod diff;
+pub mod eval;
+pub mod expand;
+pub mod fmt;
+pub mod fold;
+pub mod parser;
+pub mod resolve;
+pub mod tokenizer;
+pub mod typeck;
+pub mod units;
+pub mod vm;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Source<'n, 's> {
+    pub(crate) name: &'n str,
+    pub(crate) text: &'s str,
+}
+
+impl<'n, 's> Source<'n, 's> {
+    pub fn new(name: &'n str, text: &'s str) -> Self {
+        Self { name, text }
+    }
+
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    pub fn text(&self) -> &str {
+        self.text
+    }
+
+    /// The span of a slice of the text, like the name in a binding pattern.
+    pub fn span_of(&self, slice: &str) -> Option<Span> {
+        let start = (slice.as_ptr() as usize).checked_sub(self.text.as_ptr() as usize)?;
+
+        (start + slice.len() <= self.text.len()).then(|| Span::new(start, start + slice.len()))
+    }
+
+    /// The position of the text a span covers, for diagnostics about syntax
+    /// found after parsing.
+    pub fn position(&self, span: Span) -> TokenPosition<'s> {
+        let before = &self.text[..span.start];
+
+        TokenPosition {
+            absolute_position: span.start,
+            line: before.matches('\n').count() + 1,
+            column: before.len() - before.rfind('\n').map_or(0, |n| n + 1) + 1,
+            text: &self.text[span.range()],
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic<'s> {
+    ty: DiagnosticType,
+    position: TokenPosition<'s>,
+}
+impl<'s> Diagnostic<'s> {
+    pub fn new(ty: DiagnosticType, position: TokenPosition<'s>) -> Self {
+        Self { ty, position }
+    }
+
+    pub fn ty(&self) -> &DiagnosticType {
+        &self.ty
+    }
+
+    pub fn position(&self) -> &TokenPosition<'s> {
+        &self.position
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum DiagnosticLevel {
+    Debug,
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiagnosticType {
+    UnclosedMultilineComment,
+    UnreachableMatchArm,
+    /// A line break ended a statement, but the next line could continue it.
+    AmbiguousLineBreak,
+    /// A doc comment not followed by a statement it could document.
+    UnusedDocComment,
+    /// Tabs and spaces in the indentation of the same line.
+    MixedIndentation,
+    /// The indentation doesn't match any enclosing level, or doesn't extend it.
+    InconsistentIndentation,
+    UnknownAttribute,
+    UndefinedMacro,
+    NoMatchingMacroRule,
+    MacroRecursionLimit,
+    InvalidMacroExpansion,
+    /// An integer division or remainder by a constant zero.
+    ConstantDivisionByZero,
+    UnresolvedName,
+    /// A name exported by more than one of the imported modules.
+    AmbiguousImport,
+    /// A binding hiding a variable of an enclosing scope.
+    ShadowedName,
+    /// The type found at the diagnostic's position isn't the one expected,
+    /// because of the expression at `expected_at` if any.
+    TypeMismatch {
+        expected: String,
+        found: String,
+        expected_at: Option<Span>,
+    },
+    /// A type that would have to contain itself, like a function taking itself.
+    InfiniteType,
+    /// A number literal that isn't a value of its suffix's type, like
+    /// `300u8` or `2.5i32`.
+    LiteralOutOfRange,
+    /// A number followed by a name that isn't a known unit, like `5parsec`.
+    UnknownUnit,
+}
+
+impl DiagnosticType {
+    pub fn level(&self) -> DiagnosticLevel {
+        match self {
+            DiagnosticType::UnclosedMultilineComment => DiagnosticLevel::Info,
+            DiagnosticType::UnreachableMatchArm => DiagnosticLevel::Warning,
+            DiagnosticType::AmbiguousLineBreak => DiagnosticLevel::Warning,
+            DiagnosticType::UnusedDocComment => DiagnosticLevel::Warning,
+            DiagnosticType::MixedIndentation => DiagnosticLevel::Warning,
+            DiagnosticType::InconsistentIndentation => DiagnosticLevel::Error,
+            DiagnosticType::UnknownAttribute => DiagnosticLevel::Warning,
+            DiagnosticType::UndefinedMacro
+            | DiagnosticType::NoMatchingMacroRule
+            | DiagnosticType::MacroRecursionLimit
+            | DiagnosticType::InvalidMacroExpansion => DiagnosticLevel::Error,
+            DiagnosticType::ConstantDivisionByZero => DiagnosticLevel::Warning,
+            DiagnosticType::UnresolvedName | DiagnosticType::AmbiguousImport => {
+                DiagnosticLevel::Error
+            }
+            DiagnosticType::ShadowedName => DiagnosticLevel::Warning,
+            DiagnosticType::TypeMismatch { .. } | DiagnosticType::InfiniteType => {
+                DiagnosticLevel::Error
+            }
+            DiagnosticType::LiteralOutOfRange | DiagnosticType::UnknownUnit => {
+                DiagnosticLevel::Error
+            }
+        }
+    }
+}
@@ -1,43 +1,186 @@
-use tokenizer::TokenPosition;
-
-use crate::tokenizer::Tokenizer;
-
-pub mod parser;
-pub mod tokenizer;
+use parsing::{
+    cst, dialect, diff, eval, expand, fmt, fold, parser, resolve,
+    tokenizer::{self, Tokenizer},
+    typeck, vm, Source,
+};
 
 fn main() -> anyhow::Result<()> {
-    let file = std::env::args()
-        .nth(1)
+    // `--fmt` prints the formatted file, `--check` fails if it isn't formatted,
+    // `--fold` simplifies the constant expressions, `--eval` runs the program
+    // after printing it, `--vm` runs it compiled and prints the bytecode,
+    // `--resolve` reports the names without a definition, `--typeck` infers
+    // the type of the program, `--diff=x` prints the derivatives of the
+    // expressions with respect to `x`
+    let mut format = false;
+    let mut check = false;
+    let mut evaluate = false;
+    let mut simplify = false;
+    let mut bytecode = false;
+    let mut resolve = false;
+    let mut typecheck = false;
+    let mut derivative = None;
+    let mut args = vec![];
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--fmt" => format = true,
+            "--check" => check = true,
+            "--eval" => evaluate = true,
+            "--fold" => simplify = true,
+            "--vm" => bytecode = true,
+            "--resolve" => resolve = true,
+            "--typeck" => typecheck = true,
+            _ => match arg.strip_prefix("--diff=") {
+                Some(var) => derivative = Some(var.to_string()),
+                None => args.push(arg),
+            },
+        }
+    }
+
+    let file = args
+        .first()
+        .cloned()
         .unwrap_or_else(|| "test/01.txt".to_string());
+    let dialect = match args.get(1) {
+        Some(name) => dialect::Dialect::preset(name).ok_or_else(|| {
+            anyhow::anyhow!(
+                "unknown dialect {name}, expected one of {:?}",
+                dialect::Dialect::PRESETS
+            )
+        })?,
+        None => dialect::Dialect::default(),
+    };
     let text = std::fs::read_to_string(&file)?;
     let source = Source::new(&file, &text);
 
     // print_tokens(source);
+    // print_cst(source, &dialect);
+
+    if check {
+        if !fmt::is_formatted(source, &dialect, fmt::FormatOptions::default(), &mut vec![])? {
+            eprintln!("{file} is not formatted");
+            std::process::exit(1);
+        }
+
+        return Ok(());
+    }
+
+    if format {
+        let formatted = fmt::format(source, &dialect, fmt::FormatOptions::default(), &mut vec![])?;
+        print!("{formatted}");
+
+        return Ok(());
+    }
 
     let mut parser_diagnostics = vec![];
     let mut tokenizer_diagnostics = vec![];
-    let tokenizer = Tokenizer::new(source, &mut tokenizer_diagnostics);
-    let mut parser = parser::Parser::new(tokenizer, &mut parser_diagnostics);
-
-    match parser.expr() {
-        Ok(expr) => println!("\nparsed: {expr}\n"),
+    let mut expansion_diagnostics = vec![];
+    let mut fold_diagnostics = vec![];
+    let mut resolve_diagnostics = vec![];
+    let mut typeck_diagnostics = vec![];
+    let tokenizer = Tokenizer::with_dialect(source, &mut tokenizer_diagnostics, &dialect);
+    let mut parser = parser::Parser::with_dialect(tokenizer, &mut parser_diagnostics, &dialect);
+
+    match parser.program() {
+        Ok(mut statements) => {
+            expand::Expander::new(&mut expansion_diagnostics).expand(&mut statements);
+
+            if simplify {
+                fold::Folder::with_units(source, &mut fold_diagnostics, &dialect.units)
+                    .fold(&mut statements);
+            }
+
+            if resolve {
+                let mut resolver = resolve::Resolver::new(source, &mut resolve_diagnostics);
+                resolver.define_environment(&eval::Environment::with_prelude());
+                resolver.resolve(&mut statements);
+            }
+
+            println!();
+            for s in &statements {
+                println!("parsed: {s}");
+            }
+            println!();
+
+            if typecheck {
+                let mut checker =
+                    typeck::Checker::with_units(source, &mut typeck_diagnostics, &dialect.units);
+                checker.define_builtins();
+                println!("type: {}\n", checker.check(&statements));
+            }
+
+            if let Some(var) = &derivative {
+                let printer = parser::print::Printer::new(&dialect.operators);
+
+                for s in &statements {
+                    let parser::statement::Statement::Expression { expr, .. } = s.inner() else {
+                        continue;
+                    };
+
+                    match diff::diff(expr, var) {
+                        Ok(d) => println!("d/d{var}: {}", printer.print(&d)),
+                        Err(e) => println!("d/d{var}: {e}"),
+                    }
+                }
+                println!();
+            }
+
+            if evaluate {
+                let env = eval::Environment::with_units(&dialect.units);
+                env.define_prelude();
+
+                match eval::run(&statements, &env) {
+                    Ok(value) => println!("value: {value}\n"),
+                    Err(e) => println!("runtime error: {e}: {}\n", &text[e.span().range()]),
+                }
+            }
+
+            if bytecode {
+                match vm::Compiler::with_units(&dialect.units).compile_program(&statements) {
+                    Ok(chunk) => {
+                        print!("{chunk}");
+
+                        let env = eval::Environment::with_units(&dialect.units);
+                        env.define_prelude();
+
+                        match vm::Vm::new().run(&chunk, &env) {
+                            Ok(value) => println!("value: {value}\n"),
+                            Err(e) => {
+                                println!("runtime error: {e}: {}\n", &text[e.span().range()])
+                            }
+                        }
+                    }
+                    Err(e) => println!("compile error: {e}\n"),
+                }
+            }
+        }
         Err(e) => println!("\nparser error: {e}\n"),
     }
 
     println!("tokenizer diagnostics: {:?}", tokenizer_diagnostics);
     println!("parser diagnostics: {:?}", parser_diagnostics);
+    println!("expansion diagnostics: {:?}", expansion_diagnostics);
+    println!("fold diagnostics: {:?}", fold_diagnostics);
+    println!("resolve diagnostics: {:?}", resolve_diagnostics);
+    println!("typeck diagnostics: {:?}", typeck_diagnostics);
 
     Ok(())
 }
 
+pub fn print_cst(source: Source<'_, '_>, dialect: &dialect::Dialect) {
+    match cst::parse(source, dialect, &mut vec![]) {
+        Ok((root, _)) => {
+            print!("{}", root.debug_tree());
+            assert_eq!(root.to_string(), source.text(), "the tree is lossless");
+        }
+        Err(e) => println!("parser error: {e}"),
+    }
+}
+
 pub fn print_tokens(source: Source<'_, '_>) {
     for t in Tokenizer::new(source, &mut vec![]) {
         let comment_or_whitespace = matches!(
-            t,
-            Ok(tokenizer::Token {
-                ty: tokenizer::TokenType::Whitespace,
-                ..
-            })
+            t.as_ref().map(tokenizer::Token::ty),
+            Ok(tokenizer::TokenType::Whitespace)
         );
 
         if !comment_or_whitespace {
@@ -45,63 +188,3 @@ pub fn print_tokens(source: Source<'_, '_>) {
         }
     }
 }
-
-#[derive(Debug, Clone, Copy)]
-pub struct Source<'n, 's> {
-    pub(crate) name: &'n str,
-    pub(crate) text: &'s str,
-}
-
-impl<'n, 's> Source<'n, 's> {
-    pub fn new(name: &'n str, text: &'s str) -> Self {
-        Self { name, text }
-    }
-
-    pub fn name(&self) -> &str {
-        self.name
-    }
-
-    pub fn text(&self) -> &str {
-        self.text
-    }
-}
-
-#[derive(Debug, Clone)]
-pub struct Diagnostic<'s> {
-    ty: DiagnosticType,
-    position: TokenPosition<'s>,
-}
-impl<'s> Diagnostic<'s> {
-    pub fn new(ty: DiagnosticType, position: TokenPosition<'s>) -> Self {
-        Self { ty, position }
-    }
-
-    pub fn ty(&self) -> &DiagnosticType {
-        &self.ty
-    }
-
-    pub fn position(&self) -> &TokenPosition<'s> {
-        &self.position
-    }
-}
-
-#[derive(Debug, Clone, Copy)]
-pub enum DiagnosticLevel {
-    Debug,
-    Info,
-    Warning,
-    Error,
-}
-
-#[derive(Debug, Clone)]
-pub enum DiagnosticType {
-    UnclosedMultilineComment,
-}
-
-impl DiagnosticType {
-    pub fn level(&self) -> DiagnosticLevel {
-        match self {
-            DiagnosticType::UnclosedMultilineComment => DiagnosticLevel::Info,
-        }
-    }
-}
@@ -0,0 +1,100 @@
+use std::{collections::HashSet, fmt::Display};
+
+use crate::tokenizer::TokenPosition;
+
+use super::expression::{Expression, Path};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeStyle {
+    /// `#[name(args)]`
+    Hash,
+
+    /// `@name(args)`
+    At,
+}
+
+#[derive(Debug, Clone)]
+pub struct Attribute<'s> {
+    pub(crate) style: AttributeStyle,
+    pub(crate) path: Path<'s>,
+    pub(crate) arguments: Vec<Expression<'s>>,
+
+    pub(crate) position: TokenPosition<'s>,
+}
+
+impl<'s> Attribute<'s> {
+    pub fn style(&self) -> AttributeStyle {
+        self.style
+    }
+
+    pub fn path(&self) -> &Path<'s> {
+        &self.path
+    }
+
+    pub fn arguments(&self) -> &[Expression<'s>] {
+        &self.arguments
+    }
+
+    pub fn position(&self) -> &TokenPosition<'s> {
+        &self.position
+    }
+}
+
+impl PartialEq for Attribute<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.style == other.style && self.path == other.path && self.arguments == other.arguments
+    }
+}
+
+impl Display for Attribute<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.style {
+            AttributeStyle::Hash => write!(f, "#[{}", self.path)?,
+            AttributeStyle::At => write!(f, "@{}", self.path)?,
+        }
+
+        if !self.arguments.is_empty() {
+            write!(f, "(")?;
+
+            let mut args = self.arguments.iter();
+            if let Some(a) = args.next() {
+                write!(f, "{a}")?;
+            }
+
+            for a in args {
+                write!(f, ", {a}")?;
+            }
+
+            write!(f, ")")?;
+        }
+
+        if self.style == AttributeStyle::Hash {
+            write!(f, "]")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The attribute names a tool understands. When the parser is given one,
+/// every other attribute is reported with [`crate::DiagnosticType::UnknownAttribute`].
+#[derive(Debug, Clone, Default)]
+pub struct AttributeRegistry {
+    names: HashSet<String>,
+}
+
+impl AttributeRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a name, which is the attribute's path as written, e.g. `tool::skip`.
+    pub fn register(&mut self, name: impl Into<String>) -> &mut Self {
+        self.names.insert(name.into());
+        self
+    }
+
+    pub fn is_known(&self, path: &Path<'_>) -> bool {
+        self.names.contains(&path.to_string())
+    }
+}
@@ -0,0 +1,76 @@
+use std::fmt::Display;
+
+use crate::tokenizer::{Comment, Token, TokenPosition, TokenType};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocStyle {
+    /// `/// text`
+    Line,
+
+    /// `/** text */`
+    Block,
+}
+
+/// A documentation comment, attached to the statement after it.
+#[derive(Debug, Clone)]
+pub struct DocComment<'s> {
+    pub(crate) style: DocStyle,
+
+    /// The whole comment, markers included.
+    pub(crate) position: TokenPosition<'s>,
+}
+
+impl<'s> DocComment<'s> {
+    /// `None` if the token is not a doc comment.
+    pub fn from_token(token: &Token<'s>) -> Option<Self> {
+        let style = match token.ty {
+            TokenType::Comment(Comment::DocLine) => DocStyle::Line,
+            TokenType::Comment(Comment::DocBlock) => DocStyle::Block,
+
+            _ => return None,
+        };
+
+        Some(Self {
+            style,
+            position: token.position,
+        })
+    }
+
+    pub fn style(&self) -> DocStyle {
+        self.style
+    }
+
+    pub fn position(&self) -> &TokenPosition<'s> {
+        &self.position
+    }
+
+    /// The documentation without the comment markers, a single space after
+    /// `///` is left out too.
+    pub fn content(&self) -> &'s str {
+        let text = self.position.text;
+
+        match self.style {
+            DocStyle::Line => {
+                let text = &text[3..];
+                text.strip_prefix(' ').unwrap_or(text)
+            }
+
+            DocStyle::Block => {
+                let text = &text[3..];
+                text.strip_suffix("*/").unwrap_or(text)
+            }
+        }
+    }
+}
+
+impl PartialEq for DocComment<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.style == other.style && self.position.text == other.position.text
+    }
+}
+
+impl Display for DocComment<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.position.text)
+    }
+}
@@ -1,80 +1,665 @@
 use std::fmt::{Debug, Display};
 
-use crate::tokenizer::{Literal, Operator, Token};
+use crate::{
+    resolve::DefinitionId,
+    tokenizer::{DelimeterType, Literal, Operator, Span, TokenPosition},
+};
+
+use super::{
+    attribute::Attribute,
+    macros::{self, TokenTree},
+    pattern::Pattern,
+    statement::Block,
+    types::Type,
+    ParserError,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathSegment<'s> {
+    Name {
+        name: &'s str,
+        generics: Vec<Type<'s>>,
+    },
+
+    /// `self`
+    SelfModule,
+
+    /// `super`
+    Super,
+}
+
+impl<'s> PathSegment<'s> {
+    pub fn name(name: &'s str) -> Self {
+        Self::Name {
+            name,
+            generics: vec![],
+        }
+    }
+}
+
+impl Display for PathSegment<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Name { name, generics } => {
+                write!(f, "{name}")?;
+
+                if !generics.is_empty() {
+                    write!(f, "::<")?;
+
+                    let mut generics = generics.iter();
+                    if let Some(g) = generics.next() {
+                        write!(f, "{g}")?;
+                    }
+
+                    for g in generics {
+                        write!(f, ", {g}")?;
+                    }
+
+                    write!(f, ">")?;
+                }
+
+                Ok(())
+            }
+
+            Self::SelfModule => write!(f, "self"),
+            Self::Super => write!(f, "super"),
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Path<'s> {
-    pub(crate) head: &'s str,
-    pub(crate) tail: Option<Vec<Token<'s>>>,
+    /// Starts with `::`
+    pub(crate) absolute: bool,
+    pub(crate) segments: Vec<PathSegment<'s>>,
+    pub(crate) span: Span,
+    /// What the path refers to, set by the [`Resolver`](crate::resolve::Resolver).
+    pub(crate) definition: Option<DefinitionId>,
 }
 
 impl<'s> Path<'s> {
-    pub fn new(head: &'s str, tail: Option<Vec<Token<'s>>>) -> Self {
-        Self { head, tail }
+    pub fn new(absolute: bool, segments: Vec<PathSegment<'s>>) -> Self {
+        Self {
+            absolute,
+            segments,
+            span: Span::default(),
+            definition: None,
+        }
+    }
+
+    /// A relative path of a single name without generics.
+    pub fn single(name: &'s str) -> Self {
+        Self::new(false, vec![PathSegment::name(name)])
+    }
+
+    pub fn is_absolute(&self) -> bool {
+        self.absolute
+    }
+
+    pub fn segments(&self) -> &[PathSegment<'s>] {
+        &self.segments
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn definition(&self) -> Option<DefinitionId> {
+        self.definition
+    }
+
+    /// The name if this is a path made by [`Path::single`].
+    pub fn as_identifier(&self) -> Option<&'s str> {
+        match self.segments.as_slice() {
+            [PathSegment::Name { name, generics }] if !self.absolute && generics.is_empty() => {
+                Some(name)
+            }
+
+            _ => None,
+        }
+    }
+}
+
+/// Paths are equal when they have the same segments, wherever they were
+/// parsed from and whatever they were resolved to.
+impl PartialEq for Path<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.absolute == other.absolute && self.segments == other.segments
     }
 }
 
 impl Display for Path<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.head)?;
-        let Some(tail) = &self.tail else {
-            return Ok(());
-        };
+        if self.absolute {
+            write!(f, "::")?;
+        }
+
+        let mut segments = self.segments.iter();
+        if let Some(s) = segments.next() {
+            write!(f, "{s}")?;
+        }
 
-        for n in tail {
-            write!(f, "::{}", n.text())?;
+        for s in segments {
+            write!(f, "::{s}")?;
         }
 
         Ok(())
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter<'s> {
+    pub(crate) name: &'s str,
+}
+
+impl<'s> Parameter<'s> {
+    pub fn new(name: &'s str) -> Self {
+        Self { name }
+    }
+
+    pub fn name(&self) -> &'s str {
+        self.name
+    }
+}
+
+impl<'s> TryFrom<Expression<'s>> for Parameter<'s> {
+    type Error = ParserError;
+
+    fn try_from(value: Expression<'s>) -> Result<Self, Self::Error> {
+        match value {
+            Expression::Name(ref p) => match p.as_identifier() {
+                Some(name) => Ok(Self::new(name)),
+                None => Err(ParserError::InvalidParameter(value.to_string())),
+            },
+
+            e => Err(ParserError::InvalidParameter(e.to_string())),
+        }
+    }
+}
+
+impl Display for Parameter<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchArm<'s> {
+    pub(crate) pattern: Pattern<'s>,
+    pub(crate) guard: Option<Expression<'s>>,
+    pub(crate) body: Expression<'s>,
+
+    pub(crate) position: TokenPosition<'s>,
+}
+
+impl<'s> MatchArm<'s> {
+    pub fn pattern(&self) -> &Pattern<'s> {
+        &self.pattern
+    }
+
+    pub fn guard(&self) -> Option<&Expression<'s>> {
+        self.guard.as_ref()
+    }
+
+    pub fn body(&self) -> &Expression<'s> {
+        &self.body
+    }
+
+    pub fn position(&self) -> &TokenPosition<'s> {
+        &self.position
+    }
+}
+
+impl PartialEq for MatchArm<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern && self.guard == other.guard && self.body == other.body
+    }
+}
+
+impl Display for MatchArm<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.pattern)?;
+
+        if let Some(guard) = &self.guard {
+            write!(f, " if {guard}")?;
+        }
+
+        write!(f, " => {}", self.body)
+    }
+}
+
+/// A field of a struct literal, `value` is `None` for the `Point { x }` shorthand.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldInit<'s> {
+    pub(crate) name: &'s str,
+    pub(crate) value: Option<Expression<'s>>,
+}
+
+impl<'s> FieldInit<'s> {
+    pub fn name(&self) -> &'s str {
+        self.name
+    }
+
+    pub fn value(&self) -> Option<&Expression<'s>> {
+        self.value.as_ref()
+    }
+}
+
+impl Display for FieldInit<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.value {
+            Some(value) => write!(f, "{}: {value}", self.name),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Expression<'s> {
     Prefix {
         op: Operator,
         right: Box<Expression<'s>>,
+        span: Span,
     },
 
     Infix {
         left: Box<Expression<'s>>,
         op: Operator,
         right: Box<Expression<'s>>,
+        span: Span,
+    },
+
+    /// An infix operator declared with `infixl`/`infixr`/`infix`.
+    CustomInfix {
+        left: Box<Expression<'s>>,
+        op: String,
+        right: Box<Expression<'s>>,
+        span: Span,
     },
 
     Postfix {
         left: Box<Expression<'s>>,
         op: Operator,
+        span: Span,
     },
 
     Call {
         function: Path<'s>,
         arguments: Vec<Expression<'s>>,
+        span: Span,
     },
 
     Name(Path<'s>),
 
     Lit {
         value: Literal,
+        span: Span,
     },
 
     Index {
         expr: Box<Expression<'s>>,
         with: Box<Expression<'s>>,
+        span: Span,
+    },
+
+    Lambda {
+        parameters: Vec<Parameter<'s>>,
+        body: Box<Expression<'s>>,
+        span: Span,
+    },
+
+    Match {
+        scrutinee: Box<Expression<'s>>,
+        arms: Vec<MatchArm<'s>>,
+        span: Span,
+    },
+
+    Tuple {
+        items: Vec<Expression<'s>>,
+        span: Span,
+    },
+
+    Array {
+        items: Vec<Expression<'s>>,
+        span: Span,
+    },
+
+    /// `[value; count]`
+    Repeat {
+        value: Box<Expression<'s>>,
+        count: Box<Expression<'s>>,
+        span: Span,
     },
+
+    Map {
+        entries: Vec<(Expression<'s>, Expression<'s>)>,
+        span: Span,
+    },
+
+    Struct {
+        path: Path<'s>,
+        fields: Vec<FieldInit<'s>>,
+        base: Option<Box<Expression<'s>>>,
+        span: Span,
+    },
+
+    Block(Block<'s>),
+
+    If {
+        condition: Box<Expression<'s>>,
+        then: Block<'s>,
+        otherwise: Option<Box<Expression<'s>>>,
+        span: Span,
+    },
+
+    While {
+        condition: Box<Expression<'s>>,
+        body: Block<'s>,
+        span: Span,
+    },
+
+    Attributed {
+        attributes: Vec<Attribute<'s>>,
+        expr: Box<Expression<'s>>,
+        span: Span,
+    },
+
+    /// `path!(tokens)`, replaced by the expansion pass.
+    MacroCall {
+        path: Path<'s>,
+        delimiter: DelimeterType,
+        tokens: Vec<TokenTree<'s>>,
+
+        position: TokenPosition<'s>,
+        span: Span,
+    },
+}
+
+impl Expression<'_> {
+    /// The bytes of the source the expression was parsed from, empty for
+    /// expressions made by passes over the tree.
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Name(path) => path.span,
+            Self::Block(block) => block.span,
+
+            Self::Prefix { span, .. }
+            | Self::Infix { span, .. }
+            | Self::CustomInfix { span, .. }
+            | Self::Postfix { span, .. }
+            | Self::Call { span, .. }
+            | Self::Lit { span, .. }
+            | Self::Index { span, .. }
+            | Self::Lambda { span, .. }
+            | Self::Match { span, .. }
+            | Self::Tuple { span, .. }
+            | Self::Array { span, .. }
+            | Self::Repeat { span, .. }
+            | Self::Map { span, .. }
+            | Self::Struct { span, .. }
+            | Self::If { span, .. }
+            | Self::While { span, .. }
+            | Self::Attributed { span, .. }
+            | Self::MacroCall { span, .. } => *span,
+        }
+    }
+
+    pub fn span_mut(&mut self) -> &mut Span {
+        match self {
+            Self::Name(path) => &mut path.span,
+            Self::Block(block) => &mut block.span,
+
+            Self::Prefix { span, .. }
+            | Self::Infix { span, .. }
+            | Self::CustomInfix { span, .. }
+            | Self::Postfix { span, .. }
+            | Self::Call { span, .. }
+            | Self::Lit { span, .. }
+            | Self::Index { span, .. }
+            | Self::Lambda { span, .. }
+            | Self::Match { span, .. }
+            | Self::Tuple { span, .. }
+            | Self::Array { span, .. }
+            | Self::Repeat { span, .. }
+            | Self::Map { span, .. }
+            | Self::Struct { span, .. }
+            | Self::If { span, .. }
+            | Self::While { span, .. }
+            | Self::Attributed { span, .. }
+            | Self::MacroCall { span, .. } => span,
+        }
+    }
+
+    /// Whether the expression ends with a block, and so doesn't need a
+    /// semicolon to be followed by another statement.
+    pub fn is_block_like(&self) -> bool {
+        match self {
+            Self::Block(_) | Self::If { .. } | Self::While { .. } | Self::Match { .. } => true,
+            Self::Attributed { expr, .. } => expr.is_block_like(),
+
+            _ => false,
+        }
+    }
+}
+/// Expressions are equal when they have the same structure, the spans of
+/// where they were parsed from aren't compared.
+impl PartialEq for Expression<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                Self::Prefix { op, right, .. },
+                Self::Prefix {
+                    op: other_op,
+                    right: other_right,
+                    ..
+                },
+            ) => op == other_op && right == other_right,
+
+            (
+                Self::Infix {
+                    left, op, right, ..
+                },
+                Self::Infix {
+                    left: other_left,
+                    op: other_op,
+                    right: other_right,
+                    ..
+                },
+            ) => left == other_left && op == other_op && right == other_right,
+
+            (
+                Self::CustomInfix {
+                    left, op, right, ..
+                },
+                Self::CustomInfix {
+                    left: other_left,
+                    op: other_op,
+                    right: other_right,
+                    ..
+                },
+            ) => left == other_left && op == other_op && right == other_right,
+
+            (
+                Self::Postfix { left, op, .. },
+                Self::Postfix {
+                    left: other_left,
+                    op: other_op,
+                    ..
+                },
+            ) => left == other_left && op == other_op,
+
+            (
+                Self::Call {
+                    function,
+                    arguments,
+                    ..
+                },
+                Self::Call {
+                    function: other_function,
+                    arguments: other_arguments,
+                    ..
+                },
+            ) => function == other_function && arguments == other_arguments,
+
+            (Self::Name(path), Self::Name(other_path)) => path == other_path,
+
+            (
+                Self::Lit { value, .. },
+                Self::Lit {
+                    value: other_value, ..
+                },
+            ) => value == other_value,
+
+            (
+                Self::Index { expr, with, .. },
+                Self::Index {
+                    expr: other_expr,
+                    with: other_with,
+                    ..
+                },
+            ) => expr == other_expr && with == other_with,
+
+            (
+                Self::Lambda {
+                    parameters, body, ..
+                },
+                Self::Lambda {
+                    parameters: other_parameters,
+                    body: other_body,
+                    ..
+                },
+            ) => parameters == other_parameters && body == other_body,
+
+            (
+                Self::Match {
+                    scrutinee, arms, ..
+                },
+                Self::Match {
+                    scrutinee: other_scrutinee,
+                    arms: other_arms,
+                    ..
+                },
+            ) => scrutinee == other_scrutinee && arms == other_arms,
+
+            (
+                Self::Tuple { items, .. },
+                Self::Tuple {
+                    items: other_items, ..
+                },
+            )
+            | (
+                Self::Array { items, .. },
+                Self::Array {
+                    items: other_items, ..
+                },
+            ) => items == other_items,
+
+            (
+                Self::Repeat { value, count, .. },
+                Self::Repeat {
+                    value: other_value,
+                    count: other_count,
+                    ..
+                },
+            ) => value == other_value && count == other_count,
+
+            (
+                Self::Map { entries, .. },
+                Self::Map {
+                    entries: other_entries,
+                    ..
+                },
+            ) => entries == other_entries,
+
+            (
+                Self::Struct {
+                    path, fields, base, ..
+                },
+                Self::Struct {
+                    path: other_path,
+                    fields: other_fields,
+                    base: other_base,
+                    ..
+                },
+            ) => path == other_path && fields == other_fields && base == other_base,
+
+            (Self::Block(block), Self::Block(other_block)) => block == other_block,
+
+            (
+                Self::If {
+                    condition,
+                    then,
+                    otherwise,
+                    ..
+                },
+                Self::If {
+                    condition: other_condition,
+                    then: other_then,
+                    otherwise: other_otherwise,
+                    ..
+                },
+            ) => condition == other_condition && then == other_then && otherwise == other_otherwise,
+
+            (
+                Self::While {
+                    condition, body, ..
+                },
+                Self::While {
+                    condition: other_condition,
+                    body: other_body,
+                    ..
+                },
+            ) => condition == other_condition && body == other_body,
+
+            (
+                Self::Attributed {
+                    attributes, expr, ..
+                },
+                Self::Attributed {
+                    attributes: other_attributes,
+                    expr: other_expr,
+                    ..
+                },
+            ) => attributes == other_attributes && expr == other_expr,
+
+            (
+                Self::MacroCall {
+                    path,
+                    delimiter,
+                    tokens,
+                    ..
+                },
+                Self::MacroCall {
+                    path: other_path,
+                    delimiter: other_delimiter,
+                    tokens: other_tokens,
+                    ..
+                },
+            ) => path == other_path && delimiter == other_delimiter && tokens == other_tokens,
+
+            _ => false,
+        }
+    }
 }
+
 impl Display for Expression<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Name(p) => write!(f, "{p}"),
-            Self::Lit { value } => write!(f, "{value}"),
-            Self::Prefix { op, right } => write!(f, "({op}{right})"),
-            Self::Infix { left, op, right } => write!(f, "({left} {op} {right})"),
-            Self::Index { expr, with } => write!(f, "({expr}[{with}])"),
-            Self::Postfix { left, op } => write!(f, "({left}{op})"),
+            Self::Lit { value, .. } => write!(f, "{value}"),
+            Self::Prefix { op, right, .. } => write!(f, "({op}{right})"),
+            Self::Infix {
+                left, op, right, ..
+            } => write!(f, "({left} {op} {right})"),
+            Self::CustomInfix {
+                left, op, right, ..
+            } => write!(f, "({left} {op} {right})"),
+            Self::Index { expr, with, .. } => write!(f, "({expr}[{with}])"),
+            Self::Postfix { left, op, .. } => write!(f, "({left}{op})"),
             Self::Call {
                 function,
                 arguments,
+                ..
             } => {
                 write!(f, "({function}(")?;
 
@@ -89,6 +674,154 @@ impl Display for Expression<'_> {
 
                 write!(f, "))")
             }
+            Self::Lambda {
+                parameters, body, ..
+            } => {
+                write!(f, "((")?;
+
+                let mut params = parameters.iter();
+                if let Some(p) = params.next() {
+                    write!(f, "{p}")?;
+                }
+
+                for p in params {
+                    write!(f, ", {p}")?;
+                }
+
+                write!(f, ") => {body})")
+            }
+            Self::Match {
+                scrutinee, arms, ..
+            } => {
+                write!(f, "(match {scrutinee} {{")?;
+
+                let mut arms = arms.iter();
+                if let Some(a) = arms.next() {
+                    write!(f, " {a}")?;
+                }
+
+                for a in arms {
+                    write!(f, ", {a}")?;
+                }
+
+                write!(f, " }})")
+            }
+            Self::Tuple { items, .. } => {
+                write!(f, "(")?;
+
+                let mut it = items.iter();
+                if let Some(i) = it.next() {
+                    write!(f, "{i}")?;
+                }
+
+                for i in it {
+                    write!(f, ", {i}")?;
+                }
+
+                if items.len() == 1 {
+                    write!(f, ",")?;
+                }
+
+                write!(f, ")")
+            }
+            Self::Array { items, .. } => {
+                write!(f, "[")?;
+
+                let mut items = items.iter();
+                if let Some(i) = items.next() {
+                    write!(f, "{i}")?;
+                }
+
+                for i in items {
+                    write!(f, ", {i}")?;
+                }
+
+                write!(f, "]")
+            }
+            Self::Repeat { value, count, .. } => write!(f, "[{value}; {count}]"),
+            Self::Map { entries, .. } => {
+                write!(f, "{{")?;
+
+                let mut entries = entries.iter();
+                if let Some((k, v)) = entries.next() {
+                    write!(f, "{k}: {v}")?;
+                }
+
+                for (k, v) in entries {
+                    write!(f, ", {k}: {v}")?;
+                }
+
+                write!(f, "}}")
+            }
+            Self::Struct {
+                path, fields, base, ..
+            } => {
+                write!(f, "{path} {{")?;
+
+                let mut fields_iter = fields.iter();
+                if let Some(fi) = fields_iter.next() {
+                    write!(f, " {fi}")?;
+                }
+
+                for fi in fields_iter {
+                    write!(f, ", {fi}")?;
+                }
+
+                if let Some(base) = base {
+                    if !fields.is_empty() {
+                        write!(f, ",")?;
+                    }
+
+                    write!(f, " ..{base}")?;
+                }
+
+                write!(f, " }}")
+            }
+            Self::Block(block) => write!(f, "{block}"),
+            Self::If {
+                condition,
+                then,
+                otherwise,
+                ..
+            } => {
+                write!(f, "if {condition} {then}")?;
+
+                if let Some(otherwise) = otherwise {
+                    write!(f, " else {otherwise}")?;
+                }
+
+                Ok(())
+            }
+            Self::While {
+                condition, body, ..
+            } => write!(f, "while {condition} {body}"),
+            Self::Attributed {
+                attributes, expr, ..
+            } => {
+                write!(f, "(")?;
+
+                for a in attributes {
+                    write!(f, "{a} ")?;
+                }
+
+                write!(f, "{expr})")
+            }
+            Self::MacroCall {
+                path,
+                delimiter,
+                tokens,
+                ..
+            } => {
+                let (open, close) = match delimiter {
+                    DelimeterType::Parentheses => ("(", ")"),
+                    DelimeterType::Square => ("[", "]"),
+                    DelimeterType::Curly => ("{", "}"),
+                };
+
+                write!(f, "{path}!{open}")?;
+                macros::write_trees(f, tokens)?;
+                write!(f, "{close}")
+            }
         }
     }
 }
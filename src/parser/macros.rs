@@ -0,0 +1,283 @@
+use std::fmt::Display;
+
+use crate::tokenizer::{DelimeterType, Token, TokenPosition};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenTree<'s> {
+    Token(Token<'s>),
+
+    /// Tokens between a pair of matching delimiters.
+    Group {
+        delimiter: DelimeterType,
+        open: Token<'s>,
+        trees: Vec<TokenTree<'s>>,
+        close: Token<'s>,
+    },
+}
+
+impl<'s> TokenTree<'s> {
+    /// Appends the tokens of this tree to `out`, delimiters included.
+    pub fn flatten_into(&self, out: &mut Vec<Token<'s>>) {
+        match self {
+            Self::Token(t) => out.push(t.clone()),
+            Self::Group {
+                open, trees, close, ..
+            } => {
+                out.push(open.clone());
+
+                for t in trees {
+                    t.flatten_into(out);
+                }
+
+                out.push(close.clone());
+            }
+        }
+    }
+
+    pub fn first_token(&self) -> &Token<'s> {
+        match self {
+            Self::Token(t) => t,
+            Self::Group { open, .. } => open,
+        }
+    }
+
+    /// The position of the first token of the tree.
+    pub fn position(&self) -> &TokenPosition<'s> {
+        self.first_token().position()
+    }
+}
+
+impl Display for TokenTree<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Token(t) => write!(f, "{}", t.text()),
+            Self::Group {
+                open, trees, close, ..
+            } => {
+                write!(f, "{}", open.text())?;
+                write_trees(f, trees)?;
+                write!(f, "{}", close.text())
+            }
+        }
+    }
+}
+
+pub(crate) fn write_trees(
+    f: &mut std::fmt::Formatter<'_>,
+    trees: &[TokenTree<'_>],
+) -> std::fmt::Result {
+    let mut trees = trees.iter();
+    if let Some(t) = trees.next() {
+        write!(f, "{t}")?;
+    }
+
+    for t in trees {
+        write!(f, " {t}")?;
+    }
+
+    Ok(())
+}
+
+/// What a `$name:kind` metavariable matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FragmentKind {
+    Expr,
+    Ident,
+    Literal,
+    Ty,
+    Pat,
+    Tt,
+}
+
+impl FragmentKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "expr" => Self::Expr,
+            "ident" => Self::Ident,
+            "literal" => Self::Literal,
+            "ty" => Self::Ty,
+            "pat" => Self::Pat,
+            "tt" => Self::Tt,
+
+            _ => return None,
+        })
+    }
+}
+
+impl Display for FragmentKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Expr => write!(f, "expr"),
+            Self::Ident => write!(f, "ident"),
+            Self::Literal => write!(f, "literal"),
+            Self::Ty => write!(f, "ty"),
+            Self::Pat => write!(f, "pat"),
+            Self::Tt => write!(f, "tt"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepetitionKind {
+    /// `*`
+    ZeroOrMore,
+    /// `+`
+    OneOrMore,
+    /// `?`
+    ZeroOrOne,
+}
+
+impl Display for RepetitionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ZeroOrMore => write!(f, "*"),
+            Self::OneOrMore => write!(f, "+"),
+            Self::ZeroOrOne => write!(f, "?"),
+        }
+    }
+}
+
+/// The left hand side of a macro rule.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Matcher<'s> {
+    /// Matches a token with the same type and text.
+    Token(Token<'s>),
+
+    Group {
+        delimiter: DelimeterType,
+        matchers: Vec<Matcher<'s>>,
+    },
+
+    /// `$name:kind`
+    Fragment { name: &'s str, kind: FragmentKind },
+
+    /// `$( ... ) separator kind`
+    Repetition {
+        matchers: Vec<Matcher<'s>>,
+        separator: Option<Token<'s>>,
+        kind: RepetitionKind,
+    },
+}
+
+impl Display for Matcher<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Token(t) => write!(f, "{}", t.text()),
+            Self::Group {
+                delimiter,
+                matchers,
+            } => {
+                let (open, close) = match delimiter {
+                    DelimeterType::Parentheses => ("(", ")"),
+                    DelimeterType::Square => ("[", "]"),
+                    DelimeterType::Curly => ("{", "}"),
+                };
+
+                write!(f, "{open}")?;
+                write_matchers(f, matchers)?;
+                write!(f, "{close}")
+            }
+            Self::Fragment { name, kind } => write!(f, "${name}:{kind}"),
+            Self::Repetition {
+                matchers,
+                separator,
+                kind,
+            } => {
+                write!(f, "$(")?;
+                write_matchers(f, matchers)?;
+                write!(f, ")")?;
+
+                if let Some(separator) = separator {
+                    write!(f, "{}", separator.text())?;
+                }
+
+                write!(f, "{kind}")
+            }
+        }
+    }
+}
+
+fn write_matchers(f: &mut std::fmt::Formatter<'_>, matchers: &[Matcher<'_>]) -> std::fmt::Result {
+    let mut matchers = matchers.iter();
+    if let Some(m) = matchers.next() {
+        write!(f, "{m}")?;
+    }
+
+    for m in matchers {
+        write!(f, " {m}")?;
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MacroRule<'s> {
+    pub(crate) matchers: Vec<Matcher<'s>>,
+    pub(crate) transcriber: Vec<TokenTree<'s>>,
+}
+
+impl<'s> MacroRule<'s> {
+    pub fn matchers(&self) -> &[Matcher<'s>] {
+        &self.matchers
+    }
+
+    pub fn transcriber(&self) -> &[TokenTree<'s>] {
+        &self.transcriber
+    }
+}
+
+impl Display for MacroRule<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "(")?;
+        write_matchers(f, &self.matchers)?;
+        write!(f, ") => {{ ")?;
+        write_trees(f, &self.transcriber)?;
+        write!(f, " }}")
+    }
+}
+
+/// `macro name { (matcher) => { transcriber }; ... }`
+#[derive(Debug, Clone)]
+pub struct MacroDefinition<'s> {
+    pub(crate) name: &'s str,
+    pub(crate) rules: Vec<MacroRule<'s>>,
+
+    pub(crate) position: TokenPosition<'s>,
+}
+
+impl<'s> MacroDefinition<'s> {
+    pub fn name(&self) -> &'s str {
+        self.name
+    }
+
+    pub fn rules(&self) -> &[MacroRule<'s>] {
+        &self.rules
+    }
+
+    pub fn position(&self) -> &TokenPosition<'s> {
+        &self.position
+    }
+}
+
+impl PartialEq for MacroDefinition<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.rules == other.rules
+    }
+}
+
+impl Display for MacroDefinition<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "macro {} {{", self.name)?;
+
+        let mut rules = self.rules.iter();
+        if let Some(r) = rules.next() {
+            write!(f, " {r}")?;
+        }
+
+        for r in rules {
+            write!(f, "; {r}")?;
+        }
+
+        write!(f, " }}")
+    }
+}
@@ -1,8 +1,36 @@
+pub mod attribute;
+pub mod doc;
 pub mod expression;
+pub mod macros;
+pub mod operator;
+pub mod pattern;
+pub mod print;
+pub mod statement;
+pub mod types;
+pub mod visit;
 
-use crate::{tokenizer::*, Diagnostic};
+use std::collections::VecDeque;
 
-use self::expression::{Expression as Expr, Path};
+use crate::{
+    cst::{NodeKind, NodeSpan},
+    dialect::{Constructs, Dialect},
+    tokenizer::*,
+    units::UnitRegistry,
+    Diagnostic, DiagnosticType,
+};
+
+use self::{
+    attribute::{Attribute, AttributeRegistry, AttributeStyle},
+    doc::DocComment,
+    expression::{Expression as Expr, FieldInit, MatchArm, Parameter, Path, PathSegment},
+    macros::{FragmentKind, MacroDefinition, MacroRule, Matcher, RepetitionKind, TokenTree},
+    operator::{
+        binding_power, Associativity, Fixity, OperatorTable, MAX_LEVEL, PREFIX_BINDING_POWER,
+    },
+    pattern::{Pattern, PatternLiteral},
+    statement::{Block, Statement},
+    types::Type,
+};
 use Operator as Op;
 use ParserError as ParsErr;
 use TokenType as Ty;
@@ -20,6 +48,15 @@ pub enum ParserError {
 
     #[error("Unexpected end of expression")]
     UnexpectedEnd,
+
+    #[error("Expected a parameter name, found expression {0}")]
+    InvalidParameter(String),
+
+    #[error("Operator {0} is not associative, chaining it needs parentheses")]
+    NonAssociative(String),
+
+    #[error("{0} are not allowed in this dialect")]
+    Disallowed(&'static str),
 }
 
 impl ParserError {
@@ -28,39 +65,254 @@ impl ParserError {
     }
 }
 
+/// Constructs that are not allowed in the current context, because they
+/// would be ambiguous with what follows it.
+#[derive(Debug, Clone, Copy, Default)]
+struct Restrictions {
+    /// `x => ...` would swallow the fat arrow of a match arm.
+    no_lambda: bool,
+
+    /// `x { ... }` would swallow the block after a condition.
+    no_struct_literal: bool,
+
+    /// Inside brackets line breaks can't end a statement.
+    ignore_newlines: bool,
+}
+
+impl Restrictions {
+    /// For the items between brackets.
+    fn brackets() -> Self {
+        Self {
+            ignore_newlines: true,
+            ..Self::default()
+        }
+    }
+}
+
+/// A token the parser looked at, with what the tokenizer emitted before it
+/// that isn't a part of the grammar.
+struct Lookahead<'s> {
+    item: TokenizerItem<'s>,
+    newline_before: bool,
+    docs: Vec<DocComment<'s>>,
+}
+
 /// based on: https://matklad.github.io/2020/04/13/simple-but-powerful-pratt-parsing.html
 pub struct Parser<'s, 'd, T: Iterator<Item = TokenizerItem<'s>>, D: Extend<Diagnostic<'s>>> {
-    tokenizer: std::iter::Peekable<T>,
-    _diagnostics: &'d mut D,
+    tokenizer: T,
+    lookahead: VecDeque<Lookahead<'s>>,
+    newline_pending: bool,
+    docs_pending: Vec<DocComment<'s>>,
+    /// The end of the last token taken from the lookahead.
+    last_end: usize,
+    /// The nodes parsed so far, when building a concrete syntax tree.
+    nodes: Option<Vec<NodeSpan>>,
+    diagnostics: &'d mut D,
+
+    /// The operators given to [`Parser::new`], and the infix ones declared
+    /// with `infixl`/`infixr`/`infix` so far.
+    operators: OperatorTable,
+    constructs: Constructs,
+    /// Whether a line break can end a statement instead of a semicolon.
+    automatic_semicolons: bool,
+    /// Whether indented lines can make a block, see [`Tokenizer::emit_indentation`].
+    indentation_blocks: bool,
+
+    restrictions: Restrictions,
+
+    /// Set while parsing the operand of a prefix minus.
+    negating: bool,
+    /// A literal like `128i8` found right after a minus, reported unless the
+    /// minus applies to the literal alone.
+    negated_literal: Option<TokenPosition<'s>>,
+
+    /// When set, attributes not in the registry are reported as diagnostics.
+    attribute_registry: Option<AttributeRegistry>,
+    /// The units quantities in patterns are compared in.
+    units: UnitRegistry,
 }
 
 impl<'s, 'd, T: Iterator<Item = TokenizerItem<'s>>, D: Extend<Diagnostic<'s>>>
     Parser<'s, 'd, T, D>
 {
-    pub fn new(tokenizer: T, diagnostics: &'d mut D) -> Self {
+    pub fn new(tokenizer: T, diagnostics: &'d mut D, operators: OperatorTable) -> Self {
+        Self {
+            tokenizer,
+            lookahead: VecDeque::new(),
+            newline_pending: false,
+            docs_pending: vec![],
+            last_end: 0,
+            nodes: None,
+            diagnostics,
+
+            operators,
+            constructs: Constructs::default(),
+            automatic_semicolons: false,
+            indentation_blocks: false,
+
+            restrictions: Restrictions::default(),
+
+            negating: false,
+            negated_literal: None,
+
+            attribute_registry: None,
+            units: UnitRegistry::standard().clone(),
+        }
+    }
+
+    /// Parses with the operators and constructs of the dialect, the tokenizer
+    /// should be made for the same dialect.
+    pub fn with_dialect(tokenizer: T, diagnostics: &'d mut D, dialect: &Dialect) -> Self {
         Self {
-            tokenizer: tokenizer.peekable(),
-            _diagnostics: diagnostics,
+            constructs: dialect.constructs,
+            automatic_semicolons: dialect.automatic_semicolons,
+            indentation_blocks: dialect.indentation_blocks,
+            units: dialect.units.clone(),
+            ..Self::new(tokenizer, diagnostics, dialect.operators.clone())
+        }
+    }
+
+    fn with_restrictions<R>(
+        &mut self,
+        restrictions: Restrictions,
+        f: impl FnOnce(&mut Self) -> Result<R, ParsErr>,
+    ) -> Result<R, ParsErr> {
+        let old = std::mem::replace(&mut self.restrictions, restrictions);
+        let r = f(self);
+        self.restrictions = old;
+
+        r
+    }
+
+    /// Makes sure there are at least `n` tokens in the lookahead, unless the
+    /// tokenizer runs out before that. Newlines and doc comments are
+    /// remembered with the token after them, other comments are skipped.
+    fn fill_lookahead(&mut self, n: usize) {
+        while self.lookahead.len() < n {
+            match self.tokenizer.next() {
+                Some(Ok(Token {
+                    ty: Ty::Newline, ..
+                })) => self.newline_pending = true,
+
+                Some(Ok(Token {
+                    ty: Ty::Whitespace, ..
+                })) => (),
+
+                Some(Ok(
+                    t @ Token {
+                        ty: Ty::Comment(_), ..
+                    },
+                )) => self.docs_pending.extend(DocComment::from_token(&t)),
+
+                Some(item) => self.lookahead.push_back(Lookahead {
+                    item,
+                    newline_before: std::mem::take(&mut self.newline_pending),
+                    docs: std::mem::take(&mut self.docs_pending),
+                }),
+
+                None => break,
+            }
         }
     }
 
     #[allow(unused)]
     fn next_token(&mut self) -> Result<Option<Token<'s>>, TokenizerError> {
-        match self.tokenizer.next() {
-            Some(Ok(t)) => Ok(Some(t)),
-            Some(Err(e)) => Err(e),
-            None => Ok(None),
+        self.fill_lookahead(1);
+
+        let Some(Lookahead { item, docs, .. }) = self.lookahead.pop_front() else {
+            return Ok(None);
+        };
+
+        // `take_docs` wasn't called for this token
+        self.report_unused_docs(&docs);
+
+        if let Ok(t) = &item {
+            if !matches!(t.ty, Ty::Indent | Ty::Dedent) {
+                self.last_end = t.position.absolute_position + t.text().len();
+            }
+        }
+
+        item.map(Some)
+    }
+
+    /// Takes the doc comments before the next token.
+    fn take_docs(&mut self) -> Vec<DocComment<'s>> {
+        self.fill_lookahead(1);
+
+        match self.lookahead.front_mut() {
+            Some(l) => std::mem::take(&mut l.docs),
+            None => vec![],
+        }
+    }
+
+    /// Reports a literal that is a value of its type only after a minus,
+    /// the tokenizer reports the other literals out of range.
+    fn check_unnegated_literal(&mut self, literal: &Literal, position: TokenPosition<'s>) {
+        if literal.fits_only_negated() {
+            self.diagnostics
+                .extend([Diagnostic::new(DiagnosticType::LiteralOutOfRange, position)]);
+        }
+    }
+
+    fn report_unused_docs(&mut self, docs: &[DocComment<'s>]) {
+        if let Some(d) = docs.first() {
+            self.diagnostics.extend([Diagnostic::new(
+                DiagnosticType::UnusedDocComment,
+                d.position,
+            )]);
         }
     }
     #[allow(unused)]
     fn peek_token(&mut self) -> Result<Option<&Token<'s>>, TokenizerError> {
-        match self.tokenizer.peek() {
+        self.peek_nth_token(0)
+    }
+    fn peek_nth_token(&mut self, n: usize) -> Result<Option<&Token<'s>>, TokenizerError> {
+        self.fill_lookahead(n + 1);
+
+        match self.lookahead.get(n).map(|l| &l.item) {
             Some(Ok(t)) => Ok(Some(t)),
             Some(Err(e)) => Err(*e),
             None => Ok(None),
         }
     }
 
+    /// Whether a line break here ends the statement, also true at the end of
+    /// the input.
+    fn at_line_end(&mut self) -> bool {
+        if !self.automatic_semicolons || self.restrictions.ignore_newlines {
+            return false;
+        }
+
+        self.fill_lookahead(1);
+        self.lookahead.front().is_none_or(|l| l.newline_before)
+    }
+
+    /// Ends the expression at a line break, warning when the next line starts
+    /// with a token that would have continued it.
+    fn line_break_ends_expr(&mut self, continues: bool) -> Result<bool, ParsErr> {
+        if !self.at_line_end() {
+            return Ok(false);
+        }
+
+        if continues {
+            if let Some(t) = self.peek_token()? {
+                let position = t.position;
+                self.diagnostics.extend([Diagnostic::new(
+                    DiagnosticType::AmbiguousLineBreak,
+                    position,
+                )]);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Eats the semicolon ending a statement, which can be left out at the
+    /// end of a line with automatic semicolons.
+    fn eat_statement_end(&mut self) -> Result<bool, ParsErr> {
+        Ok(self.eat(Ty::Punctuation(Punctuation::Semicolon))? || self.at_line_end())
+    }
+
     #[allow(unused)]
     fn next_token_ty(&mut self) -> Result<Option<Ty>, TokenizerError> {
         Ok(self.next_token()?.map(|t| t.ty))
@@ -70,7 +322,6 @@ impl<'s, 'd, T: Iterator<Item = TokenizerItem<'s>>, D: Extend<Diagnostic<'s>>>
         Ok(self.peek_token()?.map(|t| &t.ty))
     }
 
-    #[allow(unused)]
     fn eat(&mut self, ty: Ty) -> Result<bool, ParsErr> {
         let Some(a) = self.peek_token_ty()? else {
             return Ok(false);
@@ -85,61 +336,114 @@ impl<'s, 'd, T: Iterator<Item = TokenizerItem<'s>>, D: Extend<Diagnostic<'s>>>
     }
 
     fn expr_primary(&mut self) -> Result<Expr<'s>, ParsErr> {
+        let negated = std::mem::take(&mut self.negating);
+
         Ok(match self.next_token()? {
             Some(Token {
                 ty: Ty::Literal(value),
-                ..
-            }) => Expr::Lit { value },
+                position,
+            }) => {
+                if negated && value.fits_only_negated() {
+                    self.negated_literal = Some(position);
+                } else {
+                    self.check_unnegated_literal(&value, position);
+                }
+
+                Expr::Lit {
+                    value,
+                    span: Span::default(),
+                }
+            }
 
             Some(
                 t @ Token {
-                    ty: Ty::Identifier, ..
+                    ty:
+                        Ty::Identifier
+                        | Ty::Punctuation(Punctuation::DoubleColon)
+                        | Ty::Keyword(Keyword::SelfValue | Keyword::Super),
+                    ..
                 },
-            ) => match self.peek_token_ty()? {
-                Some(Ty::Delimeter(Delimeter {
-                    ty: DelimeterType::Parentheses,
-                    side: DelimeterSide::Left,
-                })) => {
-                    self.next_token()?;
-                    self.call_expr(Path::new(t.text(), None))?
+            ) => {
+                if t.ty == Ty::Identifier
+                    && self.lambdas_allowed()
+                    && self.eat(Ty::Punctuation(Punctuation::FatArrow))?
+                {
+                    return self.lambda_expr(vec![Parameter::new(t.text())]);
                 }
 
-                Some(Ty::Punctuation(Punctuation::DoubleColon)) => {
-                    self.next_token()?;
-                    let mut tail = vec![];
+                let position = t.position;
+                let p = self.path(t, false)?;
 
-                    while let Some(Token {
-                        ty: Ty::Identifier, ..
-                    }) = self.peek_token()?
-                    {
-                        tail.push(self.next_token()?.unwrap());
+                // a postfix bang on the next line is reported by `expr_bp`
+                let continues = matches!(
+                    self.peek_token_ty()?,
+                    Some(Ty::Delimeter(Delimeter {
+                        ty: DelimeterType::Parentheses,
+                        side: DelimeterSide::Left,
+                    }))
+                );
+                let next = if self.line_break_ends_expr(continues)? {
+                    None
+                } else {
+                    self.peek_token_ty()?.cloned()
+                };
 
-                        if let Some(Ty::Punctuation(Punctuation::DoubleColon)) =
-                            self.peek_token_ty()?
-                        {
-                            self.next_token()?;
-                        } else {
-                            break;
-                        }
-                    }
+                match next {
+                    Some(Ty::Operator(Op::Bang)) => {
+                        self.next_token()?;
 
-                    let p = Path::new(t.text(), Some(tail));
+                        match self.peek_token_ty()?.cloned() {
+                            Some(Ty::Delimeter(Delimeter {
+                                side: DelimeterSide::Left,
+                                ..
+                            })) if self.constructs.macros => {
+                                let open = self.next_token()?.unwrap();
+                                let TokenTree::Group {
+                                    delimiter, trees, ..
+                                } = self.token_tree(open)?
+                                else {
+                                    unreachable!()
+                                };
 
-                    match self.peek_token_ty()? {
-                        Some(Ty::Delimeter(Delimeter {
-                            ty: DelimeterType::Parentheses,
-                            side: DelimeterSide::Left,
-                        })) => {
-                            self.next_token()?;
-                            self.call_expr(p)?
+                                Expr::MacroCall {
+                                    path: p,
+                                    delimiter,
+                                    tokens: trees,
+                                    position,
+                                    span: Span::default(),
+                                }
+                            }
+
+                            // not a macro call, just a postfix bang
+                            _ => Expr::Postfix {
+                                left: Box::new(Expr::Name(p)),
+                                op: Op::Bang,
+                                span: Span::default(),
+                            },
                         }
+                    }
 
-                        _ => Expr::Name(p),
+                    Some(Ty::Delimeter(Delimeter {
+                        ty: DelimeterType::Parentheses,
+                        side: DelimeterSide::Left,
+                    })) => {
+                        self.next_token()?;
+                        self.with_restrictions(Restrictions::brackets(), |this| this.call_expr(p))?
                     }
-                }
 
-                _ => Expr::Name(Path::new(t.text(), None)),
-            },
+                    Some(Ty::Delimeter(Delimeter {
+                        ty: DelimeterType::Curly,
+                        side: DelimeterSide::Left,
+                    })) if !self.restrictions.no_struct_literal => {
+                        self.next_token()?;
+                        self.with_restrictions(Restrictions::brackets(), |this| {
+                            this.struct_expr(p)
+                        })?
+                    }
+
+                    _ => Expr::Name(p),
+                }
+            }
 
             Some(Token {
                 ty:
@@ -148,38 +452,102 @@ impl<'s, 'd, T: Iterator<Item = TokenizerItem<'s>>, D: Extend<Diagnostic<'s>>>
                         side: DelimeterSide::Left,
                     }),
                 ..
-            }) => {
-                let lhs = self.expr_bp(0)?;
+            }) => self.paren_expr()?,
 
-                match self.next_token_ty()? {
-                    Some(Ty::Delimeter(Delimeter {
-                        ty: DelimeterType::Parentheses,
-                        side: DelimeterSide::Right,
-                    })) => (),
+            Some(Token {
+                ty:
+                    Ty::Delimeter(Delimeter {
+                        ty: DelimeterType::Square,
+                        side: DelimeterSide::Left,
+                    }),
+                ..
+            }) => self.with_restrictions(Restrictions::brackets(), Self::array_expr)?,
 
-                    found => {
-                        return Err(ParserError::Unexpected {
-                            found,
-                            expected: "a matching right parenthesis",
-                        })
-                    }
+            Some(
+                t @ Token {
+                    ty:
+                        Ty::Delimeter(Delimeter {
+                            ty: DelimeterType::Curly,
+                            side: DelimeterSide::Left,
+                        }),
+                    ..
+                },
+            ) => {
+                if self.is_map_literal()? {
+                    self.with_restrictions(Restrictions::brackets(), Self::map_expr)?
+                } else {
+                    Expr::Block(self.block_after_brace(t.position.absolute_position)?)
+                }
+            }
+
+            Some(Token {
+                ty: Ty::Keyword(Keyword::Match),
+                ..
+            }) => self.match_expr()?,
+
+            Some(Token {
+                ty: Ty::Keyword(Keyword::If),
+                ..
+            }) => self.if_expr()?,
+
+            Some(Token {
+                ty: Ty::Keyword(Keyword::While),
+                ..
+            }) => {
+                let condition = self.condition()?;
+                let body = self.block()?;
+
+                Expr::While {
+                    condition: Box::new(condition),
+                    body,
+                    span: Span::default(),
                 }
+            }
+
+            Some(
+                t @ Token {
+                    ty: Ty::Punctuation(Punctuation::HashSymbol | Punctuation::AtSign),
+                    ..
+                },
+            ) => {
+                let mut attributes = vec![self.attribute(t)?];
+                attributes.extend(self.attributes()?);
 
-                lhs
+                let expr = self.expr_bp(PREFIX_BINDING_POWER)?;
+                Expr::Attributed {
+                    attributes,
+                    expr: Box::new(expr),
+                    span: Span::default(),
+                }
             }
 
             Some(Token {
                 ty: Ty::Operator(op),
                 ..
             }) => {
-                let Some(((), r_bp)) = prefix_binding_power(op) else {
-                    todo!()
+                let Some(precedence) = self.operators.prefix(op) else {
+                    return Err(ParsErr::unexpected(
+                        Some(Ty::Operator(op)),
+                        "a literal or an identifier",
+                    ));
                 };
 
-                let rhs = self.expr_bp(r_bp)?;
+                self.negating = op == Op::Minus;
+                self.negated_literal = None;
+                let rhs = self.expr_bp(binding_power(precedence))?;
+
+                // `-128i8 ** 2` raises the literal before negating it
+                if let Some(position) = self.negated_literal.take() {
+                    if !matches!(rhs, Expr::Lit { .. }) {
+                        self.diagnostics
+                            .extend([Diagnostic::new(DiagnosticType::LiteralOutOfRange, position)]);
+                    }
+                }
+
                 Expr::Prefix {
                     op,
                     right: Box::new(rhs),
+                    span: Span::default(),
                 }
             }
 
@@ -194,20 +562,49 @@ impl<'s, 'd, T: Iterator<Item = TokenizerItem<'s>>, D: Extend<Diagnostic<'s>>>
         })
     }
 
-    fn expr_bp(&mut self, min_bp: u8) -> Result<Expr<'s>, ParsErr> {
+    /// Also sets the spans of the expressions, so the constructors in the
+    /// other parsing functions leave them empty.
+    fn expr_bp(&mut self, min_bp: u16) -> Result<Expr<'s>, ParsErr> {
+        let start = self.node_start();
+        let span_start = self.span_start();
+        let paren = matches!(
+            self.peek_token_ty()?,
+            Some(Ty::Delimeter(Delimeter {
+                ty: DelimeterType::Parentheses,
+                side: DelimeterSide::Left,
+            }))
+        );
+
         let mut lhs = self.expr_primary()?;
+        match NodeKind::of_expression(&lhs) {
+            NodeKind::TupleExpr | NodeKind::LambdaExpr => (),
+            _ if paren => self.finish_node(NodeKind::ParenExpr, start),
+            kind => self.finish_node(kind, start),
+        }
 
-        while let Some(
-            op @ (Ty::Operator(_)
-            | Ty::Delimeter(Delimeter {
-                ty: DelimeterType::Square,
-                ..
-            })),
-        ) = self.peek_token_ty()?
-        {
-            let op = op.clone();
+        // the expression in parentheses keeps its own span
+        if !paren || matches!(lhs, Expr::Tuple { .. } | Expr::Lambda { .. }) {
+            *lhs.span_mut() = Span::new(span_start, self.last_end);
+        }
+
+        // the last infix operator in this loop, for non associative ones
+        let mut previous: Option<Fixity> = None;
+
+        while let Some(op) = self.peek_token_ty()?.cloned() {
+            if matches!(op, Ty::Operator(op) if op.is_assignment()) && !self.constructs.assignments
+            {
+                return Err(ParsErr::Disallowed("assignments"));
+            }
+
+            let infix = self.peek_infix_operator()?;
 
-            if let Some((l_bp, ())) = postfix_binding_power(&op) {
+            let continues = infix.is_some() || self.postfix_binding_power(&op).is_some();
+            if self.line_break_ends_expr(continues)? {
+                break;
+            }
+
+            let multi_token_infix = matches!(infix, Some((_, len, _)) if len > 1);
+            if let (Some(l_bp), false) = (self.postfix_binding_power(&op), multi_token_infix) {
                 if l_bp < min_bp {
                     break;
                 }
@@ -221,7 +618,8 @@ impl<'s, 'd, T: Iterator<Item = TokenizerItem<'s>>, D: Extend<Diagnostic<'s>>>
                         side: DelimeterSide::Left,
                     })
                 ) {
-                    let rhs = self.expr_bp(0)?;
+                    let rhs =
+                        self.with_restrictions(Restrictions::brackets(), |this| this.expr_bp(0))?;
 
                     match self.next_token_ty()? {
                         Some(Ty::Delimeter(Delimeter {
@@ -240,6 +638,7 @@ impl<'s, 'd, T: Iterator<Item = TokenizerItem<'s>>, D: Extend<Diagnostic<'s>>>
                     Expr::Index {
                         expr: Box::new(lhs),
                         with: Box::new(rhs),
+                        span: Span::default(),
                     }
                 } else {
                     Expr::Postfix {
@@ -249,30 +648,48 @@ impl<'s, 'd, T: Iterator<Item = TokenizerItem<'s>>, D: Extend<Diagnostic<'s>>>
                         } else {
                             unreachable!()
                         },
+                        span: Span::default(),
                     }
                 };
 
+                *lhs.span_mut() = Span::new(span_start, self.last_end);
+                self.finish_node(NodeKind::of_expression(&lhs), start);
                 continue;
             };
 
-            if let Some((l_bp, r_bp)) = infix_binding_power(&op) {
+            if let Some((symbol, len, fixity)) = infix {
+                let (l_bp, r_bp) = fixity.binding_power();
                 if l_bp < min_bp {
                     break;
                 }
 
-                self.next_token()?;
+                if fixity.associativity == Associativity::None && previous == Some(fixity) {
+                    return Err(ParsErr::NonAssociative(symbol));
+                }
+                previous = Some(fixity);
+
+                for _ in 0..len {
+                    self.next_token()?;
+                }
 
                 let rhs = self.expr_bp(r_bp)?;
-                lhs = Expr::Infix {
-                    left: Box::new(lhs),
-                    op: if let Ty::Operator(op) = op {
-                        op
-                    } else {
-                        unreachable!()
+                lhs = match op {
+                    Ty::Operator(op) if len == 1 => Expr::Infix {
+                        left: Box::new(lhs),
+                        op,
+                        right: Box::new(rhs),
+                        span: Span::default(),
                     },
 
-                    right: Box::new(rhs),
+                    _ => Expr::CustomInfix {
+                        left: Box::new(lhs),
+                        op: symbol,
+                        right: Box::new(rhs),
+                        span: Span::default(),
+                    },
                 };
+                *lhs.span_mut() = Span::new(span_start, self.last_end);
+                self.finish_node(NodeKind::of_expression(&lhs), start);
                 continue;
             }
 
@@ -282,108 +699,2276 @@ impl<'s, 'd, T: Iterator<Item = TokenizerItem<'s>>, D: Extend<Diagnostic<'s>>>
         Ok(lhs)
     }
 
-    pub fn expr(&mut self) -> Result<Expr<'s>, ParserError> {
-        self.expr_bp(0)
+    fn lambdas_allowed(&self) -> bool {
+        self.constructs.lambdas && !self.restrictions.no_lambda
     }
 
-    fn call_expr(&mut self, function: Path<'s>) -> Result<Expr<'s>, ParsErr> {
-        let mut args = vec![];
+    fn postfix_binding_power(&self, op: &Ty) -> Option<u16> {
+        let precedence = match op {
+            Ty::Operator(op) => self.operators.postfix(*op),
+            Ty::Delimeter(Delimeter {
+                ty: DelimeterType::Square,
+                side: DelimeterSide::Left,
+            }) => self.operators.index(),
 
-        loop {
-            let arg = self.expr()?;
-            args.push(arg);
+            _ => None,
+        };
 
-            match self.peek_token_ty()? {
-                Some(Ty::Delimeter(Delimeter {
-                    ty: DelimeterType::Parentheses,
-                    side: DelimeterSide::Right,
-                })) => {
-                    self.next_token()?;
-                    break;
-                }
+        precedence.map(binding_power)
+    }
 
-                Some(Ty::Punctuation(Punctuation::Comma)) => {
-                    self.next_token()?;
-                    continue;
-                }
+    /// Finds the longest known infix operator at the current position. An
+    /// operator can be made of several adjacent tokens, like `<+>`.
+    ///
+    /// Returns the operator's text, the number of its tokens and its fixity.
+    fn peek_infix_operator(&mut self) -> Result<Option<(String, usize, Fixity)>, ParsErr> {
+        let max_len = self.operators.max_infix_len();
 
-                ty => {
-                    return Err(ParserError::Unexpected {
-                        found: ty.cloned(),
-                        expected:
-                            "a comma after the argument, or a parenthesis closing the agument list",
-                    })
-                }
+        let mut symbol = String::new();
+        let mut found = None;
+        let mut end = None;
+
+        for n in 0.. {
+            let Some(t) = self.peek_nth_token(n)? else {
+                break;
+            };
+
+            let adjacent = end.is_none_or(|end| end == t.position.absolute_position);
+            if !adjacent || !is_operator_symbol(&t.ty) {
+                break;
+            }
+
+            symbol.push_str(t.text());
+            end = Some(t.position.absolute_position + t.text().len());
+
+            if symbol.len() > max_len {
+                break;
+            }
+
+            if let Some(fixity) = self.operators.infix(&symbol) {
+                found = Some((symbol.clone(), n + 1, fixity));
             }
         }
 
-        Ok(Expr::Call {
-            function,
-            arguments: args,
+        Ok(found)
+    }
+
+    /// Parses an operator declaration like `infixl 6 <+>` after its keyword,
+    /// and adds the operator to the ones known from now on.
+    fn fixity_declaration(
+        &mut self,
+        associativity: Associativity,
+    ) -> Result<Statement<'s>, ParsErr> {
+        let level = match self.next_token_ty()? {
+            Some(Ty::Literal(Literal::Number(NumberLiteral::Integer(level), None)))
+                if level <= MAX_LEVEL as u64 =>
+            {
+                level as u8
+            }
+
+            found => {
+                return Err(ParsErr::unexpected(
+                    found,
+                    "an operator precedence level from 0 to 9",
+                ))
+            }
+        };
+
+        let mut symbol = String::new();
+        let mut end = None;
+
+        while let Some(t) = self.peek_token()? {
+            let adjacent = end.is_none_or(|end| end == t.position.absolute_position);
+            if !adjacent || !is_operator_symbol(&t.ty) {
+                break;
+            }
+
+            end = Some(t.position.absolute_position + t.text().len());
+            symbol.push_str(t.text());
+            self.next_token()?;
+        }
+
+        if symbol.is_empty() {
+            return Err(ParsErr::unexpected(
+                self.peek_token_ty()?.cloned(),
+                "an operator symbol",
+            ));
+        }
+
+        if !self.eat_statement_end()? {
+            return Err(ParsErr::unexpected(
+                self.peek_token_ty()?.cloned(),
+                "a semicolon after the operator declaration",
+            ));
+        }
+
+        self.operators
+            .add_infix(symbol.clone(), Fixity::from_level(level, associativity));
+
+        Ok(Statement::Fixity {
+            associativity,
+            level,
+            symbol,
         })
     }
-}
 
-fn prefix_binding_power(op: Operator) -> Option<((), u8)> {
-    match op {
-        Op::Plus | Op::Minus => Some(((), 5)),
-        Op::DoublePlus | Op::DoubleMinus => Some(((), 5)),
-        _ => None,
+    pub fn expr(&mut self) -> Result<Expr<'s>, ParserError> {
+        self.expr_bp(0)
     }
-}
 
-fn infix_binding_power(op: &Ty) -> Option<(u8, u8)> {
-    let Ty::Operator(op) = op else { return None };
-
-    Some(match op {
-        Op::Plus | Op::Minus => (1, 2),
-        Op::Star | Op::Slash => (3, 4),
-
-        Op::DoublePlus => todo!(),
-        Op::DoubleMinus => todo!(),
-        Op::BangEquals => todo!(),
-        Op::RightShift => todo!(),
-        Op::LesserThan => todo!(),
-        Op::DoubleStar => todo!(),
-        Op::DoubleAnd => todo!(),
-        Op::DoubleOr => todo!(),
-        Op::DoubleEquals => todo!(),
-        Op::GreaterThan => todo!(),
-        Op::Caret => todo!(),
-        Op::Percent => todo!(),
-        Op::SingleAnd => todo!(),
-        Op::SingleOr => todo!(),
-        Op::LeftShift => todo!(),
-        Op::Equals => todo!(),
-        Op::PlusEquals => todo!(),
-        Op::MinusEquals => todo!(),
-        Op::StarEquals => todo!(),
-        Op::SlashEquals => todo!(),
-        Op::TildaEquals => todo!(),
-        Op::DoubleStarEquals => todo!(),
-        Op::DoubleAndEquals => todo!(),
-        Op::DoubleOrEquals => todo!(),
-        Op::LesserThanEquals => todo!(),
-        Op::GreaterThanEquals => todo!(),
-        Op::CaretEquals => todo!(),
-        Op::PercentEquals => todo!(),
-        Op::SingleAndEquals => todo!(),
-        Op::SingleOrEquals => todo!(),
-        Op::LeftShiftEquals => todo!(),
-        Op::RightShiftEquals => todo!(),
-
-        _ => return None,
-    })
-}
+    /// Parses statements until the end of the input.
+    pub fn program(&mut self) -> Result<Vec<Statement<'s>>, ParsErr> {
+        let mut statements = vec![];
 
-fn postfix_binding_power(op: &Ty) -> Option<(u8, ())> {
-    Some(match op {
-        Ty::Operator(Op::Bang)
-        | Ty::Delimeter(Delimeter {
-            ty: DelimeterType::Square,
-            side: DelimeterSide::Left,
-        }) => (7, ()),
+        while self.peek_token()?.is_some() {
+            statements.push(self.statement()?);
+        }
+
+        let docs = std::mem::take(&mut self.docs_pending);
+        self.report_unused_docs(&docs);
+
+        Ok(statements)
+    }
+
+    pub fn statement(&mut self) -> Result<Statement<'s>, ParsErr> {
+        let start = self.node_start();
+        let statement = self.statement_inner()?;
+        self.finish_node(NodeKind::of_statement(&statement), start);
+
+        Ok(statement)
+    }
+
+    fn statement_inner(&mut self) -> Result<Statement<'s>, ParsErr> {
+        let docs = self.take_docs();
+        if !docs.is_empty() {
+            let statement = self.statement()?;
+
+            return Ok(Statement::Documented {
+                docs,
+                statement: Box::new(statement),
+            });
+        }
 
-        _ => return None,
-    })
+        let attributes = self.attributes()?;
+        if !attributes.is_empty() {
+            let statement = self.statement()?;
+
+            return Ok(Statement::Attributed {
+                attributes,
+                statement: Box::new(statement),
+            });
+        }
+
+        if self.eat(Ty::Keyword(Keyword::Let))? {
+            return self.let_statement();
+        }
+
+        if self.eat(Ty::Keyword(Keyword::Macro))? {
+            if !self.constructs.macros {
+                return Err(ParsErr::Disallowed("macros"));
+            }
+
+            return Ok(Statement::Macro(self.macro_definition()?));
+        }
+
+        let associativity = match self.peek_token_ty()? {
+            Some(Ty::Keyword(Keyword::Infixl)) => Some(Associativity::Left),
+            Some(Ty::Keyword(Keyword::Infixr)) => Some(Associativity::Right),
+            Some(Ty::Keyword(Keyword::Infix)) => Some(Associativity::None),
+            _ => None,
+        };
+        if let Some(associativity) = associativity {
+            if !self.constructs.operator_declarations {
+                return Err(ParsErr::Disallowed("operator declarations"));
+            }
+
+            self.next_token()?;
+            return self.fixity_declaration(associativity);
+        }
+
+        let expr = self.expr()?;
+        let semicolon = self.eat(Ty::Punctuation(Punctuation::Semicolon))?;
+        let line_end = self.at_line_end();
+
+        let end = matches!(
+            self.peek_token_ty()?,
+            None | Some(Ty::Dedent)
+                | Some(Ty::Delimeter(Delimeter {
+                    ty: DelimeterType::Curly,
+                    side: DelimeterSide::Right,
+                }))
+        );
+
+        if !semicolon && !line_end && !end && !expr.is_block_like() {
+            return Err(ParsErr::unexpected(
+                self.peek_token_ty()?.cloned(),
+                "a semicolon after the expression",
+            ));
+        }
+
+        Ok(Statement::Expression { expr, semicolon })
+    }
+
+    /// Parses the `#[...]` and `@...` attributes before a statement or expression.
+    fn attributes(&mut self) -> Result<Vec<Attribute<'s>>, ParsErr> {
+        let mut attributes = vec![];
+
+        while let Some(Ty::Punctuation(Punctuation::HashSymbol | Punctuation::AtSign)) =
+            self.peek_token_ty()?
+        {
+            let t = self.next_token()?.unwrap();
+            attributes.push(self.attribute(t)?);
+        }
+
+        Ok(attributes)
+    }
+
+    /// Parses an attribute after its starting `#` or `@` token.
+    fn attribute(&mut self, start: Token<'s>) -> Result<Attribute<'s>, ParsErr> {
+        let node_start = start.position.absolute_position;
+        let attribute = self.attribute_inner(start)?;
+        self.finish_node(NodeKind::Attribute, node_start);
+
+        Ok(attribute)
+    }
+
+    fn attribute_inner(&mut self, start: Token<'s>) -> Result<Attribute<'s>, ParsErr> {
+        if !self.constructs.attributes {
+            return Err(ParsErr::Disallowed("attributes"));
+        }
+
+        let style = if start.ty == Ty::Punctuation(Punctuation::HashSymbol) {
+            if !self.eat(Ty::Delimeter(Delimeter {
+                ty: DelimeterType::Square,
+                side: DelimeterSide::Left,
+            }))? {
+                return Err(ParsErr::unexpected(
+                    self.peek_token_ty()?.cloned(),
+                    "a left square bracket after the hash symbol",
+                ));
+            }
+
+            AttributeStyle::Hash
+        } else {
+            AttributeStyle::At
+        };
+
+        let (first, position) = match self.next_token()? {
+            Some(t) => {
+                let position = t.position;
+                (t, position)
+            }
+            None => return Err(ParsErr::UnexpectedEnd),
+        };
+
+        let path = self.path(first, false)?;
+
+        let arguments = if self.eat(Ty::Delimeter(Delimeter {
+            ty: DelimeterType::Parentheses,
+            side: DelimeterSide::Left,
+        }))? {
+            let (arguments, _) =
+                self.with_restrictions(Restrictions::brackets(), Self::paren_items)?;
+            arguments
+        } else {
+            vec![]
+        };
+
+        if style == AttributeStyle::Hash
+            && !self.eat(Ty::Delimeter(Delimeter {
+                ty: DelimeterType::Square,
+                side: DelimeterSide::Right,
+            }))?
+        {
+            return Err(ParsErr::unexpected(
+                self.peek_token_ty()?.cloned(),
+                "a right square bracket closing the attribute",
+            ));
+        }
+
+        if let Some(registry) = &self.attribute_registry {
+            if !registry.is_known(&path) {
+                self.diagnostics
+                    .extend([Diagnostic::new(DiagnosticType::UnknownAttribute, position)]);
+            }
+        }
+
+        Ok(Attribute {
+            style,
+            path,
+            arguments,
+            position,
+        })
+    }
+
+    fn let_statement(&mut self) -> Result<Statement<'s>, ParsErr> {
+        let pattern = self.pattern()?;
+
+        let ty = if self.eat(Ty::Punctuation(Punctuation::Colon))? {
+            Some(self.ty()?)
+        } else {
+            None
+        };
+
+        let value = if self.eat(Ty::Operator(Op::Equals))? {
+            Some(self.expr()?)
+        } else {
+            None
+        };
+
+        if !self.eat_statement_end()? {
+            return Err(ParsErr::unexpected(
+                self.peek_token_ty()?.cloned(),
+                "a semicolon after the let statement",
+            ));
+        }
+
+        Ok(Statement::Let { pattern, ty, value })
+    }
+
+    pub fn ty(&mut self) -> Result<Type<'s>, ParsErr> {
+        let start = self.node_start();
+        let ty = self.ty_inner()?;
+        self.finish_node(NodeKind::Type, start);
+
+        Ok(ty)
+    }
+
+    fn ty_inner(&mut self) -> Result<Type<'s>, ParsErr> {
+        Ok(match self.next_token()? {
+            Some(Token {
+                ty: Ty::Keyword(Keyword::Underscore),
+                ..
+            }) => Type::Infer,
+
+            Some(
+                t @ Token {
+                    ty:
+                        Ty::Identifier
+                        | Ty::Punctuation(Punctuation::DoubleColon)
+                        | Ty::Keyword(Keyword::SelfValue | Keyword::Super),
+                    ..
+                },
+            ) => Type::Path(self.path(t, true)?),
+
+            Some(Token {
+                ty:
+                    Ty::Delimeter(Delimeter {
+                        ty: DelimeterType::Parentheses,
+                        side: DelimeterSide::Left,
+                    }),
+                ..
+            }) => match self.type_list()? {
+                (mut items, false) if items.len() == 1 => items.pop().unwrap(),
+                (items, _) => Type::Tuple(items),
+            },
+
+            Some(Token {
+                ty:
+                    Ty::Delimeter(Delimeter {
+                        ty: DelimeterType::Square,
+                        side: DelimeterSide::Left,
+                    }),
+                ..
+            }) => {
+                let element = Box::new(self.ty()?);
+
+                let length = if self.eat(Ty::Punctuation(Punctuation::Semicolon))? {
+                    Some(Box::new(self.expr()?))
+                } else {
+                    None
+                };
+
+                if !self.eat(Ty::Delimeter(Delimeter {
+                    ty: DelimeterType::Square,
+                    side: DelimeterSide::Right,
+                }))? {
+                    return Err(ParsErr::unexpected(
+                        self.peek_token_ty()?.cloned(),
+                        "a matching right square bracket",
+                    ));
+                }
+
+                Type::Array { element, length }
+            }
+
+            Some(Token {
+                ty: Ty::Keyword(Keyword::Fn),
+                ..
+            }) => {
+                if !self.eat(Ty::Delimeter(Delimeter {
+                    ty: DelimeterType::Parentheses,
+                    side: DelimeterSide::Left,
+                }))? {
+                    return Err(ParsErr::unexpected(
+                        self.peek_token_ty()?.cloned(),
+                        "a left parenthesis opening the parameter types",
+                    ));
+                }
+
+                let (parameters, _) = self.type_list()?;
+
+                let output = if self.eat(Ty::Punctuation(Punctuation::Arrow))? {
+                    self.ty()?
+                } else {
+                    Type::Tuple(vec![])
+                };
+
+                Type::Function {
+                    parameters,
+                    output: Box::new(output),
+                }
+            }
+
+            Some(t) => return Err(ParsErr::unexpected(Some(t.ty), "a type")),
+
+            None => return Err(ParsErr::UnexpectedEnd),
+        })
+    }
+
+    /// Parses comma separated types up to and including the closing
+    /// parenthesis. Also returns whether there was a trailing comma.
+    fn type_list(&mut self) -> Result<(Vec<Type<'s>>, bool), ParsErr> {
+        let mut items = vec![];
+        let mut trailing_comma = false;
+
+        loop {
+            if self.eat(Ty::Delimeter(Delimeter {
+                ty: DelimeterType::Parentheses,
+                side: DelimeterSide::Right,
+            }))? {
+                break;
+            }
+
+            items.push(self.ty()?);
+            trailing_comma = false;
+
+            match self.next_token_ty()? {
+                Some(Ty::Delimeter(Delimeter {
+                    ty: DelimeterType::Parentheses,
+                    side: DelimeterSide::Right,
+                })) => break,
+
+                Some(Ty::Punctuation(Punctuation::Comma)) => trailing_comma = true,
+
+                found => {
+                    return Err(ParserError::Unexpected {
+                        found,
+                        expected: "a comma or a matching right parenthesis",
+                    })
+                }
+            }
+        }
+
+        Ok((items, trailing_comma))
+    }
+
+    /// Parses types after a `<` up to and including the closing `>`.
+    fn generic_arguments(&mut self) -> Result<Vec<Type<'s>>, ParsErr> {
+        let mut arguments = vec![];
+
+        loop {
+            if self.eat_generics_end()? {
+                break;
+            }
+
+            arguments.push(self.ty()?);
+
+            if self.eat_generics_end()? {
+                break;
+            }
+
+            if !self.eat(Ty::Punctuation(Punctuation::Comma))? {
+                return Err(ParsErr::unexpected(
+                    self.peek_token_ty()?.cloned(),
+                    "a comma or a closing angle bracket",
+                ));
+            }
+        }
+
+        Ok(arguments)
+    }
+
+    /// Eats a `>`, splitting `>>` into two when needed. The second half is
+    /// left as a `>` token, for the enclosing list or as an unexpected token.
+    fn eat_generics_end(&mut self) -> Result<bool, ParsErr> {
+        if self.peek_token_ty()? == Some(&Ty::Operator(Op::RightShift)) {
+            self.split_right_shift();
+        }
+
+        self.eat(Ty::Operator(Op::GreaterThan))
+    }
+
+    /// Replaces the `>>` token at the front of the lookahead by two `>`.
+    fn split_right_shift(&mut self) {
+        let Some(Lookahead {
+            item: Ok(token), ..
+        }) = self.lookahead.front_mut()
+        else {
+            return;
+        };
+
+        let position = token.position;
+        let half = |offset: usize| Token {
+            position: TokenPosition {
+                absolute_position: position.absolute_position + offset,
+                line: position.line,
+                column: position.column + offset,
+                text: &position.text[offset..offset + 1],
+            },
+            ty: Ty::Operator(Op::GreaterThan),
+        };
+
+        *token = half(0);
+        self.lookahead.insert(
+            1,
+            Lookahead {
+                item: Ok(half(1)),
+                newline_before: false,
+                docs: vec![],
+            },
+        );
+    }
+
+    /// Parses what follows a left parenthesis. This is either a grouping or,
+    /// if a fat arrow follows the closing parenthesis, the parameter list of
+    /// a lambda. Both are parsed as a list of expressions first, which then
+    /// get reinterpreted as parameters when needed.
+    fn paren_expr(&mut self) -> Result<Expr<'s>, ParsErr> {
+        let (items, trailing_comma) =
+            self.with_restrictions(Restrictions::brackets(), Self::paren_items)?;
+
+        self.finish_paren_expr(items, trailing_comma)
+    }
+
+    /// Parses comma separated expressions up to and including the closing
+    /// parenthesis. Also returns whether there was a trailing comma.
+    fn paren_items(&mut self) -> Result<(Vec<Expr<'s>>, bool), ParsErr> {
+        let mut items = vec![];
+        let mut trailing_comma = false;
+
+        loop {
+            if self.eat(Ty::Delimeter(Delimeter {
+                ty: DelimeterType::Parentheses,
+                side: DelimeterSide::Right,
+            }))? {
+                break;
+            }
+
+            items.push(self.expr()?);
+            trailing_comma = false;
+
+            match self.next_token_ty()? {
+                Some(Ty::Delimeter(Delimeter {
+                    ty: DelimeterType::Parentheses,
+                    side: DelimeterSide::Right,
+                })) => break,
+
+                Some(Ty::Punctuation(Punctuation::Comma)) => trailing_comma = true,
+
+                found => {
+                    return Err(ParserError::Unexpected {
+                        found,
+                        expected: "a comma or a matching right parenthesis",
+                    })
+                }
+            }
+        }
+
+        Ok((items, trailing_comma))
+    }
+
+    /// A single expression without a trailing comma is a grouping, anything
+    /// else is a tuple: `()`, `(x,)`, `(x, y)`.
+    fn finish_paren_expr(
+        &mut self,
+        mut items: Vec<Expr<'s>>,
+        trailing_comma: bool,
+    ) -> Result<Expr<'s>, ParsErr> {
+        if self.lambdas_allowed() && self.eat(Ty::Punctuation(Punctuation::FatArrow))? {
+            let parameters = items
+                .into_iter()
+                .map(Parameter::try_from)
+                .collect::<Result<_, _>>()?;
+
+            return self.lambda_expr(parameters);
+        }
+
+        if items.len() == 1 && !trailing_comma {
+            return Ok(items.pop().unwrap());
+        }
+
+        Ok(Expr::Tuple {
+            items,
+            span: Span::default(),
+        })
+    }
+
+    /// Parses what follows a left square bracket: `[a, b, c]` or `[value; count]`.
+    fn array_expr(&mut self) -> Result<Expr<'s>, ParsErr> {
+        let mut items = vec![];
+
+        loop {
+            if self.eat(Ty::Delimeter(Delimeter {
+                ty: DelimeterType::Square,
+                side: DelimeterSide::Right,
+            }))? {
+                break;
+            }
+
+            items.push(self.expr()?);
+
+            match self.next_token_ty()? {
+                Some(Ty::Delimeter(Delimeter {
+                    ty: DelimeterType::Square,
+                    side: DelimeterSide::Right,
+                })) => break,
+
+                Some(Ty::Punctuation(Punctuation::Comma)) => (),
+
+                Some(Ty::Punctuation(Punctuation::Semicolon)) if items.len() == 1 => {
+                    let count = self.expr()?;
+
+                    if !self.eat(Ty::Delimeter(Delimeter {
+                        ty: DelimeterType::Square,
+                        side: DelimeterSide::Right,
+                    }))? {
+                        return Err(ParsErr::unexpected(
+                            self.peek_token_ty()?.cloned(),
+                            "a matching right square bracket",
+                        ));
+                    }
+
+                    return Ok(Expr::Repeat {
+                        value: Box::new(items.pop().unwrap()),
+                        count: Box::new(count),
+                        span: Span::default(),
+                    });
+                }
+
+                found => {
+                    return Err(ParserError::Unexpected {
+                        found,
+                        expected: "a comma or a matching right square bracket",
+                    })
+                }
+            }
+        }
+
+        Ok(Expr::Array {
+            items,
+            span: Span::default(),
+        })
+    }
+
+    /// Whether the left curly brace just taken opens a map literal rather
+    /// than a block: `{}`, or a colon after its first expression.
+    fn is_map_literal(&mut self) -> Result<bool, ParsErr> {
+        let mut depth = 0usize;
+
+        for n in 0.. {
+            let Some(t) = self.peek_nth_token(n)? else {
+                return Ok(false);
+            };
+
+            match &t.ty {
+                Ty::Delimeter(Delimeter {
+                    side: DelimeterSide::Left,
+                    ..
+                }) => depth += 1,
+
+                Ty::Delimeter(Delimeter {
+                    side: DelimeterSide::Right,
+                    ..
+                }) if depth == 0 => return Ok(n == 0),
+                Ty::Delimeter(Delimeter {
+                    side: DelimeterSide::Right,
+                    ..
+                }) => depth -= 1,
+
+                Ty::Punctuation(Punctuation::Colon) if depth == 0 => return Ok(true),
+                Ty::Punctuation(Punctuation::Semicolon | Punctuation::Comma) if depth == 0 => {
+                    return Ok(false)
+                }
+                Ty::Keyword(Keyword::Let | Keyword::Macro) if n == 0 => return Ok(false),
+
+                _ => (),
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Parses what follows a left curly brace: `{ key: value, ... }`.
+    fn map_expr(&mut self) -> Result<Expr<'s>, ParsErr> {
+        let mut entries = vec![];
+
+        loop {
+            if self.eat(Ty::Delimeter(Delimeter {
+                ty: DelimeterType::Curly,
+                side: DelimeterSide::Right,
+            }))? {
+                break;
+            }
+
+            let key = self.expr()?;
+
+            if !self.eat(Ty::Punctuation(Punctuation::Colon))? {
+                return Err(ParsErr::unexpected(
+                    self.peek_token_ty()?.cloned(),
+                    "a colon after the map key",
+                ));
+            }
+
+            let value = self.expr()?;
+            entries.push((key, value));
+
+            match self.next_token_ty()? {
+                Some(Ty::Delimeter(Delimeter {
+                    ty: DelimeterType::Curly,
+                    side: DelimeterSide::Right,
+                })) => break,
+
+                Some(Ty::Punctuation(Punctuation::Comma)) => (),
+
+                found => {
+                    return Err(ParserError::Unexpected {
+                        found,
+                        expected: "a comma or a matching right curly brace",
+                    })
+                }
+            }
+        }
+
+        Ok(Expr::Map {
+            entries,
+            span: Span::default(),
+        })
+    }
+
+    fn lambda_expr(&mut self, parameters: Vec<Parameter<'s>>) -> Result<Expr<'s>, ParsErr> {
+        let body = self.expr()?;
+
+        Ok(Expr::Lambda {
+            parameters,
+            body: Box::new(body),
+            span: Span::default(),
+        })
+    }
+
+    /// Parses the expression before a block, where struct literals are not allowed.
+    fn condition(&mut self) -> Result<Expr<'s>, ParsErr> {
+        let restrictions = Restrictions {
+            no_struct_literal: true,
+            ..self.restrictions
+        };
+
+        self.with_restrictions(restrictions, Self::expr)
+    }
+
+    fn if_expr(&mut self) -> Result<Expr<'s>, ParsErr> {
+        let condition = self.condition()?;
+        let then = self.block()?;
+
+        let otherwise = if !self.eat(Ty::Keyword(Keyword::Else))? {
+            None
+        } else if self.eat(Ty::Keyword(Keyword::If))? {
+            Some(self.if_expr()?)
+        } else {
+            Some(Expr::Block(self.block()?))
+        };
+
+        Ok(Expr::If {
+            condition: Box::new(condition),
+            then,
+            otherwise: otherwise.map(Box::new),
+            span: Span::default(),
+        })
+    }
+
+    /// Parses a curly brace delimited list of statements.
+    fn block(&mut self) -> Result<Block<'s>, ParsErr> {
+        let start = self.node_start();
+        let span_start = self.span_start();
+        let mut block = self.block_inner()?;
+        self.finish_node(NodeKind::Block, start);
+
+        block.span = Span::new(span_start, self.last_end);
+        Ok(block)
+    }
+
+    fn block_inner(&mut self) -> Result<Block<'s>, ParsErr> {
+        if self.indentation_blocks
+            && matches!(
+                self.peek_token_ty()?,
+                Some(Ty::Indent | Ty::Punctuation(Punctuation::Colon))
+            )
+        {
+            return self.indented_block();
+        }
+
+        if !self.eat(Ty::Delimeter(Delimeter {
+            ty: DelimeterType::Curly,
+            side: DelimeterSide::Left,
+        }))? {
+            return Err(ParsErr::unexpected(
+                self.peek_token_ty()?.cloned(),
+                "a curly brace opening a block",
+            ));
+        }
+
+        self.block_statements()
+    }
+
+    /// Parses a block expression after its left curly brace, which starts
+    /// at `start`.
+    fn block_after_brace(&mut self, start: usize) -> Result<Block<'s>, ParsErr> {
+        let mut block = self.block_statements()?;
+        self.finish_node(NodeKind::Block, start);
+
+        block.span = Span::new(start, self.last_end);
+        Ok(block)
+    }
+
+    /// Parses the statements of a block up to and including the right curly brace.
+    fn block_statements(&mut self) -> Result<Block<'s>, ParsErr> {
+        self.with_restrictions(Restrictions::default(), |p| {
+            let mut statements = vec![];
+
+            while !p.eat(Ty::Delimeter(Delimeter {
+                ty: DelimeterType::Curly,
+                side: DelimeterSide::Right,
+            }))? {
+                statements.push(p.statement()?);
+            }
+
+            Ok(Block::new(statements, Span::default()))
+        })
+    }
+
+    /// Parses a block of indented lines, optionally after a colon.
+    fn indented_block(&mut self) -> Result<Block<'s>, ParsErr> {
+        self.eat(Ty::Punctuation(Punctuation::Colon))?;
+
+        if !self.eat(Ty::Indent)? {
+            return Err(ParsErr::unexpected(
+                self.peek_token_ty()?.cloned(),
+                "an indented line opening a block",
+            ));
+        }
+
+        self.with_restrictions(Restrictions::default(), |p| {
+            let mut statements = vec![];
+
+            while !p.eat(Ty::Dedent)? {
+                statements.push(p.statement()?);
+            }
+
+            Ok(Block::new(statements, Span::default()))
+        })
+    }
+
+    /// Parses the fields of a struct literal after the opening curly brace.
+    fn struct_expr(&mut self, path: Path<'s>) -> Result<Expr<'s>, ParsErr> {
+        let mut fields = vec![];
+        let mut base = None;
+
+        loop {
+            if self.eat(Ty::Delimeter(Delimeter {
+                ty: DelimeterType::Curly,
+                side: DelimeterSide::Right,
+            }))? {
+                break;
+            }
+
+            if self.eat(Ty::Punctuation(Punctuation::DoubleDot))? {
+                base = Some(Box::new(self.expr()?));
+
+                if !self.eat(Ty::Delimeter(Delimeter {
+                    ty: DelimeterType::Curly,
+                    side: DelimeterSide::Right,
+                }))? {
+                    return Err(ParsErr::unexpected(
+                        self.peek_token_ty()?.cloned(),
+                        "a curly brace closing the struct after the base expression",
+                    ));
+                }
+
+                break;
+            }
+
+            let name = match self.next_token()? {
+                Some(
+                    t @ Token {
+                        ty: Ty::Identifier, ..
+                    },
+                ) => t.text(),
+
+                t => return Err(ParsErr::unexpected(t.map(|t| t.ty), "a field name")),
+            };
+
+            let value = if self.eat(Ty::Punctuation(Punctuation::Colon))? {
+                Some(self.expr()?)
+            } else {
+                None
+            };
+
+            fields.push(FieldInit { name, value });
+
+            match self.next_token_ty()? {
+                Some(Ty::Delimeter(Delimeter {
+                    ty: DelimeterType::Curly,
+                    side: DelimeterSide::Right,
+                })) => break,
+
+                Some(Ty::Punctuation(Punctuation::Comma)) => (),
+
+                found => {
+                    return Err(ParserError::Unexpected {
+                        found,
+                        expected: "a comma or a curly brace closing the struct",
+                    })
+                }
+            }
+        }
+
+        Ok(Expr::Struct {
+            path,
+            fields,
+            base,
+            span: Span::default(),
+        })
+    }
+
+    /// Parses a path starting with `first`, which is either its first
+    /// segment, or the `::` of an absolute path.
+    ///
+    /// Generic arguments are written as `Vec::<i32>` in expressions and
+    /// patterns, but types also allow the shorter `Vec<i32>`.
+    fn path(&mut self, first: Token<'s>, in_type: bool) -> Result<Path<'s>, ParsErr> {
+        let absolute = first.ty == Ty::Punctuation(Punctuation::DoubleColon);
+        let start = first.position.absolute_position;
+
+        let mut segments = vec![];
+        let mut segment = if absolute {
+            self.next_token()?
+        } else {
+            Some(first)
+        };
+
+        loop {
+            let mut s = match segment {
+                Some(Token {
+                    ty: Ty::Identifier,
+                    position,
+                }) => PathSegment::name(position.text),
+
+                Some(Token {
+                    ty: Ty::Keyword(Keyword::SelfValue),
+                    ..
+                }) => PathSegment::SelfModule,
+
+                Some(Token {
+                    ty: Ty::Keyword(Keyword::Super),
+                    ..
+                }) => PathSegment::Super,
+
+                t => return Err(ParsErr::unexpected(t.map(|t| t.ty), "a path segment")),
+            };
+
+            let mut has_separator = self.eat(Ty::Punctuation(Punctuation::DoubleColon))?;
+
+            let generics_start = if in_type && !has_separator {
+                self.eat(Ty::Operator(Op::LesserThan))?
+            } else {
+                has_separator && self.eat(Ty::Operator(Op::LesserThan))?
+            };
+
+            if generics_start {
+                let PathSegment::Name { generics, .. } = &mut s else {
+                    return Err(ParsErr::unexpected(
+                        Some(Ty::Operator(Op::LesserThan)),
+                        "generic arguments only after a name",
+                    ));
+                };
+
+                *generics = self.generic_arguments()?;
+                has_separator = self.eat(Ty::Punctuation(Punctuation::DoubleColon))?;
+            }
+
+            segments.push(s);
+
+            if !has_separator {
+                break;
+            }
+
+            segment = self.next_token()?;
+        }
+
+        let mut path = Path::new(absolute, segments);
+        path.span = Span::new(start, self.last_end);
+
+        Ok(path)
+    }
+
+    fn match_expr(&mut self) -> Result<Expr<'s>, ParsErr> {
+        let scrutinee = self.condition()?;
+
+        if !self.eat(Ty::Delimeter(Delimeter {
+            ty: DelimeterType::Curly,
+            side: DelimeterSide::Left,
+        }))? {
+            return Err(ParsErr::unexpected(
+                self.peek_token_ty()?.cloned(),
+                "a curly brace opening the match arms",
+            ));
+        }
+
+        // the arms are between braces, like the statements of a block
+        let arms = self.with_restrictions(Restrictions::default(), Self::match_arms)?;
+        self.check_unreachable_arms(&arms);
+
+        Ok(Expr::Match {
+            scrutinee: Box::new(scrutinee),
+            arms,
+            span: Span::default(),
+        })
+    }
+
+    /// Parses the arms of a match up to and including the right curly brace.
+    fn match_arms(&mut self) -> Result<Vec<MatchArm<'s>>, ParsErr> {
+        let mut arms = vec![];
+
+        loop {
+            if self.eat(Ty::Delimeter(Delimeter {
+                ty: DelimeterType::Curly,
+                side: DelimeterSide::Right,
+            }))? {
+                break;
+            }
+
+            let position = match self.peek_token()? {
+                Some(t) => t.position,
+                None => return Err(ParsErr::UnexpectedEnd),
+            };
+
+            let pattern = self.pattern()?;
+
+            let guard = if self.eat(Ty::Keyword(Keyword::If))? {
+                let restrictions = Restrictions {
+                    no_lambda: true,
+                    ..self.restrictions
+                };
+
+                Some(self.with_restrictions(restrictions, Self::expr)?)
+            } else {
+                None
+            };
+
+            if !self.eat(Ty::Punctuation(Punctuation::FatArrow))? {
+                return Err(ParsErr::unexpected(
+                    self.peek_token_ty()?.cloned(),
+                    "a fat arrow after the match pattern",
+                ));
+            }
+
+            let body = self.expr()?;
+
+            arms.push(MatchArm {
+                pattern,
+                guard,
+                body,
+                position,
+            });
+            self.finish_node(NodeKind::MatchArm, position.absolute_position);
+
+            match self.next_token_ty()? {
+                Some(Ty::Delimeter(Delimeter {
+                    ty: DelimeterType::Curly,
+                    side: DelimeterSide::Right,
+                })) => break,
+
+                Some(Ty::Punctuation(Punctuation::Comma)) => (),
+
+                found => {
+                    return Err(ParserError::Unexpected {
+                        found,
+                        expected: "a comma after the match arm, or a curly brace closing the match",
+                    })
+                }
+            }
+        }
+
+        Ok(arms)
+    }
+
+    /// Warns about arms whose pattern is already covered by an earlier,
+    /// unguarded arm.
+    fn check_unreachable_arms(&mut self, arms: &[MatchArm<'s>]) {
+        for (i, arm) in arms.iter().enumerate() {
+            let unreachable = arms[..i]
+                .iter()
+                .any(|prev| prev.guard.is_none() && prev.pattern.covers(&arm.pattern, &self.units));
+
+            if unreachable {
+                self.diagnostics.extend([Diagnostic::new(
+                    DiagnosticType::UnreachableMatchArm,
+                    arm.position,
+                )]);
+            }
+        }
+    }
+
+    pub fn pattern(&mut self) -> Result<Pattern<'s>, ParsErr> {
+        let start = self.node_start();
+        let pattern = self.pattern_inner()?;
+        self.finish_node(NodeKind::Pattern, start);
+
+        Ok(pattern)
+    }
+
+    fn pattern_inner(&mut self) -> Result<Pattern<'s>, ParsErr> {
+        let first = self.pattern_primary()?;
+
+        if !matches!(self.peek_token_ty()?, Some(Ty::Operator(Op::SingleOr))) {
+            return Ok(first);
+        }
+
+        let mut alternatives = vec![first];
+        while self.eat(Ty::Operator(Op::SingleOr))? {
+            alternatives.push(self.pattern_primary()?);
+        }
+
+        Ok(Pattern::Or(alternatives))
+    }
+
+    fn pattern_primary(&mut self) -> Result<Pattern<'s>, ParsErr> {
+        Ok(match self.next_token()? {
+            Some(Token {
+                ty: Ty::Keyword(Keyword::Underscore),
+                ..
+            }) => Pattern::Wildcard,
+
+            Some(Token {
+                ty: Ty::Literal(value),
+                position,
+            }) => {
+                self.check_unnegated_literal(&value, position);
+                self.literal_pattern(PatternLiteral::new(value))?
+            }
+
+            Some(Token {
+                ty: Ty::Operator(Op::Minus),
+                ..
+            }) => {
+                let value = self.negative_literal()?;
+                self.literal_pattern(value)?
+            }
+
+            Some(Token {
+                ty: Ty::Punctuation(p @ (Punctuation::DoubleDot | Punctuation::DoubleDotEquals)),
+                ..
+            }) => Pattern::Range {
+                start: None,
+                end: self.range_pattern_end()?,
+                inclusive: p == Punctuation::DoubleDotEquals,
+            },
+
+            Some(
+                t @ Token {
+                    ty:
+                        Ty::Identifier
+                        | Ty::Punctuation(Punctuation::DoubleColon)
+                        | Ty::Keyword(Keyword::SelfValue | Keyword::Super),
+                    ..
+                },
+            ) => {
+                let path = self.path(t, false)?;
+
+                if self.eat(Ty::Delimeter(Delimeter {
+                    ty: DelimeterType::Parentheses,
+                    side: DelimeterSide::Left,
+                }))? {
+                    let (fields, _) = self.pattern_list()?;
+                    Pattern::TupleStruct { path, fields }
+                } else if let Some(name) = path.as_identifier() {
+                    Pattern::Binding(name)
+                } else {
+                    Pattern::Path(path)
+                }
+            }
+
+            Some(Token {
+                ty:
+                    Ty::Delimeter(Delimeter {
+                        ty: DelimeterType::Parentheses,
+                        side: DelimeterSide::Left,
+                    }),
+                ..
+            }) => match self.pattern_list()? {
+                (mut items, false) if items.len() == 1 => items.pop().unwrap(),
+                (items, _) => Pattern::Tuple(items),
+            },
+
+            Some(t) => return Err(ParsErr::unexpected(Some(t.ty), "a pattern")),
+
+            None => return Err(ParsErr::UnexpectedEnd),
+        })
+    }
+
+    /// Parses comma separated patterns up to and including the closing
+    /// parenthesis. Also returns whether there was a trailing comma.
+    fn pattern_list(&mut self) -> Result<(Vec<Pattern<'s>>, bool), ParsErr> {
+        let mut items = vec![];
+        let mut trailing_comma = false;
+
+        loop {
+            if self.eat(Ty::Delimeter(Delimeter {
+                ty: DelimeterType::Parentheses,
+                side: DelimeterSide::Right,
+            }))? {
+                break;
+            }
+
+            items.push(self.pattern()?);
+            trailing_comma = false;
+
+            match self.next_token_ty()? {
+                Some(Ty::Delimeter(Delimeter {
+                    ty: DelimeterType::Parentheses,
+                    side: DelimeterSide::Right,
+                })) => break,
+
+                Some(Ty::Punctuation(Punctuation::Comma)) => trailing_comma = true,
+
+                found => {
+                    return Err(ParserError::Unexpected {
+                        found,
+                        expected: "a comma or a matching right parenthesis",
+                    })
+                }
+            }
+        }
+
+        Ok((items, trailing_comma))
+    }
+
+    /// A literal pattern, or the start of a range pattern.
+    fn literal_pattern(&mut self, value: PatternLiteral) -> Result<Pattern<'s>, ParsErr> {
+        Ok(match self.range_pattern_inclusive()? {
+            Some(inclusive) => Pattern::Range {
+                start: Some(value),
+                end: self.range_pattern_end()?,
+                inclusive,
+            },
+
+            None => Pattern::Literal(value),
+        })
+    }
+
+    /// The number after the minus of a negative literal pattern like `-1`.
+    fn negative_literal(&mut self) -> Result<PatternLiteral, ParsErr> {
+        match self.next_token_ty()? {
+            Some(Ty::Literal(value @ (Literal::Number(..) | Literal::Quantity { .. }))) => {
+                Ok(PatternLiteral::negative(value))
+            }
+
+            found => Err(ParsErr::unexpected(found, "a number after the minus sign")),
+        }
+    }
+
+    fn range_pattern_inclusive(&mut self) -> Result<Option<bool>, ParsErr> {
+        Ok(
+            if self.eat(Ty::Punctuation(Punctuation::DoubleDotEquals))? {
+                Some(true)
+            } else if self.eat(Ty::Punctuation(Punctuation::DoubleDot))? {
+                Some(false)
+            } else {
+                None
+            },
+        )
+    }
+
+    fn range_pattern_end(&mut self) -> Result<Option<PatternLiteral>, ParsErr> {
+        if let Some(Ty::Literal(_)) = self.peek_token_ty()? {
+            let Some(Token {
+                ty: Ty::Literal(end),
+                position,
+            }) = self.next_token()?
+            else {
+                unreachable!()
+            };
+
+            self.check_unnegated_literal(&end, position);
+            return Ok(Some(PatternLiteral::new(end)));
+        }
+
+        if self.eat(Ty::Operator(Op::Minus))? {
+            return self.negative_literal().map(Some);
+        }
+
+        Ok(None)
+    }
+
+    fn call_expr(&mut self, function: Path<'s>) -> Result<Expr<'s>, ParsErr> {
+        let mut args = vec![];
+
+        loop {
+            if self.eat(Ty::Delimeter(Delimeter {
+                ty: DelimeterType::Parentheses,
+                side: DelimeterSide::Right,
+            }))? {
+                break;
+            }
+
+            let arg = self.expr()?;
+            args.push(arg);
+
+            match self.peek_token_ty()? {
+                Some(Ty::Delimeter(Delimeter {
+                    ty: DelimeterType::Parentheses,
+                    side: DelimeterSide::Right,
+                })) => {
+                    self.next_token()?;
+                    break;
+                }
+
+                Some(Ty::Punctuation(Punctuation::Comma)) => {
+                    self.next_token()?;
+                    continue;
+                }
+
+                ty => {
+                    return Err(ParserError::Unexpected {
+                        found: ty.cloned(),
+                        expected:
+                            "a comma after the argument, or a parenthesis closing the agument list",
+                    })
+                }
+            }
+        }
+
+        Ok(Expr::Call {
+            function,
+            arguments: args,
+            span: Span::default(),
+        })
+    }
+}
+
+impl<'s, 'd, T: Iterator<Item = TokenizerItem<'s>>, D: Extend<Diagnostic<'s>>>
+    Parser<'s, 'd, T, D>
+{
+    /// The operators known at this point, including declared ones.
+    pub fn operators(&self) -> &OperatorTable {
+        &self.operators
+    }
+
+    /// Reports the attributes that aren't in the registry from now on.
+    pub fn check_attributes(&mut self, registry: AttributeRegistry) {
+        self.attribute_registry = Some(registry);
+    }
+
+    /// Remembers the span of every node from now on, for [`cst::build`](crate::cst::build).
+    pub fn record_nodes(&mut self) {
+        self.nodes.get_or_insert_with(Vec::new);
+    }
+
+    /// The spans of the nodes parsed since [`Parser::record_nodes`], inner
+    /// nodes before the ones containing them.
+    pub fn take_nodes(&mut self) -> Vec<NodeSpan> {
+        self.nodes.take().unwrap_or_default()
+    }
+
+    /// Where a node starting with the next token starts, including the doc
+    /// comments before the token.
+    fn node_start(&mut self) -> usize {
+        if self.nodes.is_none() {
+            return 0;
+        }
+
+        self.fill_lookahead(1);
+
+        match self.lookahead.front() {
+            Some(Lookahead { docs, .. }) if !docs.is_empty() => docs[0].position.absolute_position,
+            Some(Lookahead { item: Ok(t), .. }) => t.position.absolute_position,
+
+            _ => self.last_end,
+        }
+    }
+
+    /// Where the next token starts, for the [`Span`] of the syntax starting with it.
+    fn span_start(&mut self) -> usize {
+        self.fill_lookahead(1);
+
+        match self.lookahead.front() {
+            Some(Lookahead { item: Ok(t), .. }) => t.position.absolute_position,
+            _ => self.last_end,
+        }
+    }
+
+    fn finish_node(&mut self, kind: NodeKind, start: usize) {
+        let end = self.last_end.max(start);
+
+        if let Some(nodes) = &mut self.nodes {
+            nodes.push(NodeSpan { kind, start, end });
+        }
+    }
+
+    pub(crate) fn at_end(&mut self) -> Result<bool, ParsErr> {
+        Ok(self.peek_token()?.is_none())
+    }
+
+    /// Parses a single token tree starting with `first`.
+    pub fn token_tree(&mut self, first: Token<'s>) -> Result<TokenTree<'s>, ParsErr> {
+        let Ty::Delimeter(Delimeter {
+            ty: delimiter,
+            side,
+        }) = first.ty
+        else {
+            return Ok(TokenTree::Token(first));
+        };
+
+        if side == DelimeterSide::Right {
+            return Err(ParsErr::unexpected(Some(first.ty), "a token tree"));
+        }
+
+        let mut trees = vec![];
+
+        loop {
+            match self.next_token()? {
+                Some(
+                    close @ Token {
+                        ty:
+                            Ty::Delimeter(Delimeter {
+                                side: DelimeterSide::Right,
+                                ..
+                            }),
+                        ..
+                    },
+                ) => {
+                    if close.ty
+                        != Ty::Delimeter(Delimeter {
+                            ty: delimiter,
+                            side: DelimeterSide::Right,
+                        })
+                    {
+                        return Err(ParsErr::unexpected(
+                            Some(close.ty),
+                            "a matching closing delimiter",
+                        ));
+                    }
+
+                    return Ok(TokenTree::Group {
+                        delimiter,
+                        open: first,
+                        trees,
+                        close,
+                    });
+                }
+
+                Some(t) => trees.push(self.token_tree(t)?),
+
+                None => return Err(ParsErr::UnexpectedEnd),
+            }
+        }
+    }
+
+    /// Parses a macro definition after the `macro` keyword.
+    fn macro_definition(&mut self) -> Result<MacroDefinition<'s>, ParsErr> {
+        let (name, position) = match self.next_token()? {
+            Some(
+                t @ Token {
+                    ty: Ty::Identifier, ..
+                },
+            ) => (t.text(), t.position),
+
+            t => return Err(ParsErr::unexpected(t.map(|t| t.ty), "a macro name")),
+        };
+
+        if !self.eat(Ty::Delimeter(Delimeter {
+            ty: DelimeterType::Curly,
+            side: DelimeterSide::Left,
+        }))? {
+            return Err(ParsErr::unexpected(
+                self.peek_token_ty()?.cloned(),
+                "a curly brace opening the macro rules",
+            ));
+        }
+
+        let mut rules = vec![];
+
+        loop {
+            if self.eat(Ty::Delimeter(Delimeter {
+                ty: DelimeterType::Curly,
+                side: DelimeterSide::Right,
+            }))? {
+                break;
+            }
+
+            let matchers = match self.next_token()? {
+                Some(
+                    t @ Token {
+                        ty:
+                            Ty::Delimeter(Delimeter {
+                                side: DelimeterSide::Left,
+                                ..
+                            }),
+                        ..
+                    },
+                ) => {
+                    let TokenTree::Group { trees, .. } = self.token_tree(t)? else {
+                        unreachable!()
+                    };
+
+                    matchers(trees)?
+                }
+
+                t => return Err(ParsErr::unexpected(t.map(|t| t.ty), "a macro matcher")),
+            };
+
+            if !self.eat(Ty::Punctuation(Punctuation::FatArrow))? {
+                return Err(ParsErr::unexpected(
+                    self.peek_token_ty()?.cloned(),
+                    "a fat arrow after the macro matcher",
+                ));
+            }
+
+            let transcriber = match self.next_token()? {
+                Some(
+                    t @ Token {
+                        ty:
+                            Ty::Delimeter(Delimeter {
+                                side: DelimeterSide::Left,
+                                ..
+                            }),
+                        ..
+                    },
+                ) => {
+                    let TokenTree::Group { trees, .. } = self.token_tree(t)? else {
+                        unreachable!()
+                    };
+
+                    trees
+                }
+
+                t => return Err(ParsErr::unexpected(t.map(|t| t.ty), "a macro transcriber")),
+            };
+
+            rules.push(MacroRule {
+                matchers,
+                transcriber,
+            });
+
+            match self.next_token_ty()? {
+                Some(Ty::Delimeter(Delimeter {
+                    ty: DelimeterType::Curly,
+                    side: DelimeterSide::Right,
+                })) => break,
+
+                Some(Ty::Punctuation(Punctuation::Semicolon | Punctuation::Comma)) => (),
+
+                found => {
+                    return Err(ParserError::Unexpected {
+                        found,
+                        expected:
+                            "a semicolon after the macro rule, or a curly brace closing the macro",
+                    })
+                }
+            }
+        }
+
+        Ok(MacroDefinition {
+            name,
+            rules,
+            position,
+        })
+    }
+}
+
+/// Turns the token trees of a macro rule's left hand side into matchers.
+fn matchers(trees: Vec<TokenTree<'_>>) -> Result<Vec<Matcher<'_>>, ParsErr> {
+    let mut matchers = vec![];
+    let mut trees = trees.into_iter().peekable();
+
+    while let Some(tree) = trees.next() {
+        match tree {
+            TokenTree::Group {
+                delimiter, trees, ..
+            } => {
+                matchers.push(Matcher::Group {
+                    delimiter,
+                    matchers: self::matchers(trees)?,
+                });
+                continue;
+            }
+
+            TokenTree::Token(t) if t.ty == Ty::Punctuation(Punctuation::Dollar) => (),
+            TokenTree::Token(t) => {
+                matchers.push(Matcher::Token(t));
+                continue;
+            }
+        }
+
+        match trees.next() {
+            Some(TokenTree::Token(
+                name @ Token {
+                    ty: Ty::Identifier, ..
+                },
+            )) => {
+                match trees.next() {
+                    Some(TokenTree::Token(Token {
+                        ty: Ty::Punctuation(Punctuation::Colon),
+                        ..
+                    })) => (),
+
+                    t => {
+                        return Err(ParsErr::unexpected(
+                            t.map(|t| t.first_token().ty.clone()),
+                            "a colon after the metavariable name",
+                        ))
+                    }
+                }
+
+                let kind = match trees.next() {
+                    Some(TokenTree::Token(
+                        t @ Token {
+                            ty: Ty::Identifier, ..
+                        },
+                    )) => FragmentKind::from_name(t.text()),
+
+                    _ => None,
+                };
+
+                let Some(kind) = kind else {
+                    return Err(ParsErr::unexpected(
+                        Some(Ty::Identifier),
+                        "a fragment kind: expr, ident, literal, ty, pat or tt",
+                    ));
+                };
+
+                matchers.push(Matcher::Fragment {
+                    name: name.text(),
+                    kind,
+                });
+            }
+
+            Some(TokenTree::Group {
+                delimiter: DelimeterType::Parentheses,
+                trees: inner,
+                ..
+            }) => {
+                let repetition_kind = |t: &TokenTree<'_>| match t {
+                    TokenTree::Token(Token {
+                        ty: Ty::Operator(Op::Star),
+                        ..
+                    }) => Some(RepetitionKind::ZeroOrMore),
+                    TokenTree::Token(Token {
+                        ty: Ty::Operator(Op::Plus),
+                        ..
+                    }) => Some(RepetitionKind::OneOrMore),
+                    TokenTree::Token(Token {
+                        ty: Ty::Punctuation(Punctuation::QuestionMark),
+                        ..
+                    }) => Some(RepetitionKind::ZeroOrOne),
+
+                    _ => None,
+                };
+
+                let separator = match trees.peek() {
+                    Some(t @ TokenTree::Token(separator)) if repetition_kind(t).is_none() => {
+                        let separator = separator.clone();
+                        trees.next();
+                        Some(separator)
+                    }
+
+                    _ => None,
+                };
+
+                let Some(kind) = trees.next().as_ref().and_then(repetition_kind) else {
+                    return Err(ParsErr::unexpected(
+                        None,
+                        "a repetition operator: *, + or ?",
+                    ));
+                };
+
+                matchers.push(Matcher::Repetition {
+                    matchers: self::matchers(inner)?,
+                    separator,
+                    kind,
+                });
+            }
+
+            t => {
+                return Err(ParsErr::unexpected(
+                    t.map(|t| t.first_token().ty.clone()),
+                    "a metavariable or a repetition after the dollar sign",
+                ))
+            }
+        }
+    }
+
+    Ok(matchers)
+}
+
+/// Whether the token can be a part of an infix operator's symbol.
+fn is_operator_symbol(ty: &Ty) -> bool {
+    matches!(
+        ty,
+        Ty::Operator(_)
+            | Ty::Punctuation(
+                Punctuation::Dot
+                    | Punctuation::DoubleDot
+                    | Punctuation::DoubleDotEquals
+                    | Punctuation::TripleDot
+                    | Punctuation::QuestionMark
+                    | Punctuation::Dollar
+                    | Punctuation::DoubleDollar
+                    | Punctuation::AtSign
+                    | Punctuation::HashSymbol
+                    | Punctuation::Arrow
+            )
+    )
+}
+
+/// Writes the items with the separator between them, for the `Display`
+/// implementations of the syntax tree, values and types.
+pub(crate) fn write_separated<T: std::fmt::Display>(
+    f: &mut std::fmt::Formatter<'_>,
+    items: impl IntoIterator<Item = T>,
+    separator: &str,
+) -> std::fmt::Result {
+    let mut items = items.into_iter();
+    if let Some(i) = items.next() {
+        write!(f, "{i}")?;
+    }
+
+    for i in items {
+        write!(f, "{separator}{i}")?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{eval::RuntimeErrorKind, Source};
+
+    /// The diagnostics of the tokenizer and the parser, with the text
+    /// they're about.
+    fn diagnostics(text: &str) -> Vec<(DiagnosticType, &str)> {
+        let source = Source::new("test", text);
+        let (mut tokenizer_diagnostics, mut parser_diagnostics) = (vec![], vec![]);
+
+        let tokenizer = Tokenizer::new(source, &mut tokenizer_diagnostics);
+        Parser::new(tokenizer, &mut parser_diagnostics, OperatorTable::default())
+            .program()
+            .unwrap();
+
+        tokenizer_diagnostics
+            .iter()
+            .chain(&parser_diagnostics)
+            .map(|d| (d.ty().clone(), d.position().text))
+            .collect()
+    }
+
+    /// The text of the literals reported out of range.
+    fn out_of_range(text: &str) -> Vec<&str> {
+        diagnostics(text)
+            .into_iter()
+            .filter(|(ty, _)| matches!(ty, DiagnosticType::LiteralOutOfRange))
+            .map(|(_, text)| text)
+            .collect()
+    }
+
+    #[test]
+    fn signed_minimums_need_a_minus() {
+        assert!(out_of_range("-128i8; 127i8; - -128i8; -9223372036854775808i64;").is_empty());
+
+        assert_eq!(out_of_range("128i8;"), ["128i8"]);
+        assert_eq!(out_of_range("-129i8;"), ["129i8"]);
+        assert_eq!(out_of_range("1 - 32768i16;"), ["32768i16"]);
+        assert_eq!(out_of_range("-128i8 ** 2;"), ["128i8"]);
+        assert_eq!(out_of_range("-f(128i8);"), ["128i8"]);
+    }
+
+    fn program(text: &str) -> Vec<Statement<'_>> {
+        let source = Source::new("test", text);
+        let (mut tokenizer_diagnostics, mut parser_diagnostics) = (vec![], vec![]);
+
+        let tokenizer = Tokenizer::new(source, &mut tokenizer_diagnostics);
+        Parser::new(tokenizer, &mut parser_diagnostics, OperatorTable::default())
+            .program()
+            .unwrap()
+    }
+
+    /// The error the text fails to parse with.
+    fn error(text: &str) -> ParserError {
+        let source = Source::new("test", text);
+        let (mut tokenizer_diagnostics, mut parser_diagnostics) = (vec![], vec![]);
+
+        let tokenizer = Tokenizer::new(source, &mut tokenizer_diagnostics);
+        Parser::new(tokenizer, &mut parser_diagnostics, OperatorTable::default())
+            .program()
+            .unwrap_err()
+    }
+
+    /// The statements parsed from the text, one per line.
+    fn parsed(text: &str) -> String {
+        program(text)
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn brackets_lift_the_restrictions() {
+        assert_eq!(
+            parsed("if f(Point { x: 1 }) { 1 }"),
+            "if (f(Point { x: 1 })) { 1 }"
+        );
+        assert_eq!(
+            parsed("while xs[Point { x: 1 }] { 1 }"),
+            "while (xs[Point { x: 1 }]) { 1 }"
+        );
+        assert_eq!(
+            parsed("match x { a if f(y => y) => 1 }"),
+            "(match x { a if (f(((y) => y))) => 1 })"
+        );
+
+        // outside the brackets the block still belongs to the `if`
+        assert_eq!(parsed("if x { 1 }"), "if x { 1 }");
+    }
+
+    #[test]
+    fn signed_minimums_evaluate() {
+        use crate::{
+            eval::{self, Environment, Value},
+            vm::{Compiler, Vm},
+        };
+
+        for text in [
+            "-9223372036854775808",
+            "-9223372036854775808i64",
+            "match -9223372036854775808 { -9223372036854775808 => -9223372036854775808, _ => 0 }",
+        ] {
+            let statements = program(text);
+            let value = eval::run(&statements, &Environment::with_prelude());
+
+            assert_eq!(value, Ok(Value::Integer(i64::MIN)), "{text}");
+        }
+
+        for text in ["-9223372036854775808", "-9223372036854775808i64"] {
+            let statements = program(text);
+            let chunk = Compiler::new().compile_program(&statements).unwrap();
+            let value = Vm::new().run(&chunk, &Environment::with_prelude());
+
+            assert_eq!(value, Ok(Value::Integer(i64::MIN)), "{text}");
+        }
+
+        let statements = program("-9223372036854775808 - 1");
+        let value = eval::run(&statements, &Environment::with_prelude());
+        assert_eq!(
+            value.map_err(|e| e.kind().clone()),
+            Err(RuntimeErrorKind::Overflow)
+        );
+    }
+
+    #[test]
+    fn signed_minimums_in_patterns() {
+        assert!(out_of_range("match x { -128i8 => 1, -128i8..=-1i8 => 2, _ => 3 };").is_empty());
+
+        assert_eq!(
+            out_of_range("match x { 128i8 => 1, 0i8..128i8 => 2, _ => 3 };"),
+            ["128i8", "128i8"]
+        );
+    }
+
+    #[test]
+    fn unreachable_match_arms() {
+        let unreachable = |text| (DiagnosticType::UnreachableMatchArm, text);
+
+        assert_eq!(
+            diagnostics("match x { _ => 1, 2 => 2, y => 3 }"),
+            [unreachable("2"), unreachable("y")]
+        );
+        assert_eq!(
+            diagnostics("match x { y => 1, (a, b) => 2 }"),
+            [unreachable("(")]
+        );
+        assert_eq!(
+            diagnostics("match x { 1..=5 => 1, 3 => 2, 2..4 => 3 }"),
+            [unreachable("3"), unreachable("2")]
+        );
+        assert_eq!(
+            diagnostics("match x { 1 | 2 => 1, 2 => 2 }"),
+            [unreachable("2")]
+        );
+    }
+
+    #[test]
+    fn reachable_match_arms() {
+        for text in [
+            "match x { 1 => 1, 2 => 2, -1 => 3, 'a' => 4, \"a\" => 5, _ => 6 }",
+            "match x { 0..5 => 1, 5 => 2, 5..=9 => 3, ..0 => 4, 10.. => 5 }",
+            "match x { 1.5 => 1, 1 => 2, 1s => 3, 1000ms => 4 }",
+            // a guard can fail
+            "match x { _ if c => 1, 2 => 2 }",
+            "match x { (1, a) => 1, (a, 1) => 2 }",
+        ] {
+            assert_eq!(diagnostics(text), [], "{text}");
+        }
+    }
+
+    /// The text of the attributes reported unknown, with `inline` and
+    /// `tool::skip` registered, and the parsed statements.
+    fn unknown_attributes(text: &str) -> (Vec<&str>, String) {
+        let source = Source::new("test", text);
+        let (mut tokenizer_diagnostics, mut parser_diagnostics) = (vec![], vec![]);
+
+        let mut registry = AttributeRegistry::new();
+        registry.register("inline").register("tool::skip");
+
+        let tokenizer = Tokenizer::new(source, &mut tokenizer_diagnostics);
+        let mut parser = Parser::new(tokenizer, &mut parser_diagnostics, OperatorTable::default());
+        parser.check_attributes(registry);
+        let statements = parser.program().unwrap();
+
+        let unknown = parser_diagnostics
+            .iter()
+            .filter(|d| matches!(d.ty(), DiagnosticType::UnknownAttribute))
+            .map(|d| d.position().text)
+            .collect();
+        let parsed = statements
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        (unknown, parsed)
+    }
+
+    #[test]
+    fn unknown_attributes_are_reported() {
+        let (unknown, _) =
+            unknown_attributes("#[inline] f(x); @tool::skip x; @inline(always) 1 + 2;");
+        assert!(unknown.is_empty(), "{unknown:?}");
+
+        let (unknown, _) = unknown_attributes("#[inlined] f(x); @tool::other(1) x; @skip y;");
+        assert_eq!(unknown, ["inlined", "tool", "skip"]);
+
+        // everything is known without a registry
+        assert_eq!(diagnostics("#[inlined] f(x);"), []);
+    }
+
+    #[test]
+    fn attributes_on_documented_statements() {
+        let (unknown, parsed) =
+            unknown_attributes("/// a doc\n#[unknown] #[inline] let x = 1;\n/// b doc\n@inline y;");
+
+        assert_eq!(unknown, ["unknown"]);
+        assert_eq!(
+            parsed,
+            "/// a doc\n#[unknown] #[inline] let x = 1;\n/// b doc\n@inline y;"
+        );
+    }
+
+    #[test]
+    fn lambdas() {
+        assert_eq!(parsed("x => x * 2;"), "((x) => (x * 2));");
+        assert_eq!(parsed("(x, y) => x + y;"), "((x, y) => (x + y));");
+        assert_eq!(parsed("() => 1;"), "(() => 1);");
+        assert_eq!(parsed("(x) + y;"), "(x + y);");
+        assert_eq!(parsed("f(x => y => x);"), "(f(((x) => ((y) => x))));");
+    }
+
+    #[test]
+    fn lambda_parameters_must_be_names() {
+        assert!(matches!(
+            error("(x, 1) => x;"),
+            ParserError::InvalidParameter(p) if p == "1"
+        ));
+        assert!(matches!(
+            error("(x + y) => x;"),
+            ParserError::InvalidParameter(p) if p == "(x + y)"
+        ));
+
+        // only a single identifier starts a lambda without parentheses
+        assert!(matches!(
+            error("a::b => 1;"),
+            ParserError::Unexpected {
+                found: Some(Ty::Punctuation(Punctuation::FatArrow)),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn typed_lets() {
+        assert_eq!(parsed("let x: i32 = 1;"), "let x: i32 = 1;");
+        assert_eq!(parsed("let (a, b) = pair;"), "let (a, b) = pair;");
+        assert_eq!(
+            parsed("let (a, _): (i32, _) = pair;"),
+            "let (a, _): (i32, _) = pair;"
+        );
+        assert_eq!(
+            parsed("let f: fn(i32, [u8; 4]) -> (bool,) = g;"),
+            "let f: fn(i32, [u8; 4]) -> (bool,) = g;"
+        );
+        assert_eq!(
+            parsed("let m: HashMap<String, Vec<Vec<i32>>>;"),
+            "let m: HashMap::<String, Vec::<Vec::<i32>>>;"
+        );
+        assert_eq!(parsed("let u: (((i32))) = 1;"), "let u: i32 = 1;");
+    }
+
+    #[test]
+    fn malformed_types() {
+        assert!(matches!(
+            error("let x: = 1;"),
+            ParserError::Unexpected {
+                found: Some(Ty::Operator(Op::Equals)),
+                expected: "a type"
+            }
+        ));
+        assert!(matches!(
+            error("let f: fn i32 = g;"),
+            ParserError::Unexpected {
+                expected: "a left parenthesis opening the parameter types",
+                ..
+            }
+        ));
+        assert!(matches!(
+            error("let xs: [i32; 4 = a;"),
+            ParserError::Unexpected {
+                expected: "a matching right square bracket",
+                ..
+            }
+        ));
+        assert!(matches!(
+            error("let m: Map<K V> = m;"),
+            ParserError::Unexpected {
+                expected: "a comma or a closing angle bracket",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn collection_literals() {
+        assert_eq!(parsed("[1, 2, 3,];"), "[1, 2, 3];");
+        assert_eq!(parsed("[0; 16];"), "[0; 16];");
+        assert_eq!(parsed("[];"), "[];");
+        assert_eq!(parsed("(x,);"), "(x,);");
+        assert_eq!(parsed("(x);"), "x;");
+        assert_eq!(parsed("(a, b,);"), "(a, b);");
+        assert_eq!(parsed("();"), "();");
+        assert_eq!(
+            parsed("m = { \"k\": v, 1: [2], };"),
+            "(m = {\"k\": v, 1: [2]});"
+        );
+    }
+
+    #[test]
+    fn malformed_collections() {
+        assert!(matches!(
+            error("[1, 2 3];"),
+            ParserError::Unexpected {
+                expected: "a comma or a matching right square bracket",
+                ..
+            }
+        ));
+        assert!(matches!(
+            error("(a, b;"),
+            ParserError::Unexpected {
+                expected: "a comma or a matching right parenthesis",
+                ..
+            }
+        ));
+        assert!(matches!(
+            error("m = { \"k\": v, \"l\" w };"),
+            ParserError::Unexpected {
+                expected: "a colon after the map key",
+                ..
+            }
+        ));
+        assert!(matches!(
+            error("m = { 1: 2 3: 4 };"),
+            ParserError::Unexpected {
+                expected: "a comma or a matching right curly brace",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn turbofish_paths() {
+        assert_eq!(parsed("Vec::<i32>::new();"), "(Vec::<i32>::new());");
+        assert_eq!(parsed("HashMap::<K, V>;"), "HashMap::<K, V>;");
+        assert_eq!(
+            parsed("::std::mem::swap(a, b);"),
+            "(::std::mem::swap(a, b));"
+        );
+        assert_eq!(parsed("self::f(super::x);"), "(self::f(super::x));");
+        assert_eq!(
+            parsed("f::<Vec<Vec<i32>>>(x);"),
+            "(f::<Vec::<Vec::<i32>>>(x));"
+        );
+        // without a turbofish `<` is a comparison
+        assert_eq!(parsed("a < b;"), "(a < b);");
+    }
+
+    #[test]
+    fn malformed_paths() {
+        assert!(matches!(
+            error("a::1;"),
+            ParserError::Unexpected {
+                expected: "a path segment",
+                ..
+            }
+        ));
+        assert!(matches!(
+            error("self::<i32>::f;"),
+            ParserError::Unexpected {
+                expected: "generic arguments only after a name",
+                ..
+            }
+        ));
+        assert!(matches!(
+            error("f::<i32 u8>();"),
+            ParserError::Unexpected {
+                expected: "a comma or a closing angle bracket",
+                ..
+            }
+        ));
+        assert!(matches!(
+            error("f::<i32"),
+            ParserError::Unexpected { found: None, .. }
+        ));
+    }
+
+    #[test]
+    fn pipelines_and_declared_operators() {
+        assert_eq!(parsed("x |> f |> g(1);"), "((x |> f) |> (g(1)));");
+        assert_eq!(parsed("x + 1 |> f;"), "((x + 1) |> f);");
+
+        assert_eq!(
+            parsed("infixl 6 <+>;\na <+> b <+> c * d;"),
+            "infixl 6 <+>;\n((a <+> b) <+> (c * d));"
+        );
+        assert_eq!(
+            parsed("infixr 8 ^^;\na ^^ b ^^ c + d;"),
+            "infixr 8 ^^;\n((a ^^ (b ^^ c)) + d);"
+        );
+        assert_eq!(parsed("infix 4 ===; a === b;"), "infix 4 ===;\n(a === b);");
+    }
+
+    #[test]
+    fn malformed_operator_declarations() {
+        // a declaration only applies to what follows it
+        assert!(matches!(
+            error("a <+> b; infixl 6 <+>;"),
+            ParserError::Unexpected {
+                found: Some(Ty::Operator(Op::GreaterThan)),
+                ..
+            }
+        ));
+        assert!(matches!(
+            error("infix 4 ===; a === b === c;"),
+            ParserError::NonAssociative(symbol) if symbol == "==="
+        ));
+        assert!(matches!(
+            error("infixl 10 <+>;"),
+            ParserError::Unexpected {
+                expected: "an operator precedence level from 0 to 9",
+                ..
+            }
+        ));
+        assert!(matches!(
+            error("infixl 6 foo;"),
+            ParserError::Unexpected {
+                expected: "an operator symbol",
+                ..
+            }
+        ));
+    }
+
+    /// The statements parsed with automatic semicolons, one per line, and the
+    /// diagnostics with the text they're about.
+    fn script(text: &str) -> (String, Vec<(DiagnosticType, &str)>) {
+        let source = Source::new("test", text);
+        let dialect = Dialect::script();
+        let (mut tokenizer_diagnostics, mut parser_diagnostics) = (vec![], vec![]);
+
+        let tokenizer = Tokenizer::with_dialect(source, &mut tokenizer_diagnostics, &dialect);
+        let statements = Parser::with_dialect(tokenizer, &mut parser_diagnostics, &dialect)
+            .program()
+            .unwrap();
+
+        let parsed = statements
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let diagnostics = tokenizer_diagnostics
+            .iter()
+            .chain(&parser_diagnostics)
+            .map(|d| (d.ty().clone(), d.position().text))
+            .collect();
+
+        (parsed, diagnostics)
+    }
+
+    #[test]
+    fn automatic_semicolons() {
+        let (parsed, diagnostics) = script("let a = 1\nlet b = a +\n    2\nf(a,\n  b)\n");
+        assert_eq!(parsed, "let a = 1;\nlet b = (a + 2);\n(f(a, b))");
+        assert_eq!(diagnostics, []);
+
+        let (parsed, _) = script("a; b\nc");
+        assert_eq!(parsed, "a;\nb\nc");
+    }
+
+    #[test]
+    fn ambiguous_line_breaks() {
+        let (parsed, diagnostics) = script("a\n- b\n");
+        assert_eq!(parsed, "a\n(-b)");
+        assert_eq!(diagnostics, [(DiagnosticType::AmbiguousLineBreak, "-")]);
+
+        let (parsed, diagnostics) = script("f\n(x)\n");
+        assert_eq!(parsed, "f\nx");
+        assert_eq!(diagnostics, [(DiagnosticType::AmbiguousLineBreak, "(")]);
+
+        // inside brackets a line break can't end the statement
+        let (_, diagnostics) = script("f(a\n- b)\n");
+        assert_eq!(diagnostics, []);
+    }
+
+    #[test]
+    fn doc_comments_are_attached() {
+        let statements = program("/// a\n///b\n/** c\n */\nlet x = 1;\nf(x);");
+        let docs = statements[0]
+            .docs()
+            .iter()
+            .map(|d| d.content())
+            .collect::<Vec<_>>();
+
+        assert_eq!(docs, ["a", "b", " c\n "]);
+        assert!(statements[1].docs().is_empty());
+        assert_eq!(
+            parsed("/// a\n/** b */ let x = 1;"),
+            "/// a\n/** b */ let x = 1;"
+        );
+    }
+
+    #[test]
+    fn unused_doc_comments() {
+        let unused = |text| (DiagnosticType::UnusedDocComment, text);
+
+        assert_eq!(diagnostics("f(x /// a\n);"), [unused("/// a")]);
+        assert_eq!(diagnostics("x; /** b */"), [unused("/** b */")]);
+        assert_eq!(diagnostics("/// a\n/// b\nx; // c"), []);
+    }
 }
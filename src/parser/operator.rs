@@ -0,0 +1,248 @@
+use std::{collections::HashMap, fmt::Display};
+
+use crate::tokenizer::Operator;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Associativity {
+    /// `infixl`, `a - b - c` is `(a - b) - c`
+    Left,
+    /// `infixr`, `a ** b ** c` is `a ** (b ** c)`
+    Right,
+    /// `infix`, after `infix 4 <=>` chaining `a <=> b <=> c` needs parentheses
+    None,
+}
+
+impl Display for Associativity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Left => write!(f, "infixl"),
+            Self::Right => write!(f, "infixr"),
+            Self::None => write!(f, "infix"),
+        }
+    }
+}
+
+/// How tightly an infix operator binds.
+///
+/// Declared levels go from 0 to 9 like in Haskell, and a level `n` operator
+/// has a precedence of `n * 10`, so builtin operators can sit in between.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fixity {
+    pub(crate) precedence: u8,
+    pub(crate) associativity: Associativity,
+}
+
+pub const MAX_LEVEL: u8 = 9;
+
+impl Fixity {
+    pub fn new(precedence: u8, associativity: Associativity) -> Self {
+        Self {
+            precedence,
+            associativity,
+        }
+    }
+
+    /// The fixity of an `infixl`/`infixr`/`infix` declaration with the given level.
+    pub fn from_level(level: u8, associativity: Associativity) -> Self {
+        Self::new(level.min(MAX_LEVEL) * 10, associativity)
+    }
+
+    pub fn precedence(&self) -> u8 {
+        self.precedence
+    }
+
+    pub fn associativity(&self) -> Associativity {
+        self.associativity
+    }
+
+    /// The left and right binding powers for the Pratt parser.
+    pub fn binding_power(&self) -> (u16, u16) {
+        let base = binding_power(self.precedence);
+
+        match self.associativity {
+            Associativity::Left | Associativity::None => (base, base + 1),
+            Associativity::Right => (base + 1, base),
+        }
+    }
+}
+
+/// Precedence of the builtin prefix operators, between `*` and `**`, so
+/// `-a * b` is `(-a) * b` but `-a ** b` is `-(a ** b)`.
+pub const PREFIX_PRECEDENCE: u8 = 75;
+
+/// Precedence of the builtin postfix operators and indexing, above every level.
+pub const POSTFIX_PRECEDENCE: u8 = 100;
+
+/// Binding power of expression attributes, the same as builtin prefix operators.
+pub const PREFIX_BINDING_POWER: u16 = binding_power(PREFIX_PRECEDENCE);
+
+/// Turns a precedence into a binding power for the Pratt parser, leaving room
+/// for the right binding power of infix operators in between. Wider than a
+/// precedence so every `u8` precedence has one.
+pub const fn binding_power(precedence: u8) -> u16 {
+    precedence as u16 * 2 + 2
+}
+
+/// The operators a [`Parser`](super::Parser) knows about, with their
+/// precedences. The default table has the builtin operators.
+///
+/// Precedences of prefix and postfix operators are on the same scale as
+/// [`Fixity::precedence`].
+#[derive(Debug, Clone)]
+pub struct OperatorTable {
+    pub(crate) prefix: HashMap<Operator, u8>,
+    /// Keyed by the operator's text, which can be made of several tokens.
+    pub(crate) infix: HashMap<String, Fixity>,
+    pub(crate) postfix: HashMap<Operator, u8>,
+    /// Precedence of `a[b]`, `None` if indexing is disabled.
+    pub(crate) index: Option<u8>,
+}
+
+impl OperatorTable {
+    /// A table without any operators.
+    pub fn empty() -> Self {
+        Self {
+            prefix: HashMap::new(),
+            infix: HashMap::new(),
+            postfix: HashMap::new(),
+            index: None,
+        }
+    }
+
+    pub fn add_prefix(&mut self, op: Operator, precedence: u8) -> &mut Self {
+        self.prefix.insert(op, precedence);
+        self
+    }
+
+    pub fn remove_prefix(&mut self, op: Operator) -> &mut Self {
+        self.prefix.remove(&op);
+        self
+    }
+
+    pub fn add_infix(&mut self, symbol: impl Into<String>, fixity: Fixity) -> &mut Self {
+        self.infix.insert(symbol.into(), fixity);
+        self
+    }
+
+    pub fn remove_infix(&mut self, symbol: &str) -> &mut Self {
+        self.infix.remove(symbol);
+        self
+    }
+
+    pub fn add_postfix(&mut self, op: Operator, precedence: u8) -> &mut Self {
+        self.postfix.insert(op, precedence);
+        self
+    }
+
+    pub fn remove_postfix(&mut self, op: Operator) -> &mut Self {
+        self.postfix.remove(&op);
+        self
+    }
+
+    pub fn set_index(&mut self, precedence: Option<u8>) -> &mut Self {
+        self.index = precedence;
+        self
+    }
+
+    pub fn prefix(&self, op: Operator) -> Option<u8> {
+        self.prefix.get(&op).copied()
+    }
+
+    pub fn infix(&self, symbol: &str) -> Option<Fixity> {
+        self.infix.get(symbol).copied()
+    }
+
+    pub fn postfix(&self, op: Operator) -> Option<u8> {
+        self.postfix.get(&op).copied()
+    }
+
+    pub fn index(&self) -> Option<u8> {
+        self.index
+    }
+
+    /// The length of the longest infix operator's text.
+    pub(crate) fn max_infix_len(&self) -> usize {
+        self.infix.keys().map(String::len).max().unwrap_or(0)
+    }
+}
+
+impl Default for OperatorTable {
+    fn default() -> Self {
+        use Associativity as A;
+        use Operator as Op;
+
+        let mut table = Self::empty();
+
+        for op in [
+            Op::Plus,
+            Op::Minus,
+            Op::DoublePlus,
+            Op::DoubleMinus,
+            Op::Bang,
+            Op::Tilda,
+        ] {
+            table.add_prefix(op, PREFIX_PRECEDENCE);
+        }
+
+        let mut add = |ops: &[Op], precedence, associativity| {
+            for op in ops {
+                table.add_infix(op.to_string(), Fixity::new(precedence, associativity));
+            }
+        };
+
+        add(Op::ASSIGNMENTS, 0, A::Right);
+
+        add(&[Op::Pipeline], 10, A::Left);
+        add(&[Op::DoubleOr], 20, A::Left);
+        add(&[Op::DoubleAnd], 30, A::Left);
+
+        add(
+            &[
+                Op::DoubleEquals,
+                Op::BangEquals,
+                Op::LesserThan,
+                Op::GreaterThan,
+                Op::LesserThanEquals,
+                Op::GreaterThanEquals,
+            ],
+            40,
+            A::Left,
+        );
+
+        add(&[Op::SingleOr], 50, A::Left);
+        add(&[Op::Caret], 52, A::Left);
+        add(&[Op::SingleAnd], 54, A::Left);
+        add(&[Op::LeftShift, Op::RightShift], 56, A::Left);
+
+        add(&[Op::Plus, Op::Minus], 60, A::Left);
+        add(&[Op::Star, Op::Slash, Op::Percent], 70, A::Left);
+        add(&[Op::DoubleStar], 80, A::Right);
+
+        table
+            .add_postfix(Op::Bang, POSTFIX_PRECEDENCE)
+            .set_index(Some(POSTFIX_PRECEDENCE));
+
+        table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highest_precedences_have_binding_powers() {
+        for associativity in [
+            Associativity::Left,
+            Associativity::Right,
+            Associativity::None,
+        ] {
+            let (left, right) = Fixity::new(u8::MAX, associativity).binding_power();
+            let below = Fixity::new(u8::MAX - 1, associativity).binding_power();
+
+            assert!(left.min(right) > below.0.max(below.1));
+        }
+
+        assert!(binding_power(u8::MAX) > binding_power(POSTFIX_PRECEDENCE));
+    }
+}
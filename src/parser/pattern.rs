@@ -0,0 +1,257 @@
+use std::{cmp::Ordering, fmt::Display};
+
+use crate::{
+    tokenizer::{Literal, NumberLiteral},
+    units::UnitRegistry,
+};
+
+use super::{expression::Path, write_separated};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern<'s> {
+    Wildcard,
+
+    Binding(&'s str),
+
+    Literal(PatternLiteral),
+
+    Range {
+        start: Option<PatternLiteral>,
+        end: Option<PatternLiteral>,
+        inclusive: bool,
+    },
+
+    Tuple(Vec<Pattern<'s>>),
+
+    Path(Path<'s>),
+
+    TupleStruct {
+        path: Path<'s>,
+        fields: Vec<Pattern<'s>>,
+    },
+
+    Or(Vec<Pattern<'s>>),
+}
+
+/// A literal in a pattern, with the sign of a negative number like `-1`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatternLiteral {
+    pub(crate) value: Literal,
+    pub(crate) negative: bool,
+}
+
+impl PatternLiteral {
+    pub fn new(value: Literal) -> Self {
+        Self {
+            value,
+            negative: false,
+        }
+    }
+
+    /// The negation of a number or quantity literal.
+    pub fn negative(value: Literal) -> Self {
+        Self {
+            value,
+            negative: true,
+        }
+    }
+
+    pub fn value(&self) -> &Literal {
+        &self.value
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+}
+
+impl Display for PatternLiteral {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.negative {
+            write!(f, "-")?;
+        }
+
+        write!(f, "{}", self.value)
+    }
+}
+
+impl<'s> Pattern<'s> {
+    /// Whether every value matched by `other` is also matched by `self`.
+    ///
+    /// This is conservative: `false` means "not proven", not "disjoint".
+    /// Quantities are compared in the given units.
+    pub fn covers(&self, other: &Pattern<'s>, units: &UnitRegistry) -> bool {
+        match (self, other) {
+            (Self::Wildcard | Self::Binding(_), _) => true,
+
+            (_, Self::Or(alternatives)) => alternatives.iter().all(|o| self.covers(o, units)),
+            (Self::Or(alternatives), _) => alternatives.iter().any(|p| p.covers(other, units)),
+
+            (Self::Literal(a), Self::Literal(b)) => a == b,
+
+            (Self::Range { .. }, Self::Literal(b)) => self.range_contains(b, b, true, units),
+            (
+                Self::Range { .. },
+                Self::Range {
+                    start: Some(start),
+                    end: Some(end),
+                    inclusive,
+                },
+            ) => self.range_contains(start, end, *inclusive, units),
+
+            (Self::Tuple(a), Self::Tuple(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.covers(b, units))
+            }
+
+            (Self::Path(a), Self::Path(b)) => a.to_string() == b.to_string(),
+
+            (
+                Self::TupleStruct { path, fields },
+                Self::TupleStruct {
+                    path: other_path,
+                    fields: other_fields,
+                },
+            ) => {
+                path.to_string() == other_path.to_string()
+                    && fields.len() == other_fields.len()
+                    && fields
+                        .iter()
+                        .zip(other_fields)
+                        .all(|(a, b)| a.covers(b, units))
+            }
+
+            _ => false,
+        }
+    }
+
+    /// Whether the range pattern `self` contains every value from `low` to `high`.
+    fn range_contains(
+        &self,
+        low: &PatternLiteral,
+        high: &PatternLiteral,
+        high_inclusive: bool,
+        units: &UnitRegistry,
+    ) -> bool {
+        let Self::Range {
+            start,
+            end,
+            inclusive,
+        } = self
+        else {
+            return false;
+        };
+
+        let above_start = match start {
+            Some(start) => matches!(
+                compare_literals(start, low, units),
+                Some(Ordering::Less | Ordering::Equal)
+            ),
+            None => true,
+        };
+
+        let below_end = match end {
+            Some(end) => match compare_literals(high, end, units) {
+                Some(Ordering::Less) => true,
+                Some(Ordering::Equal) => *inclusive || !high_inclusive,
+                _ => false,
+            },
+            None => true,
+        };
+
+        above_start && below_end
+    }
+}
+
+fn compare_literals(
+    a: &PatternLiteral,
+    b: &PatternLiteral,
+    units: &UnitRegistry,
+) -> Option<Ordering> {
+    let sign = |l: &PatternLiteral| if l.negative { -1. } else { 1. };
+
+    match (&a.value, &b.value) {
+        (
+            Literal::Number(NumberLiteral::Integer(x), _),
+            Literal::Number(NumberLiteral::Integer(y), _),
+        ) => {
+            let signed = |l: &PatternLiteral, n: u64| {
+                if l.negative {
+                    -i128::from(n)
+                } else {
+                    i128::from(n)
+                }
+            };
+
+            Some(signed(a, *x).cmp(&signed(b, *y)))
+        }
+        (Literal::Number(x, _), Literal::Number(y, _)) => {
+            (sign(a) * x.to_f64()).partial_cmp(&(sign(b) * y.to_f64()))
+        }
+        (
+            Literal::Quantity {
+                value: x,
+                unit: x_unit,
+            },
+            Literal::Quantity {
+                value: y,
+                unit: y_unit,
+            },
+        ) => {
+            let (x, x_dimension) = units.to_base(sign(a) * x.to_f64(), x_unit)?;
+            let (y, y_dimension) = units.to_base(sign(b) * y.to_f64(), y_unit)?;
+
+            (x_dimension == y_dimension).then(|| x.partial_cmp(&y))?
+        }
+        (Literal::Char(x), Literal::Char(y)) => Some(x.cmp(y)),
+
+        _ => None,
+    }
+}
+
+impl Display for Pattern<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Wildcard => write!(f, "_"),
+            Self::Binding(name) => write!(f, "{name}"),
+            Self::Literal(value) => write!(f, "{value}"),
+            Self::Path(p) => write!(f, "{p}"),
+
+            Self::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                if let Some(start) = start {
+                    write!(f, "{start}")?;
+                }
+
+                write!(f, "{}", if *inclusive { "..=" } else { ".." })?;
+
+                if let Some(end) = end {
+                    write!(f, "{end}")?;
+                }
+
+                Ok(())
+            }
+
+            Self::Tuple(items) => {
+                write!(f, "(")?;
+                write_separated(f, items, ", ")?;
+
+                if items.len() == 1 {
+                    write!(f, ",")?;
+                }
+
+                write!(f, ")")
+            }
+
+            Self::TupleStruct { path, fields } => {
+                write!(f, "{path}(")?;
+                write_separated(f, fields, ", ")?;
+                write!(f, ")")
+            }
+
+            Self::Or(alternatives) => write_separated(f, alternatives, " | "),
+        }
+    }
+}
@@ -0,0 +1,775 @@
+//! Printing expressions with only the parentheses they need.
+//!
+//! The `Display` impl of [`Expression`] puts every node in parentheses, which
+//! is unambiguous but hard to read. [`Printer`] looks up the operators in the
+//! same [`OperatorTable`] the parser uses, so what it prints parses back to
+//! the same tree.
+
+use std::fmt::{self, Write};
+
+use super::{
+    doc::DocStyle,
+    expression::{Expression, FieldInit, MatchArm},
+    operator::{binding_power, Associativity, OperatorTable, PREFIX_BINDING_POWER},
+    statement::{Block, Statement},
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Printer<'o> {
+    operators: &'o OperatorTable,
+}
+
+/// Where an expression is printed, mirroring the state of the parser
+/// when it parses the expression back.
+#[derive(Debug, Clone, Copy, Default)]
+struct Context {
+    /// The binding power the expression's operator needs to be parsed here.
+    min_bp: u16,
+    /// The left binding power of the operator after the expression, which
+    /// would be taken by an operand at the end of the expression.
+    next_bp: Option<u16>,
+
+    /// In a condition before a block.
+    no_struct_literal: bool,
+    /// In a match guard before a fat arrow.
+    no_lambda: bool,
+    /// At the start of a statement, where attributes are the statement's.
+    statement_start: bool,
+    /// Before an opening bracket or brace, which makes `a!` a macro call.
+    before_delimiter: bool,
+}
+
+impl Context {
+    /// An operand after the start of the expression printed in this context.
+    fn operand(self, min_bp: u16, next_bp: Option<u16>) -> Self {
+        Self {
+            min_bp,
+            next_bp,
+            statement_start: false,
+            ..self
+        }
+    }
+
+    /// The operand at the start of the expression, followed by an operator.
+    fn left_operand(self, next_bp: Option<u16>) -> Self {
+        Self {
+            next_bp,
+            before_delimiter: false,
+            ..self
+        }
+    }
+}
+
+impl<'o> Printer<'o> {
+    pub fn new(operators: &'o OperatorTable) -> Self {
+        Self { operators }
+    }
+
+    pub fn print(&self, expr: &Expression<'_>) -> String {
+        let mut out = String::new();
+        self.expr(&mut out, expr, Context::default())
+            .expect("writing to a string doesn't fail");
+        out
+    }
+
+    pub fn print_statement(&self, statement: &Statement<'_>) -> String {
+        let mut out = String::new();
+        self.statement(&mut out, statement)
+            .expect("writing to a string doesn't fail");
+        out
+    }
+
+    /// Whether the expression has to be in parentheses to be parsed back
+    /// the same way in the context.
+    fn needs_parentheses(&self, expr: &Expression<'_>, context: Context) -> bool {
+        let takes_next = |right_bp: u16| context.next_bp.is_some_and(|next| next >= right_bp);
+
+        let infix = |symbol: &str| match self.operators.infix(symbol) {
+            Some(fixity) => {
+                let (left_bp, right_bp) = fixity.binding_power();
+                left_bp < context.min_bp || takes_next(right_bp)
+            }
+
+            None => true,
+        };
+
+        match expr {
+            Expression::Infix { op, .. } => infix(&op.to_string()),
+            Expression::CustomInfix { op, .. } => infix(op),
+
+            Expression::Prefix { op, .. } => match self.operators.prefix(*op) {
+                Some(precedence) => takes_next(binding_power(precedence)),
+                None => true,
+            },
+
+            // `a! {` is a macro call
+            Expression::Postfix { left, op, .. }
+                if context.before_delimiter && matches!(**left, Expression::Name(_)) =>
+            {
+                true
+            }
+            Expression::Postfix { op, .. } => match self.operators.postfix(*op) {
+                Some(precedence) => binding_power(precedence) < context.min_bp,
+                None => true,
+            },
+
+            Expression::Index { .. } => match self.operators.index() {
+                Some(precedence) => binding_power(precedence) < context.min_bp,
+                None => true,
+            },
+
+            // the body takes everything after it
+            Expression::Lambda { .. } => context.next_bp.is_some() || context.no_lambda,
+            Expression::Attributed { .. } => {
+                context.statement_start || takes_next(PREFIX_BINDING_POWER)
+            }
+
+            Expression::Struct { .. } => context.no_struct_literal,
+
+            _ => false,
+        }
+    }
+
+    fn expr<W: Write>(&self, f: &mut W, expr: &Expression<'_>, context: Context) -> fmt::Result {
+        if self.needs_parentheses(expr, context) {
+            write!(f, "(")?;
+            self.expr(f, expr, Context::default())?;
+            return write!(f, ")");
+        }
+
+        match expr {
+            Expression::Name(p) => write!(f, "{p}"),
+            Expression::Lit { value, .. } => write!(f, "{value}"),
+
+            Expression::Prefix { op, right, .. } => {
+                let right_bp = self.operators.prefix(*op).map(binding_power).unwrap_or(0);
+
+                write!(f, "{op}")?;
+
+                // `- -x` and not `--x`, which is another operator
+                if matches!(**right, Expression::Prefix { .. }) {
+                    write!(f, " ")?;
+                }
+
+                self.expr(f, right, context.operand(right_bp, context.next_bp))
+            }
+
+            Expression::Infix {
+                left, op, right, ..
+            } => self.infix(f, left, &op.to_string(), right, context),
+            Expression::CustomInfix {
+                left, op, right, ..
+            } => self.infix(f, left, op, right, context),
+
+            Expression::Postfix { left, op, .. } => {
+                let left_bp = self.operators.postfix(*op).map(binding_power);
+
+                self.expr(f, left, context.left_operand(left_bp))?;
+                write!(f, "{op}")
+            }
+
+            Expression::Index { expr, with, .. } => {
+                let left = Context {
+                    before_delimiter: true,
+                    ..context.left_operand(self.operators.index().map(binding_power))
+                };
+
+                self.expr(f, expr, left)?;
+                write!(f, "[")?;
+                self.expr(f, with, Context::default())?;
+                write!(f, "]")
+            }
+
+            Expression::Call {
+                function,
+                arguments,
+                ..
+            } => {
+                write!(f, "{function}(")?;
+                self.list(f, arguments, Context::default())?;
+                write!(f, ")")
+            }
+
+            Expression::Lambda {
+                parameters, body, ..
+            } => {
+                match parameters.as_slice() {
+                    [p] => write!(f, "{p}")?,
+                    parameters => {
+                        write!(f, "(")?;
+
+                        let mut params = parameters.iter();
+                        if let Some(p) = params.next() {
+                            write!(f, "{p}")?;
+                        }
+
+                        for p in params {
+                            write!(f, ", {p}")?;
+                        }
+
+                        write!(f, ")")?;
+                    }
+                }
+
+                write!(f, " => ")?;
+                self.expr(f, body, context.operand(0, context.next_bp))
+            }
+
+            Expression::Match {
+                scrutinee, arms, ..
+            } => {
+                write!(f, "match ")?;
+                self.expr(f, scrutinee, Self::condition(context))?;
+                write!(f, " {{")?;
+
+                let mut arms = arms.iter();
+                if let Some(a) = arms.next() {
+                    write!(f, " ")?;
+                    self.match_arm(f, a)?;
+                }
+
+                for a in arms {
+                    write!(f, ", ")?;
+                    self.match_arm(f, a)?;
+                }
+
+                write!(f, " }}")
+            }
+
+            Expression::Tuple { items, .. } => {
+                write!(f, "(")?;
+                self.list(f, items, Context::default())?;
+
+                if items.len() == 1 {
+                    write!(f, ",")?;
+                }
+
+                write!(f, ")")
+            }
+
+            Expression::Array { items, .. } => {
+                write!(f, "[")?;
+                self.list(f, items, Context::default())?;
+                write!(f, "]")
+            }
+
+            Expression::Repeat { value, count, .. } => {
+                write!(f, "[")?;
+                self.expr(f, value, Context::default())?;
+                write!(f, "; ")?;
+                self.expr(f, count, Context::default())?;
+                write!(f, "]")
+            }
+
+            Expression::Map { entries, .. } => {
+                write!(f, "{{")?;
+
+                for (i, (k, v)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+
+                    self.expr(f, k, Context::default())?;
+                    write!(f, ": ")?;
+                    self.expr(f, v, Context::default())?;
+                }
+
+                write!(f, "}}")
+            }
+
+            Expression::Struct {
+                path, fields, base, ..
+            } => {
+                write!(f, "{path} {{")?;
+
+                for (i, field) in fields.iter().enumerate() {
+                    write!(f, "{} ", if i > 0 { "," } else { "" })?;
+                    self.field(f, field)?;
+                }
+
+                if let Some(base) = base {
+                    if !fields.is_empty() {
+                        write!(f, ",")?;
+                    }
+
+                    write!(f, " ..")?;
+                    self.expr(f, base, Context::default())?;
+                }
+
+                write!(f, " }}")
+            }
+
+            Expression::Block(block) => self.block(f, block),
+
+            Expression::If {
+                condition,
+                then,
+                otherwise,
+                ..
+            } => {
+                write!(f, "if ")?;
+                self.expr(f, condition, Self::condition(context))?;
+                write!(f, " ")?;
+                self.block(f, then)?;
+
+                if let Some(otherwise) = otherwise {
+                    write!(f, " else ")?;
+                    self.expr(f, otherwise, Context::default())?;
+                }
+
+                Ok(())
+            }
+
+            Expression::While {
+                condition, body, ..
+            } => {
+                write!(f, "while ")?;
+                self.expr(f, condition, Self::condition(context))?;
+                write!(f, " ")?;
+                self.block(f, body)
+            }
+
+            Expression::Attributed {
+                attributes, expr, ..
+            } => {
+                for a in attributes {
+                    write!(f, "{a} ")?;
+                }
+
+                self.expr(
+                    f,
+                    expr,
+                    context.operand(PREFIX_BINDING_POWER, context.next_bp),
+                )
+            }
+
+            // token trees are written as they are
+            Expression::MacroCall { .. } => write!(f, "{expr}"),
+        }
+    }
+
+    fn infix<W: Write>(
+        &self,
+        f: &mut W,
+        left: &Expression<'_>,
+        symbol: &str,
+        right: &Expression<'_>,
+        context: Context,
+    ) -> fmt::Result {
+        let fixity = self.operators.infix(symbol);
+        let (left_bp, right_bp) = fixity.map(|f| f.binding_power()).unwrap_or_default();
+
+        // `a == b == c` is an error for non associative operators
+        let chained = match left {
+            Expression::Infix { op, .. } => Some(op.to_string()),
+            Expression::CustomInfix { op, .. } => Some(op.clone()),
+            _ => None,
+        };
+        let non_associative = fixity.is_some_and(|f| f.associativity() == Associativity::None)
+            && chained.is_some_and(|op| self.operators.infix(&op) == fixity);
+
+        if non_associative {
+            write!(f, "(")?;
+            self.expr(f, left, Context::default())?;
+            write!(f, ")")?;
+        } else {
+            self.expr(f, left, context.left_operand(Some(left_bp)))?;
+        }
+
+        write!(f, " {symbol} ")?;
+        self.expr(f, right, context.operand(right_bp, context.next_bp))
+    }
+
+    /// Comma separated expressions.
+    fn list<W: Write>(&self, f: &mut W, items: &[Expression<'_>], context: Context) -> fmt::Result {
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+
+            self.expr(f, item, context)?;
+        }
+
+        Ok(())
+    }
+
+    /// The context of a condition before a block, also used for the scrutinee of a match.
+    /// A lambda stays forbidden in a condition in a match guard.
+    fn condition(context: Context) -> Context {
+        Context {
+            no_struct_literal: true,
+            no_lambda: context.no_lambda,
+            before_delimiter: true,
+            ..Context::default()
+        }
+    }
+
+    fn match_arm<W: Write>(&self, f: &mut W, arm: &MatchArm<'_>) -> fmt::Result {
+        write!(f, "{}", arm.pattern())?;
+
+        if let Some(guard) = arm.guard() {
+            let context = Context {
+                no_lambda: true,
+                ..Context::default()
+            };
+
+            write!(f, " if ")?;
+            self.expr(f, guard, context)?;
+        }
+
+        write!(f, " => ")?;
+        self.expr(f, arm.body(), Context::default())
+    }
+
+    fn field<W: Write>(&self, f: &mut W, field: &FieldInit<'_>) -> fmt::Result {
+        write!(f, "{}", field.name())?;
+
+        if let Some(value) = field.value() {
+            write!(f, ": ")?;
+            self.expr(f, value, Context::default())?;
+        }
+
+        Ok(())
+    }
+
+    fn block<W: Write>(&self, f: &mut W, block: &Block<'_>) -> fmt::Result {
+        write!(f, "{{")?;
+
+        for s in block.statements() {
+            write!(f, " ")?;
+            self.statement(f, s)?;
+        }
+
+        write!(f, " }}")
+    }
+
+    fn statement<W: Write>(&self, f: &mut W, statement: &Statement<'_>) -> fmt::Result {
+        match statement {
+            Statement::Let { pattern, ty, value } => {
+                write!(f, "let {pattern}")?;
+
+                if let Some(ty) = ty {
+                    write!(f, ": {ty}")?;
+                }
+
+                if let Some(value) = value {
+                    write!(f, " = ")?;
+                    self.expr(f, value, Context::default())?;
+                }
+
+                write!(f, ";")
+            }
+
+            Statement::Expression { expr, semicolon } => {
+                let context = Context {
+                    statement_start: true,
+                    ..Context::default()
+                };
+
+                self.expr(f, expr, context)?;
+
+                if *semicolon {
+                    write!(f, ";")?;
+                }
+
+                Ok(())
+            }
+
+            Statement::Attributed {
+                attributes,
+                statement,
+            } => {
+                for a in attributes {
+                    write!(f, "{a} ")?;
+                }
+
+                self.statement(f, statement)
+            }
+
+            Statement::Documented { docs, statement } => {
+                for d in docs {
+                    match d.style() {
+                        DocStyle::Line => writeln!(f, "{d}")?,
+                        DocStyle::Block => write!(f, "{d} ")?,
+                    }
+                }
+
+                self.statement(f, statement)
+            }
+
+            Statement::Macro(_) | Statement::Fixity { .. } => write!(f, "{statement}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parser::{
+            expression::{Parameter, Path},
+            operator::Fixity,
+            pattern::{Pattern, PatternLiteral},
+            Parser,
+        },
+        tokenizer::{Literal, NumberLiteral, Operator, Span, TokenPosition, Tokenizer},
+        Source,
+    };
+
+    /// The default operators with a non-associative `<=>` and a
+    /// right-associative `<+>`.
+    fn operators() -> OperatorTable {
+        let mut operators = OperatorTable::default();
+        operators
+            .add_infix("<=>", Fixity::new(45, Associativity::None))
+            .add_infix("<+>", Fixity::new(65, Associativity::Right));
+        operators
+    }
+
+    fn parse<'s>(text: &'s str, operators: &OperatorTable) -> Expression<'s> {
+        let source = Source::new("test", text);
+        let (mut tokenizer_diagnostics, mut parser_diagnostics) = (vec![], vec![]);
+
+        let tokenizer = Tokenizer::new(source, &mut tokenizer_diagnostics);
+        let mut statements = Parser::new(tokenizer, &mut parser_diagnostics, operators.clone())
+            .program()
+            .unwrap_or_else(|e| panic!("`{text}` doesn't parse: {e}"));
+
+        match statements.pop() {
+            Some(Statement::Expression { expr, .. }) if statements.is_empty() => expr,
+            s => panic!("`{text}` isn't an expression: {s:?}"),
+        }
+    }
+
+    /// A xorshift generator, the same seed gives the same expressions.
+    struct Random(u64);
+
+    impl Random {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as usize
+        }
+
+        fn pick<T: Copy>(&mut self, items: &[T]) -> T {
+            items[self.below(items.len())]
+        }
+
+        fn boxed(&mut self, depth: usize) -> Box<Expression<'static>> {
+            Box::new(self.expression(depth))
+        }
+
+        fn expressions(&mut self, depth: usize, most: usize) -> Vec<Expression<'static>> {
+            (0..self.below(most + 1))
+                .map(|_| self.expression(depth))
+                .collect()
+        }
+
+        fn literal(&mut self) -> Literal {
+            match self.below(5) {
+                0 => Literal::Number(NumberLiteral::Integer(self.below(100) as u64), None),
+                1 => Literal::Number(
+                    NumberLiteral::Real(self.pick(&[1., 2.5, 0.125, 1e20])),
+                    None,
+                ),
+                2 => Literal::Number(
+                    NumberLiteral::Decimal("0.1234567890123456789".to_string()),
+                    None,
+                ),
+                3 => Literal::String(
+                    self.pick(&["", "a", "a\"b", "\\", "a\nb", "\t\0", "'"])
+                        .to_string(),
+                ),
+                _ => Literal::Char(self.pick(&['a', '\'', '"', '\\', '\n', '\t', '\0'])),
+            }
+        }
+
+        fn pattern(&mut self) -> Pattern<'static> {
+            match self.below(3) {
+                0 => Pattern::Wildcard,
+                1 => Pattern::Binding(self.pick(&["x", "y"])),
+                _ => Pattern::Literal(PatternLiteral::new(self.literal())),
+            }
+        }
+
+        /// A block, with a statement at least unless `empty`.
+        fn block(&mut self, depth: usize, empty: bool) -> Block<'static> {
+            let mut statements = vec![];
+
+            for _ in 0..self.below(3) {
+                statements.push(match self.below(3) {
+                    0 => Statement::Let {
+                        pattern: Pattern::Binding(self.pick(&["x", "y"])),
+                        ty: None,
+                        value: Some(self.expression(depth)),
+                    },
+                    _ => Statement::Expression {
+                        expr: self.expression(depth),
+                        semicolon: true,
+                    },
+                });
+            }
+
+            // only the last statement goes without a semicolon
+            if self.below(2) == 0 || (statements.is_empty() && !empty) {
+                statements.push(Statement::Expression {
+                    expr: self.expression(depth),
+                    semicolon: false,
+                });
+            }
+
+            Block::new(statements, Span::default())
+        }
+
+        fn expression(&mut self, depth: usize) -> Expression<'static> {
+            use Operator as Op;
+
+            let span = Span::default();
+            let choice = if depth == 0 {
+                self.below(2)
+            } else {
+                self.below(19)
+            };
+
+            match choice {
+                0 => Expression::Name(Path::single(self.pick(&["a", "b", "c"]))),
+                1 => Expression::Lit {
+                    value: self.literal(),
+                    span,
+                },
+
+                2 => Expression::Prefix {
+                    op: self.pick(&[Op::Minus, Op::Plus, Op::Bang, Op::Tilda, Op::DoubleMinus]),
+                    right: self.boxed(depth - 1),
+                    span,
+                },
+
+                3 | 4 => Expression::Infix {
+                    left: self.boxed(depth - 1),
+                    op: self.pick(&[
+                        Op::Equals,
+                        Op::DoubleOr,
+                        Op::LesserThan,
+                        Op::Plus,
+                        Op::Minus,
+                        Op::Star,
+                        Op::DoubleStar,
+                        Op::LeftShift,
+                    ]),
+                    right: self.boxed(depth - 1),
+                    span,
+                },
+
+                5 => Expression::CustomInfix {
+                    left: self.boxed(depth - 1),
+                    op: self.pick(&["<=>", "<+>"]).to_string(),
+                    right: self.boxed(depth - 1),
+                    span,
+                },
+
+                6 => Expression::Postfix {
+                    left: self.boxed(depth - 1),
+                    op: Op::Bang,
+                    span,
+                },
+
+                7 => Expression::Index {
+                    expr: self.boxed(depth - 1),
+                    with: self.boxed(depth - 1),
+                    span,
+                },
+
+                8 => Expression::Lambda {
+                    parameters: match self.below(3) {
+                        0 => vec![Parameter::new("x")],
+                        1 => vec![],
+                        _ => vec![Parameter::new("x"), Parameter::new("y")],
+                    },
+                    body: self.boxed(depth - 1),
+                    span,
+                },
+
+                9 => Expression::Call {
+                    function: Path::single(self.pick(&["f", "g"])),
+                    arguments: self.expressions(depth - 1, 2),
+                    span,
+                },
+
+                10 => Expression::Tuple {
+                    items: self.expressions(depth - 1, 3),
+                    span,
+                },
+
+                11 => Expression::Array {
+                    items: self.expressions(depth - 1, 3),
+                    span,
+                },
+
+                12 => Expression::Repeat {
+                    value: self.boxed(depth - 1),
+                    count: self.boxed(depth - 1),
+                    span,
+                },
+
+                13 => Expression::Map {
+                    entries: (0..self.below(2) + 1)
+                        .map(|_| (self.expression(depth - 1), self.expression(depth - 1)))
+                        .collect(),
+                    span,
+                },
+
+                // `{}` is an empty map
+                14 | 15 => Expression::Block(self.block(depth - 1, false)),
+
+                16 | 17 => Expression::If {
+                    condition: self.boxed(depth - 1),
+                    then: self.block(depth - 1, true),
+                    otherwise: (self.below(2) == 0)
+                        .then(|| Box::new(Expression::Block(self.block(depth - 1, true)))),
+                    span,
+                },
+
+                _ => Expression::Match {
+                    scrutinee: self.boxed(depth - 1),
+                    arms: (0..self.below(3) + 1)
+                        .map(|_| MatchArm {
+                            pattern: self.pattern(),
+                            guard: (self.below(3) == 0).then(|| self.expression(depth - 1)),
+                            body: self.expression(depth - 1),
+                            position: TokenPosition {
+                                absolute_position: 0,
+                                line: 1,
+                                column: 1,
+                                text: "",
+                            },
+                        })
+                        .collect(),
+                    span,
+                },
+            }
+        }
+    }
+
+    #[test]
+    fn printed_expressions_parse_back() {
+        let operators = operators();
+        let printer = Printer::new(&operators);
+        let mut random = Random(0x2545_f491_4f6c_dd1d);
+
+        for _ in 0..2000 {
+            let expr = random.expression(4);
+            let text = printer.print(&expr);
+
+            assert_eq!(parse(&text, &operators), expr, "printed as `{text}`");
+        }
+    }
+
+    #[test]
+    fn whole_reals_keep_their_dot() {
+        let operators = operators();
+        let expr = parse("1.0 + 2", &operators);
+
+        assert_eq!(Printer::new(&operators).print(&expr), "1.0 + 2");
+    }
+}
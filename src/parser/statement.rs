@@ -0,0 +1,168 @@
+use std::fmt::Display;
+
+use crate::tokenizer::Span;
+
+use super::{
+    attribute::Attribute,
+    doc::{DocComment, DocStyle},
+    expression::Expression,
+    macros::MacroDefinition,
+    operator::Associativity,
+    pattern::Pattern,
+    types::Type,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement<'s> {
+    Let {
+        pattern: Pattern<'s>,
+        ty: Option<Type<'s>>,
+        value: Option<Expression<'s>>,
+    },
+
+    Expression {
+        expr: Expression<'s>,
+        semicolon: bool,
+    },
+
+    Attributed {
+        attributes: Vec<Attribute<'s>>,
+        statement: Box<Statement<'s>>,
+    },
+
+    /// A statement after doc comments.
+    Documented {
+        docs: Vec<DocComment<'s>>,
+        statement: Box<Statement<'s>>,
+    },
+
+    Macro(MacroDefinition<'s>),
+
+    /// `infixl 6 <+>;`
+    Fixity {
+        associativity: Associativity,
+        level: u8,
+        symbol: String,
+    },
+}
+
+impl<'s> Statement<'s> {
+    /// The statement without its attributes and doc comments.
+    pub fn inner(&self) -> &Statement<'s> {
+        match self {
+            Self::Attributed { statement, .. } | Self::Documented { statement, .. } => {
+                statement.inner()
+            }
+
+            s => s,
+        }
+    }
+
+    /// The doc comments right before the statement, or before its attributes.
+    pub fn docs(&self) -> &[DocComment<'s>] {
+        match self {
+            Self::Documented { docs, .. } => docs,
+            Self::Attributed { statement, .. } => statement.docs(),
+
+            _ => &[],
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Block<'s> {
+    pub(crate) statements: Vec<Statement<'s>>,
+    pub(crate) span: Span,
+}
+
+impl<'s> Block<'s> {
+    pub fn new(statements: Vec<Statement<'s>>, span: Span) -> Self {
+        Self { statements, span }
+    }
+
+    pub fn statements(&self) -> &[Statement<'s>] {
+        &self.statements
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl PartialEq for Block<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.statements == other.statements
+    }
+}
+
+impl Display for Block<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{{")?;
+
+        for s in &self.statements {
+            write!(f, " {s}")?;
+        }
+
+        write!(f, " }}")
+    }
+}
+
+impl Display for Statement<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Let { pattern, ty, value } => {
+                write!(f, "let {pattern}")?;
+
+                if let Some(ty) = ty {
+                    write!(f, ": {ty}")?;
+                }
+
+                if let Some(value) = value {
+                    write!(f, " = {value}")?;
+                }
+
+                write!(f, ";")
+            }
+
+            Self::Expression { expr, semicolon } => {
+                write!(f, "{expr}")?;
+
+                if *semicolon {
+                    write!(f, ";")?;
+                }
+
+                Ok(())
+            }
+
+            Self::Attributed {
+                attributes,
+                statement,
+            } => {
+                for a in attributes {
+                    write!(f, "{a} ")?;
+                }
+
+                write!(f, "{statement}")
+            }
+
+            Self::Documented { docs, statement } => {
+                for d in docs {
+                    match d.style() {
+                        DocStyle::Line => writeln!(f, "{d}")?,
+                        DocStyle::Block => write!(f, "{d} ")?,
+                    }
+                }
+
+                write!(f, "{statement}")
+            }
+
+            Self::Macro(definition) => write!(f, "{definition}"),
+
+            Self::Fixity {
+                associativity,
+                level,
+                symbol,
+            } => write!(f, "{associativity} {level} {symbol};"),
+        }
+    }
+}
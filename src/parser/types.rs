@@ -0,0 +1,64 @@
+use std::fmt::Display;
+
+use super::{
+    expression::{Expression, Path},
+    write_separated,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type<'s> {
+    /// `_`, left for the type checker to figure out
+    Infer,
+
+    Path(Path<'s>),
+
+    Tuple(Vec<Type<'s>>),
+
+    /// `[T; N]`, or `[T]` when the length is unknown
+    Array {
+        element: Box<Type<'s>>,
+        length: Option<Box<Expression<'s>>>,
+    },
+
+    /// `fn(A, B) -> C`
+    Function {
+        parameters: Vec<Type<'s>>,
+        output: Box<Type<'s>>,
+    },
+}
+
+impl Display for Type<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Infer => write!(f, "_"),
+
+            Self::Path(path) => write!(f, "{path}"),
+
+            Self::Tuple(items) => {
+                write!(f, "(")?;
+                write_separated(f, items, ", ")?;
+
+                if items.len() == 1 {
+                    write!(f, ",")?;
+                }
+
+                write!(f, ")")
+            }
+
+            Self::Array {
+                element,
+                length: Some(length),
+            } => write!(f, "[{element}; {length}]"),
+            Self::Array {
+                element,
+                length: None,
+            } => write!(f, "[{element}]"),
+
+            Self::Function { parameters, output } => {
+                write!(f, "fn(")?;
+                write_separated(f, parameters, ", ")?;
+                write!(f, ") -> {output}")
+            }
+        }
+    }
+}
@@ -0,0 +1,149 @@
+//! Traversal of the syntax tree, for passes that rewrite it in place.
+//!
+//! Override the `visit_*` methods to handle the nodes you care about, and
+//! call the matching `walk_*` function to continue into their children.
+
+use super::{
+    attribute::Attribute,
+    expression::Expression,
+    statement::{Block, Statement},
+};
+
+pub trait VisitorMut<'s> {
+    fn visit_expression(&mut self, expr: &mut Expression<'s>) {
+        walk_expression(self, expr);
+    }
+
+    fn visit_statement(&mut self, statement: &mut Statement<'s>) {
+        walk_statement(self, statement);
+    }
+
+    fn visit_block(&mut self, block: &mut Block<'s>) {
+        walk_block(self, block);
+    }
+
+    fn visit_attribute(&mut self, attribute: &mut Attribute<'s>) {
+        for a in &mut attribute.arguments {
+            self.visit_expression(a);
+        }
+    }
+}
+
+pub fn walk_expression<'s, V: VisitorMut<'s> + ?Sized>(v: &mut V, expr: &mut Expression<'s>) {
+    match expr {
+        Expression::Prefix { right, .. } => v.visit_expression(right),
+        Expression::Infix { left, right, .. } | Expression::CustomInfix { left, right, .. } => {
+            v.visit_expression(left);
+            v.visit_expression(right);
+        }
+        Expression::Postfix { left, .. } => v.visit_expression(left),
+        Expression::Call { arguments, .. } => {
+            for a in arguments {
+                v.visit_expression(a);
+            }
+        }
+        Expression::Name(_) | Expression::Lit { .. } | Expression::MacroCall { .. } => (),
+        Expression::Index { expr, with, .. } => {
+            v.visit_expression(expr);
+            v.visit_expression(with);
+        }
+        Expression::Lambda { body, .. } => v.visit_expression(body),
+        Expression::Match {
+            scrutinee, arms, ..
+        } => {
+            v.visit_expression(scrutinee);
+
+            for arm in arms {
+                if let Some(guard) = &mut arm.guard {
+                    v.visit_expression(guard);
+                }
+
+                v.visit_expression(&mut arm.body);
+            }
+        }
+        Expression::Tuple { items, .. } | Expression::Array { items, .. } => {
+            for i in items {
+                v.visit_expression(i);
+            }
+        }
+        Expression::Repeat { value, count, .. } => {
+            v.visit_expression(value);
+            v.visit_expression(count);
+        }
+        Expression::Map { entries, .. } => {
+            for (key, value) in entries {
+                v.visit_expression(key);
+                v.visit_expression(value);
+            }
+        }
+        Expression::Struct { fields, base, .. } => {
+            for f in fields {
+                if let Some(value) = &mut f.value {
+                    v.visit_expression(value);
+                }
+            }
+
+            if let Some(base) = base {
+                v.visit_expression(base);
+            }
+        }
+        Expression::Block(block) => v.visit_block(block),
+        Expression::If {
+            condition,
+            then,
+            otherwise,
+            ..
+        } => {
+            v.visit_expression(condition);
+            v.visit_block(then);
+
+            if let Some(otherwise) = otherwise {
+                v.visit_expression(otherwise);
+            }
+        }
+        Expression::While {
+            condition, body, ..
+        } => {
+            v.visit_expression(condition);
+            v.visit_block(body);
+        }
+        Expression::Attributed {
+            attributes, expr, ..
+        } => {
+            for a in attributes {
+                v.visit_attribute(a);
+            }
+
+            v.visit_expression(expr);
+        }
+    }
+}
+
+pub fn walk_statement<'s, V: VisitorMut<'s> + ?Sized>(v: &mut V, statement: &mut Statement<'s>) {
+    match statement {
+        Statement::Let { value, .. } => {
+            if let Some(value) = value {
+                v.visit_expression(value);
+            }
+        }
+        Statement::Expression { expr, .. } => v.visit_expression(expr),
+        Statement::Attributed {
+            attributes,
+            statement,
+        } => {
+            for a in attributes {
+                v.visit_attribute(a);
+            }
+
+            v.visit_statement(statement);
+        }
+        Statement::Documented { statement, .. } => v.visit_statement(statement),
+        Statement::Macro(_) | Statement::Fixity { .. } => (),
+    }
+}
+
+pub fn walk_block<'s, V: VisitorMut<'s> + ?Sized>(v: &mut V, block: &mut Block<'s>) {
+    for s in &mut block.statements {
+        v.visit_statement(s);
+    }
+}
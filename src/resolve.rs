@@ -0,0 +1,581 @@
+//! Name resolution, from the paths in expressions to their definitions.
+//!
+//! The scopes form a tree of modules, functions, blocks and match arms. The
+//! program is in the root module, and the other modules are added by the
+//! host with [`Resolver::add_module`], with their items visible unqualified
+//! in the root once imported with [`Resolver::import`].
+
+use std::collections::HashMap;
+
+use crate::{
+    eval::Environment,
+    parser::{
+        attribute::Attribute,
+        expression::{Expression, Path, PathSegment},
+        pattern::Pattern,
+        statement::{Block, Statement},
+        visit::{walk_block, walk_expression, walk_statement, VisitorMut},
+    },
+    tokenizer::Span,
+    Diagnostic, DiagnosticType, Source,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DefinitionId(pub(crate) u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ScopeId(pub(crate) u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefinitionKind {
+    Let,
+    Parameter,
+    MatchBinding,
+    /// Defined by the host in the root module, like the builtin functions.
+    Global,
+    /// Defined by the host in a module added with [`Resolver::add_module`].
+    ModuleItem,
+}
+
+impl DefinitionKind {
+    /// Whether the definition is a variable of the program.
+    pub fn is_local(&self) -> bool {
+        matches!(self, Self::Let | Self::Parameter | Self::MatchBinding)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Definition {
+    pub(crate) name: String,
+    pub(crate) kind: DefinitionKind,
+    pub(crate) scope: ScopeId,
+    /// `None` for the definitions of the host.
+    pub(crate) span: Option<Span>,
+}
+
+impl Definition {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> DefinitionKind {
+        self.kind
+    }
+
+    pub fn scope(&self) -> ScopeId {
+        self.scope
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        self.span
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeKind {
+    Module,
+    /// The parameters and the body of a lambda.
+    Function,
+    Block,
+    MatchArm,
+}
+
+#[derive(Debug, Clone)]
+pub struct Scope {
+    pub(crate) kind: ScopeKind,
+    pub(crate) parent: Option<ScopeId>,
+    /// The last definition of each name, the earlier ones are shadowed.
+    pub(crate) names: HashMap<String, DefinitionId>,
+    /// The modules whose items are visible in a module.
+    pub(crate) imports: Vec<ScopeId>,
+}
+
+impl Scope {
+    fn new(kind: ScopeKind, parent: Option<ScopeId>) -> Self {
+        Self {
+            kind,
+            parent,
+            names: HashMap::new(),
+            imports: vec![],
+        }
+    }
+
+    pub fn kind(&self) -> ScopeKind {
+        self.kind
+    }
+
+    pub fn parent(&self) -> Option<ScopeId> {
+        self.parent
+    }
+
+    pub fn get(&self, name: &str) -> Option<DefinitionId> {
+        self.names.get(name).copied()
+    }
+}
+
+enum Resolution {
+    Found(DefinitionId),
+    NotFound,
+    /// Found in more than one imported module.
+    Ambiguous,
+}
+
+pub struct Resolver<'n, 's, 'd, D: Extend<Diagnostic<'s>>> {
+    source: Source<'n, 's>,
+    diagnostics: &'d mut D,
+
+    scopes: Vec<Scope>,
+    definitions: Vec<Definition>,
+    /// The modules added by the host, by their path.
+    modules: HashMap<String, ScopeId>,
+    current: ScopeId,
+}
+
+impl<'n, 's, 'd, D: Extend<Diagnostic<'s>>> Resolver<'n, 's, 'd, D> {
+    pub const ROOT: ScopeId = ScopeId(0);
+
+    /// `source` is the text the statements were parsed from, for the
+    /// positions of the diagnostics and of the definitions.
+    pub fn new(source: Source<'n, 's>, diagnostics: &'d mut D) -> Self {
+        Self {
+            source,
+            diagnostics,
+
+            scopes: vec![Scope::new(ScopeKind::Module, None)],
+            definitions: vec![],
+            modules: HashMap::new(),
+            current: Self::ROOT,
+        }
+    }
+
+    /// Adds items to a module, creating it if needed.
+    pub fn add_module<'a>(
+        &mut self,
+        path: &str,
+        items: impl IntoIterator<Item = &'a str>,
+    ) -> ScopeId {
+        let module = match self.modules.get(path) {
+            Some(module) => *module,
+            None => {
+                self.scopes.push(Scope::new(ScopeKind::Module, None));
+
+                let module = ScopeId(self.scopes.len() as u32 - 1);
+                self.modules.insert(path.to_string(), module);
+                module
+            }
+        };
+
+        for item in items {
+            self.add_definition(item.to_string(), DefinitionKind::ModuleItem, module, None);
+        }
+
+        module
+    }
+
+    /// Makes the items of a module visible without its path in the root,
+    /// returns `false` if there is no such module.
+    pub fn import(&mut self, path: &str) -> bool {
+        let Some(module) = self.modules.get(path).copied() else {
+            return false;
+        };
+
+        let root = &mut self.scopes[Self::ROOT.0 as usize];
+        if !root.imports.contains(&module) {
+            root.imports.push(module);
+        }
+
+        true
+    }
+
+    pub fn define_global(&mut self, name: &str) -> DefinitionId {
+        self.add_definition(name.to_string(), DefinitionKind::Global, Self::ROOT, None)
+    }
+
+    /// Defines the variables of the environment, the ones with paths like
+    /// `math::sqrt` as the items of modules.
+    pub fn define_environment(&mut self, env: &Environment<'_>) {
+        for name in env.names() {
+            match name.rsplit_once("::") {
+                Some((module, item)) => {
+                    self.add_module(module, [item]);
+                }
+                None => {
+                    self.define_global(&name);
+                }
+            }
+        }
+    }
+
+    /// Resolves the paths in the statements, they are in the root module.
+    pub fn resolve(&mut self, statements: &mut [Statement<'s>]) {
+        for s in statements {
+            self.visit_statement(s);
+        }
+    }
+
+    pub fn scope(&self, id: ScopeId) -> &Scope {
+        &self.scopes[id.0 as usize]
+    }
+
+    pub fn scopes(&self) -> &[Scope] {
+        &self.scopes
+    }
+
+    pub fn definition(&self, id: DefinitionId) -> &Definition {
+        &self.definitions[id.0 as usize]
+    }
+
+    pub fn definitions(&self) -> &[Definition] {
+        &self.definitions
+    }
+
+    fn report(&mut self, ty: DiagnosticType, span: Span) {
+        let position = self.source.position(span);
+        self.diagnostics.extend([Diagnostic::new(ty, position)]);
+    }
+
+    fn add_definition(
+        &mut self,
+        name: String,
+        kind: DefinitionKind,
+        scope: ScopeId,
+        span: Option<Span>,
+    ) -> DefinitionId {
+        let id = DefinitionId(self.definitions.len() as u32);
+        self.scopes[scope.0 as usize].names.insert(name.clone(), id);
+        self.definitions.push(Definition {
+            name,
+            kind,
+            scope,
+            span,
+        });
+
+        id
+    }
+
+    /// Defines a variable in the current scope, warning if it hides one of
+    /// an enclosing scope.
+    fn define(&mut self, name: &'s str, kind: DefinitionKind) {
+        let span = self.source.span_of(name);
+
+        let enclosing = self.scope(self.current).parent;
+        let shadowed = self
+            .find(name, enclosing)
+            .is_some_and(|id| self.definition(id).kind.is_local());
+
+        if let (true, Some(span)) = (shadowed, span) {
+            self.report(DiagnosticType::ShadowedName, span);
+        }
+
+        self.add_definition(name.to_string(), kind, self.current, span);
+    }
+
+    fn define_pattern(&mut self, pattern: &Pattern<'s>, kind: DefinitionKind) {
+        match pattern {
+            Pattern::Binding(name) => self.define(name, kind),
+
+            Pattern::Tuple(patterns)
+            | Pattern::TupleStruct {
+                fields: patterns, ..
+            } => {
+                for p in patterns {
+                    self.define_pattern(p, kind);
+                }
+            }
+
+            // every alternative binds the same names
+            Pattern::Or(alternatives) => {
+                if let Some(first) = alternatives.first() {
+                    self.define_pattern(first, kind);
+                }
+            }
+
+            Pattern::Wildcard | Pattern::Literal(_) | Pattern::Range { .. } | Pattern::Path(_) => {}
+        }
+    }
+
+    fn push_scope(&mut self, kind: ScopeKind) {
+        self.scopes.push(Scope::new(kind, Some(self.current)));
+        self.current = ScopeId(self.scopes.len() as u32 - 1);
+    }
+
+    fn pop_scope(&mut self) {
+        self.current = self
+            .scope(self.current)
+            .parent
+            .expect("the root is never popped");
+    }
+
+    /// Looks for the name in the scope and the ones enclosing it.
+    fn find(&self, name: &str, from: Option<ScopeId>) -> Option<DefinitionId> {
+        let mut scope = from;
+
+        while let Some(s) = scope {
+            let s = self.scope(s);
+            if let Some(id) = s.get(name) {
+                return Some(id);
+            }
+
+            scope = s.parent;
+        }
+
+        None
+    }
+
+    /// Looks for a name in the enclosing scopes, then in the modules
+    /// imported by the root.
+    fn lookup(&self, name: &str) -> Resolution {
+        if let Some(id) = self.find(name, Some(self.current)) {
+            return Resolution::Found(id);
+        }
+
+        let mut found = self
+            .scope(Self::ROOT)
+            .imports
+            .iter()
+            .filter_map(|m| self.scope(*m).get(name));
+
+        match (found.next(), found.next()) {
+            (Some(id), None) => Resolution::Found(id),
+            (Some(_), Some(_)) => Resolution::Ambiguous,
+            (None, _) => Resolution::NotFound,
+        }
+    }
+
+    fn lookup_path(&self, path: &Path<'s>) -> Resolution {
+        if let Some(name) = path.as_identifier() {
+            return self.lookup(name);
+        }
+
+        let mut names = vec![];
+        for segment in path.segments() {
+            match segment {
+                PathSegment::Name { name, .. } => names.push(*name),
+                // the root module is the only one with items of the program
+                PathSegment::SelfModule if names.is_empty() => (),
+
+                PathSegment::SelfModule | PathSegment::Super => return Resolution::NotFound,
+            }
+        }
+
+        let Some((item, module)) = names.split_last() else {
+            return Resolution::NotFound;
+        };
+
+        let found = if module.is_empty() {
+            self.scope(Self::ROOT).get(item)
+        } else {
+            self.modules
+                .get(&module.join("::"))
+                .and_then(|m| self.scope(*m).get(item))
+        };
+
+        match found {
+            Some(id) => Resolution::Found(id),
+            None => Resolution::NotFound,
+        }
+    }
+
+    fn resolve_path(&mut self, path: &mut Path<'s>) {
+        match self.lookup_path(path) {
+            Resolution::Found(id) => path.definition = Some(id),
+            Resolution::NotFound => self.report(DiagnosticType::UnresolvedName, path.span()),
+            Resolution::Ambiguous => self.report(DiagnosticType::AmbiguousImport, path.span()),
+        }
+    }
+}
+
+impl<'s, D: Extend<Diagnostic<'s>>> VisitorMut<'s> for Resolver<'_, 's, '_, D> {
+    fn visit_statement(&mut self, statement: &mut Statement<'s>) {
+        let Statement::Let { pattern, value, .. } = statement else {
+            return walk_statement(self, statement);
+        };
+
+        // a lambda can call itself through the name it's bound to
+        let recursive = matches!(
+            (&pattern, &value),
+            (Pattern::Binding(_), Some(Expression::Lambda { .. }))
+        );
+
+        if recursive {
+            self.define_pattern(pattern, DefinitionKind::Let);
+        }
+
+        if let Some(value) = value {
+            self.visit_expression(value);
+        }
+
+        if !recursive {
+            self.define_pattern(pattern, DefinitionKind::Let);
+        }
+    }
+
+    fn visit_block(&mut self, block: &mut Block<'s>) {
+        self.push_scope(ScopeKind::Block);
+        walk_block(self, block);
+        self.pop_scope();
+    }
+
+    // the arguments of attributes aren't evaluated
+    fn visit_attribute(&mut self, _attribute: &mut Attribute<'s>) {}
+
+    fn visit_expression(&mut self, expr: &mut Expression<'s>) {
+        match expr {
+            Expression::Name(path) => self.resolve_path(path),
+
+            Expression::Call {
+                function,
+                arguments,
+                ..
+            } => {
+                self.resolve_path(function);
+
+                for a in arguments {
+                    self.visit_expression(a);
+                }
+            }
+
+            Expression::Lambda {
+                parameters, body, ..
+            } => {
+                self.push_scope(ScopeKind::Function);
+
+                for p in parameters.iter() {
+                    self.define(p.name(), DefinitionKind::Parameter);
+                }
+
+                self.visit_expression(body);
+                self.pop_scope();
+            }
+
+            Expression::Match {
+                scrutinee, arms, ..
+            } => {
+                self.visit_expression(scrutinee);
+
+                for arm in arms {
+                    self.push_scope(ScopeKind::MatchArm);
+                    self.define_pattern(&arm.pattern, DefinitionKind::MatchBinding);
+
+                    if let Some(guard) = &mut arm.guard {
+                        self.visit_expression(guard);
+                    }
+
+                    self.visit_expression(&mut arm.body);
+                    self.pop_scope();
+                }
+            }
+
+            Expression::Struct { fields, .. } => {
+                // `Point { x }` uses the variable `x`
+                for f in fields.iter().filter(|f| f.value.is_none()) {
+                    let mut path = Path::single(f.name());
+                    path.span = self.source.span_of(f.name()).unwrap_or_default();
+                    self.resolve_path(&mut path);
+                }
+
+                walk_expression(self, expr);
+            }
+
+            _ => walk_expression(self, expr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, tokenizer::Tokenizer};
+
+    /// The diagnostics with the text they're about, after defining the
+    /// prelude and running `setup`.
+    fn resolve<'s>(
+        text: &'s str,
+        setup: impl FnOnce(&mut Resolver<'_, 's, '_, Vec<Diagnostic<'s>>>),
+    ) -> Vec<(DiagnosticType, &'s str)> {
+        let source = Source::new("test", text);
+        let (mut tokenizer_diagnostics, mut parser_diagnostics) = (vec![], vec![]);
+
+        let tokenizer = Tokenizer::new(source, &mut tokenizer_diagnostics);
+        let mut statements = Parser::new(tokenizer, &mut parser_diagnostics, Default::default())
+            .program()
+            .unwrap();
+
+        let mut diagnostics = vec![];
+        let mut resolver = Resolver::new(source, &mut diagnostics);
+        resolver.define_environment(&Environment::with_prelude());
+        setup(&mut resolver);
+        resolver.resolve(&mut statements);
+
+        diagnostics
+            .iter()
+            .map(|d| (d.ty().clone(), d.position().text))
+            .collect()
+    }
+
+    #[test]
+    fn unresolved_names() {
+        assert_eq!(
+            resolve("let a = 1; a + b; { let c = a; c }; c", |_| ()),
+            [
+                (DiagnosticType::UnresolvedName, "b"),
+                (DiagnosticType::UnresolvedName, "c"),
+            ]
+        );
+        assert_eq!(
+            resolve("math::pi + pi + self::len + super::a", |r| {
+                r.add_module("math", ["pi"]);
+            }),
+            [
+                (DiagnosticType::UnresolvedName, "pi"),
+                (DiagnosticType::UnresolvedName, "super::a"),
+            ]
+        );
+    }
+
+    #[test]
+    fn shadowed_names() {
+        assert_eq!(
+            resolve(
+                "let x = 1; let x = 2; { let x = 3; let len = 4; } y => x => x",
+                |_| ()
+            ),
+            [
+                (DiagnosticType::ShadowedName, "x"),
+                (DiagnosticType::ShadowedName, "x"),
+            ]
+        );
+        assert_eq!(
+            resolve("let f = x => match x { (x, y) => y };", |_| ()),
+            [(DiagnosticType::ShadowedName, "x")]
+        );
+    }
+
+    #[test]
+    fn ambiguous_imports() {
+        fn modules<'s>(r: &mut Resolver<'_, 's, '_, Vec<Diagnostic<'s>>>) {
+            r.add_module("a", ["shared", "only_a"]);
+            r.add_module("b", ["shared"]);
+            r.import("a");
+            r.import("b");
+        }
+
+        assert_eq!(
+            resolve("only_a + shared + a::shared", modules),
+            [(DiagnosticType::AmbiguousImport, "shared")]
+        );
+        // a definition of the program takes precedence
+        assert_eq!(resolve("let shared = 1; shared", modules), []);
+    }
+
+    #[test]
+    fn prelude_names_are_defined() {
+        assert_eq!(
+            resolve(
+                "print(len(\"a\"), abs(-1), true && false, sqrt(2.0))",
+                |_| ()
+            ),
+            []
+        );
+    }
+}
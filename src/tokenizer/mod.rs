@@ -1,6 +1,12 @@
 mod token;
 
-use crate::{Diagnostic, DiagnosticType};
+use std::collections::{HashSet, VecDeque};
+
+use crate::{
+    dialect::{CommentSyntax, Dialect},
+    units::UnitRegistry,
+    Diagnostic, DiagnosticType,
+};
 
 use super::Source;
 pub use token::{TokenType as Ty, *};
@@ -36,12 +42,35 @@ pub struct Tokenizer<'n, 's, 'd, D> {
 
     diagnostics: &'d mut D,
 
+    comments: CommentSyntax,
+    keywords: HashSet<Keyword>,
+    caret_is_power: bool,
+    units: UnitRegistry,
+
     pub emit_whitespace: bool,
     pub emit_comments: bool,
+    /// Emit line breaks as [`TokenType::Newline`] instead of whitespace.
+    pub emit_newlines: bool,
+    /// Emit [`TokenType::Indent`] and [`TokenType::Dedent`] when the
+    /// indentation of a line changes outside of brackets, implies `emit_newlines`.
+    pub emit_indentation: bool,
+
+    /// The indentation of the enclosing lines, the first one is always empty.
+    indents: Vec<&'s str>,
+    /// The indentation of the current line until its first token.
+    line_indent: Option<&'s str>,
+    bracket_depth: usize,
+    /// Tokens to emit before tokenizing more.
+    pending: VecDeque<TokenizerItem<'s>>,
 }
 
 impl<'n, 's, 'd, D: Extend<Diagnostic<'s>>> Tokenizer<'n, 's, 'd, D> {
     pub fn new(source: Source<'n, 's>, diagnostics: &'d mut D) -> Self {
+        Self::with_dialect(source, diagnostics, &Dialect::default())
+    }
+
+    /// Tokenizes with the comment syntax and keywords of the dialect.
+    pub fn with_dialect(source: Source<'n, 's>, diagnostics: &'d mut D, dialect: &Dialect) -> Self {
         Self {
             chrs: source.text.char_indices().peekable(),
             source,
@@ -50,8 +79,20 @@ impl<'n, 's, 'd, D: Extend<Diagnostic<'s>>> Tokenizer<'n, 's, 'd, D> {
 
             diagnostics,
 
+            comments: dialect.comments,
+            keywords: dialect.keywords.clone(),
+            caret_is_power: dialect.caret_is_power,
+            units: dialect.units.clone(),
+
             emit_comments: false,
             emit_whitespace: false,
+            emit_newlines: dialect.automatic_semicolons,
+            emit_indentation: dialect.indentation_blocks,
+
+            indents: vec![""],
+            line_indent: Some(&source.text[..0]),
+            bracket_depth: 0,
+            pending: VecDeque::new(),
 
             token_start: 0,
             token_end: 0,
@@ -66,7 +107,7 @@ impl<'n, 's, 'd, D: Extend<Diagnostic<'s>>> Tokenizer<'n, 's, 'd, D> {
             absolute_position: self.token_start,
             line: self.start_line,
             column: self.start_column,
-            text: &self.source.text[self.token_start..=self.token_end],
+            text: &self.source.text[self.token_start..self.token_end],
         }
     }
 
@@ -76,7 +117,7 @@ impl<'n, 's, 'd, D: Extend<Diagnostic<'s>>> Tokenizer<'n, 's, 'd, D> {
                 if c == '\n' {
                     self.newlines.push(p);
                 }
-                self.token_end = p;
+                self.token_end = p + c.len_utf8();
 
                 Some(c)
             }
@@ -88,6 +129,14 @@ impl<'n, 's, 'd, D: Extend<Diagnostic<'s>>> Tokenizer<'n, 's, 'd, D> {
         self.chrs.peek().map(|(_, c)| *c)
     }
 
+    fn peek_second_char(&self) -> Option<char> {
+        self.peek_nth_char(1)
+    }
+
+    fn peek_nth_char(&self, n: usize) -> Option<char> {
+        self.chrs.clone().nth(n).map(|(_, c)| c)
+    }
+
     fn eat(&mut self, v: char) -> bool {
         if self.peek_char() == Some(v) {
             self.next_char();
@@ -98,18 +147,17 @@ impl<'n, 's, 'd, D: Extend<Diagnostic<'s>>> Tokenizer<'n, 's, 'd, D> {
     }
 
     fn consume(&mut self) {
-        self.token_start = self.token_end + 1;
-        self.token_end = self.token_start;
+        self.token_start = self.token_end;
 
         self.start_line = self.newlines.len();
         self.start_column = self.token_end - self.newlines.last().unwrap();
     }
 
-    fn number(&mut self, first_char: char) -> NumberLiteral {
-        let mut int_part = first_char as u64 - '0' as u64;
+    /// The digits from the next character on, without the `_` separators.
+    fn digits(&mut self, digits: &mut String) {
         loop {
             match self.peek_char() {
-                Some(c @ '0'..='9') => int_part = int_part * 10 + (c as u64 - '0' as u64),
+                Some(c @ '0'..='9') => digits.push(c),
                 Some('_') => (),
 
                 _ => break,
@@ -117,36 +165,75 @@ impl<'n, 's, 'd, D: Extend<Diagnostic<'s>>> Tokenizer<'n, 's, 'd, D> {
 
             self.next_char();
         }
+    }
 
-        if !self.eat('.') {
-            return NumberLiteral::Integer(int_part);
-        }
+    fn number(&mut self, first_char: char) -> Literal {
+        let mut integer = first_char.to_string();
+        self.digits(&mut integer);
 
-        // float
+        // `1..2` is a range, not a real followed by a dot
+        let number = if self.peek_char() != Some('.') || self.peek_second_char() == Some('.') {
+            NumberLiteral::integer(&integer)
+        } else {
+            self.next_char();
 
-        let mut float_part = 0.;
-        let mut pow = 0.1;
-        loop {
-            match self.peek_char() {
-                Some(c @ '0'..='9') => {
-                    float_part += (c as u8 - b'0') as f64 * pow;
-                    pow *= 0.1;
-                }
+            let mut fraction = String::new();
+            self.digits(&mut fraction);
 
-                Some('_') => (),
+            NumberLiteral::real(&integer, &fraction)
+        };
 
-                _ => break,
+        let name = self.name_after_number();
+        let suffix = NumberSuffix::from_name(&name);
+
+        // any other name is a unit, like the `ms` of `500ms`
+        if suffix.is_none() && !name.is_empty() {
+            if self.units.get(&name).is_none() {
+                self.diagnostics
+                    .extend([Diagnostic::new(DiagnosticType::UnknownUnit, self.pos())]);
             }
 
+            return Literal::Quantity {
+                value: number,
+                unit: name,
+            };
+        }
+
+        let fits = match suffix {
+            Some(suffix) => suffix.is_real() || number.is_integer(),
+            None => true,
+        };
+
+        // the minus before a literal is a separate token, so `128i8` is left
+        // for the parser to report when it isn't negated
+        if !fits || suffix.is_some_and(|s| !s.fits(&number, true)) {
+            self.diagnostics.extend([Diagnostic::new(
+                DiagnosticType::LiteralOutOfRange,
+                self.pos(),
+            )]);
+        }
+
+        Literal::Number(number, suffix)
+    }
+
+    /// Takes the name right after a number, a type suffix like `u8` or a
+    /// unit like `ms`.
+    fn name_after_number(&mut self) -> String {
+        let mut name = String::new();
+        while let Some(c @ ('a'..='z' | 'A'..='Z' | '_' | '0'..='9')) = self.peek_char() {
+            name.push(c);
             self.next_char();
         }
 
-        NumberLiteral::Real(int_part as f64 + float_part)
+        name
     }
 
     fn char_lit(&mut self) -> Result<char, TokenizerError> {
         let c = match self.next_char().ok_or(TokenizerError::UnfinishedChar)? {
-            '\\' => self.peek_char().ok_or(TokenizerError::UnfinishedChar)?,
+            '\\' => {
+                let c = self.next_char().ok_or(TokenizerError::UnfinishedChar)?;
+                escape(c).unwrap_or(c)
+            }
             c => c,
         };
 
@@ -162,17 +249,12 @@ impl<'n, 's, 'd, D: Extend<Diagnostic<'s>>> Tokenizer<'n, 's, 'd, D> {
         let mut escaped = false;
 
         loop {
-            match self.peek_char().ok_or(TokenizerError::UnfinishedString)? {
+            match self.next_char().ok_or(TokenizerError::UnfinishedString)? {
                 '"' if !escaped => break,
                 '\\' if !escaped => escaped = true,
 
                 c if escaped => {
-                    if let Some(escaped) = escape(c) {
-                        s.push_str(escaped);
-                    } else {
-                        s.push(c);
-                    }
-
+                    s.push(escape(c).unwrap_or(c));
                     escaped = false;
                 }
 
@@ -213,7 +295,7 @@ impl<'n, 's, 'd, D: Extend<Diagnostic<'s>>> Tokenizer<'n, 's, 'd, D> {
         closed
     }
     fn singleline_comment(&mut self) {
-        while self.peek_char() != Some('\n') {
+        while !matches!(self.peek_char(), Some('\n') | None) {
             self.next_char();
         }
     }
@@ -250,6 +332,10 @@ impl<'n, 's, 'd, D: Extend<Diagnostic<'s>>> Tokenizer<'n, 's, 'd, D> {
             '@' => Ty::Punctuation(Punctuation::AtSign),
             ',' => Ty::Punctuation(Punctuation::Comma),
             ';' => Ty::Punctuation(Punctuation::Semicolon),
+            '#' if self.comments.hash => {
+                self.singleline_comment();
+                Ty::Comment(Comment::SingleLine)
+            }
             '#' => Ty::Punctuation(Punctuation::HashSymbol),
             '?' => Ty::Punctuation(Punctuation::QuestionMark),
 
@@ -286,6 +372,8 @@ impl<'n, 's, 'd, D: Extend<Diagnostic<'s>>> Tokenizer<'n, 's, 'd, D> {
                 if self.eat('.') {
                     if self.eat('.') {
                         Ty::Punctuation(Punctuation::TripleDot)
+                    } else if self.eat('=') {
+                        Ty::Punctuation(Punctuation::DoubleDotEquals)
                     } else {
                         Ty::Punctuation(Punctuation::DoubleDot)
                     }
@@ -294,6 +382,22 @@ impl<'n, 's, 'd, D: Extend<Diagnostic<'s>>> Tokenizer<'n, 's, 'd, D> {
                 }
             }
 
+            '\n' if self.emit_newlines || self.emit_indentation => {
+                while matches!(self.peek_char(), Some(' ' | '\t' | '\n')) {
+                    self.next_char();
+                }
+
+                Ty::Newline
+            }
+
+            ' ' | '\t' if self.emit_newlines || self.emit_indentation => {
+                while matches!(self.peek_char(), Some(' ' | '\t')) {
+                    self.next_char();
+                }
+
+                Ty::Whitespace
+            }
+
             ' ' | '\t' | '\n' => {
                 while matches!(self.peek_char(), Some(' ' | '\t' | '\n')) {
                     self.next_char();
@@ -311,11 +415,12 @@ impl<'n, 's, 'd, D: Extend<Diagnostic<'s>>> Tokenizer<'n, 's, 'd, D> {
                 }
 
                 get_keyword(self.pos().text)
+                    .filter(|k| self.keywords.contains(k))
                     .map(Ty::Keyword)
                     .unwrap_or(Ty::Identifier)
             }
 
-            c @ '0'..='9' => Ty::Literal(Literal::Number(self.number(c))),
+            c @ '0'..='9' => Ty::Literal(self.number(c)),
 
             '\'' => Ty::Literal(Literal::Char(match self.char_lit() {
                 Ok(c) => c,
@@ -350,6 +455,11 @@ impl<'n, 's, 'd, D: Extend<Diagnostic<'s>>> Tokenizer<'n, 's, 'd, D> {
                     self.next_char();
                     Ty::Operator(Operator::DoubleMinus)
                 }
+
+                Some('>') => {
+                    self.next_char();
+                    Ty::Punctuation(Punctuation::Arrow)
+                }
                 _ => Ty::Operator(Operator::Minus),
             },
             '*' => match self.peek_char() {
@@ -386,13 +496,12 @@ impl<'n, 's, 'd, D: Extend<Diagnostic<'s>>> Tokenizer<'n, 's, 'd, D> {
                 }
             }
 
-            '^' => {
-                if self.eat('=') {
-                    Ty::Operator(Operator::CaretEquals)
-                } else {
-                    Ty::Operator(Operator::Caret)
-                }
-            }
+            '^' => match (self.eat('='), self.caret_is_power) {
+                (true, false) => Ty::Operator(Operator::CaretEquals),
+                (false, false) => Ty::Operator(Operator::Caret),
+                (true, true) => Ty::Operator(Operator::DoubleStarEquals),
+                (false, true) => Ty::Operator(Operator::DoubleStar),
+            },
 
             '%' => {
                 if self.eat('=') {
@@ -403,7 +512,11 @@ impl<'n, 's, 'd, D: Extend<Diagnostic<'s>>> Tokenizer<'n, 's, 'd, D> {
             }
 
             '/' => match self.peek_char() {
-                Some('*') => {
+                Some('*') if self.comments.block => {
+                    // `/**/` and `/***` are not doc comments
+                    let doc = self.peek_second_char() == Some('*')
+                        && !matches!(self.peek_nth_char(2), Some('*' | '/'));
+
                     let closed = self.multiline_comment();
 
                     if !closed {
@@ -413,12 +526,25 @@ impl<'n, 's, 'd, D: Extend<Diagnostic<'s>>> Tokenizer<'n, 's, 'd, D> {
                         )]);
                     }
 
-                    Ty::Comment(Comment::MultiLine)
+                    if doc {
+                        Ty::Comment(Comment::DocBlock)
+                    } else {
+                        Ty::Comment(Comment::MultiLine)
+                    }
                 }
 
-                Some('/') => {
+                Some('/') if self.comments.double_slash => {
+                    // `////` is not a doc comment
+                    let doc =
+                        self.peek_second_char() == Some('/') && self.peek_nth_char(2) != Some('/');
+
                     self.singleline_comment();
-                    Ty::Comment(Comment::SingleLine)
+
+                    if doc {
+                        Ty::Comment(Comment::DocLine)
+                    } else {
+                        Ty::Comment(Comment::SingleLine)
+                    }
                 }
 
                 Some('=') => {
@@ -462,6 +588,11 @@ impl<'n, 's, 'd, D: Extend<Diagnostic<'s>>> Tokenizer<'n, 's, 'd, D> {
                     TokenType::Operator(Operator::SingleOrEquals)
                 }
 
+                Some('>') => {
+                    self.next_char();
+                    Ty::Operator(Operator::Pipeline)
+                }
+
                 _ => Ty::Operator(Operator::SingleOr),
             },
 
@@ -512,10 +643,159 @@ impl<'n, 's, 'd, D: Extend<Diagnostic<'s>>> Tokenizer<'n, 's, 'd, D> {
         }))
     }
 
+    /// Queues the indentation tokens before `t` in `pending`, followed by `t`.
+    fn track_indentation(&mut self, t: Option<TokenizerItem<'s>>) {
+        match &t {
+            Some(Ok(Token {
+                ty: Ty::Newline,
+                position,
+            })) if self.bracket_depth == 0 => {
+                let text = position.text;
+                self.line_indent = Some(&text[text.rfind('\n').map_or(0, |i| i + 1)..]);
+            }
+
+            // only at the start of the input, newlines include the indentation
+            Some(Ok(Token {
+                ty: Ty::Whitespace,
+                position,
+            })) if self.line_indent.is_some() && position.absolute_position == 0 => {
+                self.line_indent = Some(position.text)
+            }
+
+            Some(Ok(Token {
+                ty: Ty::Whitespace | Ty::Comment(_) | Ty::Newline,
+                ..
+            })) => (),
+
+            Some(Ok(Token { ty, position })) => {
+                if let Some(indent) = self.line_indent.take() {
+                    self.indent_line(indent, *position);
+                }
+
+                match ty {
+                    Ty::Delimeter(Delimeter {
+                        side: DelimeterSide::Left,
+                        ..
+                    }) => self.bracket_depth += 1,
+                    Ty::Delimeter(Delimeter {
+                        side: DelimeterSide::Right,
+                        ..
+                    }) => self.bracket_depth = self.bracket_depth.saturating_sub(1),
+
+                    _ => (),
+                }
+            }
+
+            Some(Err(_)) => (),
+
+            None => {
+                let end = self.source.text.len();
+                while self.indents.len() > 1 {
+                    self.indents.pop();
+                    self.pending.push_back(Ok(Token {
+                        ty: Ty::Dedent,
+                        position: self.position_at(end, ""),
+                    }));
+                }
+
+                return;
+            }
+        }
+
+        if let Some(t) = t {
+            self.pending.push_back(t);
+        }
+    }
+
+    /// Compares the indentation of a line starting at `first` with the
+    /// enclosing ones.
+    fn indent_line(&mut self, indent: &'s str, first: TokenPosition<'s>) {
+        // a comment can be between the indentation and the first token
+        let start = self
+            .source
+            .span_of(indent)
+            .map_or(first.absolute_position - indent.len(), |span| span.start);
+        let indent_position = TokenPosition {
+            column: 1,
+            ..self.position_at(start, indent)
+        };
+
+        if indent.contains(' ') && indent.contains('\t') {
+            self.diagnostics.extend([Diagnostic::new(
+                DiagnosticType::MixedIndentation,
+                indent_position,
+            )]);
+        }
+
+        let top = self.indents[self.indents.len() - 1];
+        if indent == top {
+            return;
+        }
+
+        if indent.len() > top.len() {
+            if !indent.starts_with(top) {
+                self.diagnostics.extend([Diagnostic::new(
+                    DiagnosticType::InconsistentIndentation,
+                    indent_position,
+                )]);
+            }
+
+            self.indents.push(indent);
+            self.pending.push_back(Ok(Token {
+                ty: Ty::Indent,
+                position: indent_position,
+            }));
+
+            return;
+        }
+
+        while self.indents.len() > 1 && self.indents[self.indents.len() - 1].len() > indent.len() {
+            self.indents.pop();
+            self.pending.push_back(Ok(Token {
+                ty: Ty::Dedent,
+                position: self.position_at(first.absolute_position, ""),
+            }));
+        }
+
+        if self.indents[self.indents.len() - 1] != indent {
+            self.diagnostics.extend([Diagnostic::new(
+                DiagnosticType::InconsistentIndentation,
+                indent_position,
+            )]);
+        }
+    }
+
+    /// An empty position for synthesized tokens.
+    fn position_at(&self, absolute_position: usize, text: &'s str) -> TokenPosition<'s> {
+        let line = self
+            .newlines
+            .partition_point(|&n| n < absolute_position)
+            .max(1);
+        let column = absolute_position - self.newlines[line - 1];
+
+        TokenPosition {
+            absolute_position,
+            line,
+            column,
+            text,
+        }
+    }
+
     fn get_token(&mut self) -> Option<TokenizerItem<'s>> {
         loop {
-            let t = self.get_token_inner();
-            self.consume();
+            let t = if self.emit_indentation {
+                if self.pending.is_empty() {
+                    let t = self.get_token_inner();
+                    self.consume();
+                    self.track_indentation(t);
+                }
+
+                self.pending.pop_front()
+            } else {
+                let t = self.get_token_inner();
+                self.consume();
+                t
+            };
 
             match t {
                 Some(Ok(Token {
@@ -523,8 +803,8 @@ impl<'n, 's, 'd, D: Extend<Diagnostic<'s>>> Tokenizer<'n, 's, 'd, D> {
                 })) if !self.emit_whitespace => continue,
 
                 Some(Ok(Token {
-                    ty: Ty::Comment(_), ..
-                })) if !self.emit_comments => continue,
+                    ty: Ty::Comment(c), ..
+                })) if !self.emit_comments && !c.is_doc() => continue,
 
                 r => return r,
             };
@@ -542,12 +822,153 @@ impl<'n, 's, 'd, D: Extend<Diagnostic<'s>>> Iterator for Tokenizer<'n, 's, 'd, D
     }
 }
 
-fn escape(c: char) -> Option<&'static str> {
+/// The character of an escape like `\n` by the character after the
+/// backslash, any other one stands for itself like `\"`.
+fn escape(c: char) -> Option<char> {
     Some(match c {
-        'n' => "\n",
-        't' => "\t",
-        '0' => "\0",
+        'n' => '\n',
+        't' => '\t',
+        '0' => '\0',
 
         _ => return None,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The text of the tokens, with `>` for an indent, `<` for a dedent and
+    /// `;` for a line break.
+    fn tokens<'s>(text: &'s str, dialect: &Dialect) -> (Vec<String>, Vec<Diagnostic<'s>>) {
+        let mut diagnostics = vec![];
+        let tokens = Tokenizer::with_dialect(Source::new("test", text), &mut diagnostics, dialect)
+            .map(|t| {
+                let t = t.unwrap();
+                match t.ty {
+                    Ty::Indent => ">".to_string(),
+                    Ty::Dedent => "<".to_string(),
+                    Ty::Newline => ";".to_string(),
+                    _ => t.text().to_string(),
+                }
+            })
+            .collect();
+
+        (tokens, diagnostics)
+    }
+
+    fn indented(text: &str) -> Vec<String> {
+        let (tokens, diagnostics) = tokens(text, &Dialect::indented());
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+
+        tokens
+    }
+
+    #[test]
+    fn escapes() {
+        let literals = |text| {
+            Tokenizer::new(Source::new("test", text), &mut vec![])
+                .map(|t| match t.unwrap().ty {
+                    Ty::Literal(l) => l,
+                    ty => panic!("{ty:?} isn't a literal"),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            literals(r#""a\"b\\c\nd\te\0" '\n' '\'' '"' '\\'"#),
+            [
+                Literal::String("a\"b\\c\nd\te\0".to_string()),
+                Literal::Char('\n'),
+                Literal::Char('\''),
+                Literal::Char('"'),
+                Literal::Char('\\'),
+            ]
+        );
+        assert!(Tokenizer::new(Source::new("test", "'\\'"), &mut vec![]).any(|t| t.is_err()));
+    }
+
+    #[test]
+    fn indentation() {
+        assert_eq!(
+            indented("if x:\n    a\n    b\nc\n"),
+            ["if", "x", ":", ";", ">", "a", ";", "b", ";", "<", "c", ";"]
+        );
+    }
+
+    #[test]
+    fn comments_at_the_start_of_a_line() {
+        let block_comments = Dialect {
+            comments: Dialect::standard().comments,
+            ..Dialect::indented()
+        };
+
+        let (found, diagnostics) = tokens("if x:\n    /* c */ a\n    b\n", &block_comments);
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        assert_eq!(found, ["if", "x", ":", ";", ">", "a", ";", "b", ";", "<"]);
+
+        let (found, _) = tokens("/* c */  a\n", &block_comments);
+        assert_eq!(found, ["a", ";"]);
+
+        assert_eq!(
+            indented("if x:\n    a\n# c\n    b\n"),
+            ["if", "x", ":", ";", ">", "a", ";", ";", "b", ";", "<"]
+        );
+    }
+
+    #[test]
+    fn blank_lines() {
+        assert_eq!(
+            indented("if x:\n    a\n\n  \n    b\n\nc"),
+            ["if", "x", ":", ";", ">", "a", ";", "b", ";", "<", "c"]
+        );
+    }
+
+    #[test]
+    fn dedents_at_the_end() {
+        assert_eq!(
+            indented("if x:\n    if y:\n        a"),
+            ["if", "x", ":", ";", ">", "if", "y", ":", ";", ">", "a", "<", "<"]
+        );
+    }
+
+    #[test]
+    fn inconsistent_indentation() {
+        let (_, diagnostics) = tokens("if x:\n    a\n  b\n", &Dialect::indented());
+
+        assert!(matches!(
+            diagnostics.as_slice(),
+            [d] if matches!(d.ty(), DiagnosticType::InconsistentIndentation)
+        ));
+    }
+
+    #[test]
+    fn newlines() {
+        let (found, _) = tokens("a = 1\n\nf(\n  b)\n", &Dialect::script());
+        assert_eq!(found, ["a", "=", "1", ";", "f", "(", ";", "b", ")", ";"]);
+
+        let (found, _) = tokens("a = 1\nb\n", &Dialect::standard());
+        assert_eq!(found, ["a", "=", "1", "b"]);
+    }
+
+    #[test]
+    fn doc_comments() {
+        let comments = |text| {
+            Tokenizer::new(Source::new("test", text), &mut vec![])
+                .filter_map(|t| match t.unwrap() {
+                    Token {
+                        ty: Ty::Comment(c),
+                        position,
+                    } => Some((c, position.text)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // other comments are only emitted on request
+        assert_eq!(
+            comments("/// a\n// b\n//// c\n/** d */ /* e */ /*** f */ x"),
+            [(Comment::DocLine, "/// a"), (Comment::DocBlock, "/** d */")]
+        );
+    }
+}
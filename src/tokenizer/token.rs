@@ -34,9 +34,11 @@ pub enum Punctuation {
     Comma,
     Colon,
     DoubleColon,
+    Arrow,
     FatArrow,
     Dot,
     DoubleDot,
+    DoubleDotEquals,
     TripleDot,
     HashSymbol,
     AtSign,
@@ -47,24 +49,153 @@ pub enum Punctuation {
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Literal {
-    Number(NumberLiteral),
+    /// A number with its type suffix, like `10u8`.
+    Number(NumberLiteral, Option<NumberSuffix>),
+    /// A number with a unit, like `500ms` or `16KiB`, see [`crate::units`].
+    Quantity {
+        value: NumberLiteral,
+        unit: String,
+    },
     String(String),
     Char(char),
 }
 
+impl Literal {
+    /// Whether the literal is the magnitude of a signed minimum like the
+    /// `128i8` of `-128i8`, a value of its type only after a minus.
+    pub fn fits_only_negated(&self) -> bool {
+        match self {
+            Literal::Number(n, Some(suffix)) => !suffix.fits(n, false) && suffix.fits(n, true),
+
+            _ => false,
+        }
+    }
+}
+
 impl Display for Literal {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Literal::Number(n) => write!(f, "{n}"),
-            Literal::String(s) => write!(f, "\"{s}\""),
-            Literal::Char(c) => write!(f, "'{c}'"),
+            Literal::Number(n, None) => write!(f, "{n}"),
+            Literal::Number(n, Some(suffix)) => write!(f, "{n}{suffix}"),
+            Literal::Quantity { value, unit } => write!(f, "{value}{unit}"),
+            Literal::String(s) => {
+                write!(f, "\"")?;
+                write_escaped(f, s.chars(), '"')?;
+                write!(f, "\"")
+            }
+            Literal::Char(c) => {
+                write!(f, "'")?;
+                write_escaped(f, [*c], '\'')?;
+                write!(f, "'")
+            }
+        }
+    }
+}
+
+/// Writes the characters with the escapes the tokenizer reads back, for a
+/// literal between `quote`s.
+fn write_escaped(
+    f: &mut std::fmt::Formatter<'_>,
+    chars: impl IntoIterator<Item = char>,
+    quote: char,
+) -> std::fmt::Result {
+    for c in chars {
+        match c {
+            '\n' => write!(f, "\\n")?,
+            '\t' => write!(f, "\\t")?,
+            '\0' => write!(f, "\\0")?,
+            '\\' => write!(f, "\\\\")?,
+            c if c == quote => write!(f, "\\{c}")?,
+            c => write!(f, "{c}")?,
         }
     }
+
+    Ok(())
 }
-#[derive(Debug, Clone, Copy, PartialEq)]
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum NumberLiteral {
     Integer(u64),
     Real(f64),
+    /// An integer too large for a `u64`, by its decimal digits.
+    BigInteger(String),
+    /// A real with more digits than an `f64` holds, like `0.12345678901234567890`,
+    /// by its digits without leading or trailing zeros.
+    Decimal(String),
+}
+
+impl NumberLiteral {
+    /// The literal for the digits of an integer.
+    pub fn integer(digits: &str) -> Self {
+        match digits.parse() {
+            Ok(i) => Self::Integer(i),
+            Err(_) => Self::BigInteger(trim_zeros(digits, "").0.to_string()),
+        }
+    }
+
+    /// The literal for the digits before and after the dot of a real, which
+    /// keeps the digits when the nearest `f64` isn't the same number.
+    pub fn real(integer: &str, fraction: &str) -> Self {
+        let (integer, fraction) = trim_zeros(integer, fraction);
+        let digits = match fraction {
+            "" => integer.to_string(),
+            fraction => format!("{integer}.{fraction}"),
+        };
+
+        let nearest: f64 = digits.parse().expect("the tokenizer only passes digits");
+
+        // the shortest representation of an `f64` is its digits when it
+        // holds them exactly
+        if nearest.to_string() == digits {
+            Self::Real(nearest)
+        } else {
+            Self::Decimal(digits)
+        }
+    }
+
+    pub fn is_integer(&self) -> bool {
+        matches!(self, Self::Integer(_) | Self::BigInteger(_))
+    }
+
+    /// The nearest `f64`, which is infinite for the numbers out of its range.
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Self::Integer(i) => *i as f64,
+            Self::Real(r) => *r,
+            Self::BigInteger(digits) | Self::Decimal(digits) => {
+                digits.parse().expect("the digits of a number literal")
+            }
+        }
+    }
+
+    /// The value as an integer, for the conversions to the integer types.
+    fn to_u128(&self, ty: &'static str) -> Result<u128, NumberConversionError> {
+        let out_of_range = || NumberConversionError::OutOfRange(self.to_string(), ty);
+        let precision_loss = || NumberConversionError::PrecisionLoss(self.to_string(), ty);
+
+        match self {
+            Self::Integer(i) => Ok(*i as u128),
+            Self::BigInteger(digits) => digits.parse().map_err(|_| out_of_range()),
+
+            Self::Real(r) if r.fract() != 0.0 => Err(precision_loss()),
+            Self::Real(r) if *r < 0.0 || *r >= u128::MAX as f64 => Err(out_of_range()),
+            Self::Real(r) => Ok(*r as u128),
+
+            Self::Decimal(digits) if digits.contains('.') => Err(precision_loss()),
+            Self::Decimal(digits) => digits.parse().map_err(|_| out_of_range()),
+        }
+    }
+}
+
+/// The digits without the zeros before the integer part and after the
+/// fractional part, keeping a zero for an integer part of zeros.
+fn trim_zeros<'a>(integer: &'a str, fraction: &'a str) -> (&'a str, &'a str) {
+    let integer = match integer.trim_start_matches('0') {
+        "" => "0",
+        integer => integer,
+    };
+
+    (integer, fraction.trim_end_matches('0'))
 }
 
 impl From<u64> for NumberLiteral {
@@ -78,19 +209,56 @@ impl From<f64> for NumberLiteral {
     }
 }
 
-impl From<NumberLiteral> for u64 {
-    fn from(value: NumberLiteral) -> u64 {
-        match value {
-            NumberLiteral::Integer(i) => i,
-            NumberLiteral::Real(f) => f as u64,
-        }
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum NumberConversionError {
+    #[error("{0} is out of the range of {1}")]
+    OutOfRange(String, &'static str),
+
+    #[error("{0} can't be converted to {1} without losing precision")]
+    PrecisionLoss(String, &'static str),
+}
+
+impl TryFrom<&NumberLiteral> for u64 {
+    type Error = NumberConversionError;
+
+    fn try_from(value: &NumberLiteral) -> Result<Self, Self::Error> {
+        let i = value.to_u128("u64")?;
+        u64::try_from(i).map_err(|_| NumberConversionError::OutOfRange(value.to_string(), "u64"))
     }
 }
-impl From<NumberLiteral> for f64 {
-    fn from(value: NumberLiteral) -> f64 {
-        match value {
-            NumberLiteral::Integer(i) => i as f64,
-            NumberLiteral::Real(f) => f,
+
+impl TryFrom<&NumberLiteral> for i64 {
+    type Error = NumberConversionError;
+
+    fn try_from(value: &NumberLiteral) -> Result<Self, Self::Error> {
+        let i = value.to_u128("i64")?;
+        i64::try_from(i).map_err(|_| NumberConversionError::OutOfRange(value.to_string(), "i64"))
+    }
+}
+
+/// Fails for the integers an `f64` can't hold exactly, and for decimals.
+impl TryFrom<&NumberLiteral> for f64 {
+    type Error = NumberConversionError;
+
+    fn try_from(value: &NumberLiteral) -> Result<Self, Self::Error> {
+        let nearest = value.to_f64();
+
+        let exact = match value {
+            NumberLiteral::Integer(i) => nearest as u128 == *i as u128,
+            NumberLiteral::Real(_) => true,
+            NumberLiteral::BigInteger(digits) => nearest.to_string() == *digits,
+            NumberLiteral::Decimal(_) => false,
+        };
+
+        if !nearest.is_finite() {
+            Err(NumberConversionError::OutOfRange(value.to_string(), "f64"))
+        } else if !exact {
+            Err(NumberConversionError::PrecisionLoss(
+                value.to_string(),
+                "f64",
+            ))
+        } else {
+            Ok(nearest)
         }
     }
 }
@@ -99,18 +267,135 @@ impl Display for NumberLiteral {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             NumberLiteral::Integer(i) => write!(f, "{i}"),
-            NumberLiteral::Real(r) => write!(f, "{r}"),
+            NumberLiteral::Real(r) => write_real(f, &r.to_string()),
+            NumberLiteral::Decimal(digits) => write_real(f, digits),
+            NumberLiteral::BigInteger(digits) => write!(f, "{digits}"),
         }
     }
 }
 
+/// Writes the digits of a real with a `.0` when they are a whole number, so
+/// that they don't read back as an integer.
+fn write_real(f: &mut std::fmt::Formatter<'_>, digits: &str) -> std::fmt::Result {
+    let whole = digits.trim_start_matches('-');
+    if whole.is_empty() || !whole.bytes().all(|b| b.is_ascii_digit()) {
+        write!(f, "{digits}")
+    } else {
+        write!(f, "{digits}.0")
+    }
+}
+
+/// The type written after a number literal, like `u8` in `10u8`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberSuffix {
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    Isize,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    Usize,
+    F32,
+    F64,
+}
+
+impl NumberSuffix {
+    pub const ALL: &'static [NumberSuffix] = &[
+        Self::I8,
+        Self::I16,
+        Self::I32,
+        Self::I64,
+        Self::I128,
+        Self::Isize,
+        Self::U8,
+        Self::U16,
+        Self::U32,
+        Self::U64,
+        Self::U128,
+        Self::Usize,
+        Self::F32,
+        Self::F64,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|s| s.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::I8 => "i8",
+            Self::I16 => "i16",
+            Self::I32 => "i32",
+            Self::I64 => "i64",
+            Self::I128 => "i128",
+            Self::Isize => "isize",
+            Self::U8 => "u8",
+            Self::U16 => "u16",
+            Self::U32 => "u32",
+            Self::U64 => "u64",
+            Self::U128 => "u128",
+            Self::Usize => "usize",
+            Self::F32 => "f32",
+            Self::F64 => "f64",
+        }
+    }
+
+    pub fn is_real(&self) -> bool {
+        matches!(self, Self::F32 | Self::F64)
+    }
+
+    /// Whether the literal is a value of the suffix's type, when `negative`
+    /// the value of the literal after a minus like `-128i8`.
+    pub fn fits(&self, literal: &NumberLiteral, negative: bool) -> bool {
+        let max = match self {
+            Self::F32 => return literal.to_f64().abs() <= f32::MAX as f64,
+            Self::F64 => return literal.to_f64().is_finite(),
+
+            Self::I8 => i8::MAX as u128 + negative as u128,
+            Self::I16 => i16::MAX as u128 + negative as u128,
+            Self::I32 => i32::MAX as u128 + negative as u128,
+            Self::I64 | Self::Isize => i64::MAX as u128 + negative as u128,
+            Self::I128 => i128::MAX as u128 + negative as u128,
+            Self::U8 => u8::MAX as u128,
+            Self::U16 => u16::MAX as u128,
+            Self::U32 => u32::MAX as u128,
+            Self::U64 | Self::Usize => u64::MAX as u128,
+            Self::U128 => u128::MAX,
+        };
+
+        literal.to_u128(self.name()).is_ok_and(|i| i <= max)
+    }
+}
+
+impl Display for NumberSuffix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Comment {
     SingleLine,
     MultiLine,
+    /// `/// text`
+    DocLine,
+    /// `/** text */`
+    DocBlock,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+impl Comment {
+    /// Doc comments are emitted even when other comments are not.
+    pub fn is_doc(&self) -> bool {
+        matches!(self, Self::DocLine | Self::DocBlock)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Operator {
     Plus,
     Minus,
@@ -138,6 +423,7 @@ pub enum Operator {
     SingleAnd,
     SingleOr,
     LeftShift,
+    Pipeline,
     // binary
 
     // assignment
@@ -161,6 +447,31 @@ pub enum Operator {
     // assignment
 }
 
+impl Operator {
+    /// `=` and the compound assignments like `+=`.
+    pub const ASSIGNMENTS: &'static [Operator] = &[
+        Self::Equals,
+        Self::PlusEquals,
+        Self::MinusEquals,
+        Self::StarEquals,
+        Self::SlashEquals,
+        Self::TildaEquals,
+        Self::DoubleStarEquals,
+        Self::DoubleAndEquals,
+        Self::DoubleOrEquals,
+        Self::CaretEquals,
+        Self::PercentEquals,
+        Self::SingleAndEquals,
+        Self::SingleOrEquals,
+        Self::LeftShiftEquals,
+        Self::RightShiftEquals,
+    ];
+
+    pub fn is_assignment(&self) -> bool {
+        Self::ASSIGNMENTS.contains(self)
+    }
+}
+
 impl Display for Operator {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -185,6 +496,7 @@ impl Display for Operator {
             Self::SingleAnd => write!(f, "&"),
             Self::SingleOr => write!(f, "|"),
             Self::LeftShift => write!(f, "<<"),
+            Self::Pipeline => write!(f, "|>"),
             Self::Equals => write!(f, "="),
             Self::PlusEquals => write!(f, "+="),
             Self::MinusEquals => write!(f, "-="),
@@ -206,7 +518,7 @@ impl Display for Operator {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Keyword {
     Underscore,
     If,
@@ -215,6 +527,15 @@ pub enum Keyword {
     Continue,
     Return,
     Fn,
+    Match,
+    Let,
+    While,
+    SelfValue,
+    Super,
+    Macro,
+    Infix,
+    Infixl,
+    Infixr,
 }
 
 impl Display for Keyword {
@@ -227,6 +548,12 @@ impl Display for Keyword {
 pub enum TokenType {
     Identifier,
     Whitespace,
+    /// Line breaks, only separate from whitespace when the tokenizer emits newlines.
+    Newline,
+    /// The indentation grew, only emitted in the indentation mode.
+    Indent,
+    /// The indentation went back to an enclosing level.
+    Dedent,
 
     Punctuation(Punctuation),
     Delimeter(Delimeter),
@@ -242,6 +569,14 @@ pub struct Token<'s> {
     pub(crate) ty: TokenType,
 }
 
+/// Tokens are equal when they have the same type and text, wherever they are
+/// in the source.
+impl PartialEq for Token<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ty == other.ty && self.text() == other.text()
+    }
+}
+
 impl<'s> Token<'s> {
     pub fn position(&self) -> &TokenPosition<'s> {
         &self.position
@@ -279,6 +614,52 @@ impl<'s> TokenPosition<'s> {
     }
 }
 
+/// The bytes of the source a piece of syntax covers, from the start of its
+/// first token to the end of its last one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    pub fn range(&self) -> std::ops::Range<usize> {
+        self.start..self.end
+    }
+
+    /// The span covering both spans and everything between them.
+    pub fn to(&self, other: Span) -> Self {
+        Self::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
+impl From<TokenPosition<'_>> for Span {
+    fn from(position: TokenPosition<'_>) -> Self {
+        Self::new(
+            position.absolute_position,
+            position.absolute_position + position.text.len(),
+        )
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
 macro_rules! kws {
     ($($ss:literal => $kw:path),+ $(,)?) => {
         pub(crate) fn get_keyword(s: &str) -> Option<Keyword> {
@@ -289,6 +670,9 @@ macro_rules! kws {
             })
         }
 
+        /// Every keyword of the language.
+        pub const KEYWORDS: &[Keyword] = &[$($kw),+];
+
         pub(crate) fn get_keyword_str(k: &Keyword) -> &'static str {
             match k {
                 $($kw => $ss),+
@@ -305,4 +689,13 @@ kws!(
     "else" => Keyword::Else,
     "if" => Keyword::If,
     "fn" => Keyword::Fn,
+    "match" => Keyword::Match,
+    "let" => Keyword::Let,
+    "while" => Keyword::While,
+    "self" => Keyword::SelfValue,
+    "super" => Keyword::Super,
+    "macro" => Keyword::Macro,
+    "infix" => Keyword::Infix,
+    "infixl" => Keyword::Infixl,
+    "infixr" => Keyword::Infixr,
 );
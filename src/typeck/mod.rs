@@ -0,0 +1,1225 @@
+//! Type inference for the syntax tree, Hindley–Milner style.
+//!
+//! The types of the expressions are found by unifying type variables, the
+//! lambdas bound by `let` are generalized so they can be used at several
+//! types. The operators defined on several types constrain their operand's
+//! variable to them.
+//!
+//! An integer and a real operand give a real, as they do in the evaluator,
+//! when both types are known at the operator. Otherwise the operands are
+//! unified, so `(x => x + 2.5)(1)` is rejected although it evaluates.
+
+pub mod ty;
+
+use std::collections::HashMap;
+
+use crate::{
+    eval,
+    parser::{
+        expression::{Expression, Path, PathSegment},
+        pattern::{Pattern, PatternLiteral},
+        statement::{Block, Statement},
+        types::Type,
+    },
+    tokenizer::{Literal, NumberLiteral, Operator, Span},
+    units::UnitRegistry,
+    Diagnostic, DiagnosticType, Source,
+};
+
+pub use self::ty::{Constraint, Scheme, Ty, TyVar};
+
+enum UnifyError {
+    Mismatch,
+    /// A variable would contain itself.
+    Infinite,
+    /// A variable unified with a type outside its constraint.
+    Constraint(Constraint, Ty),
+}
+
+pub struct Checker<'n, 's, 'd, D: Extend<Diagnostic<'s>>> {
+    source: Source<'n, 's>,
+    diagnostics: &'d mut D,
+
+    /// What each type variable was unified with.
+    substitution: Vec<Option<Ty>>,
+    constraints: Vec<Constraint>,
+    /// The variables in scope, later scopes shadow earlier ones.
+    scopes: Vec<HashMap<String, Scheme>>,
+    /// The type of each expression, by its span.
+    types: HashMap<Span, Ty>,
+    /// The units of the quantity literals, which give their dimension.
+    units: UnitRegistry,
+}
+
+impl<'n, 's, 'd, D: Extend<Diagnostic<'s>>> Checker<'n, 's, 'd, D> {
+    /// `source` is the text the statements were parsed from, for the
+    /// positions of the diagnostics.
+    pub fn new(source: Source<'n, 's>, diagnostics: &'d mut D) -> Self {
+        Self::with_units(source, diagnostics, UnitRegistry::standard())
+    }
+
+    /// Checks the quantity literals in the given units.
+    pub fn with_units(
+        source: Source<'n, 's>,
+        diagnostics: &'d mut D,
+        units: &UnitRegistry,
+    ) -> Self {
+        Self {
+            source,
+            diagnostics,
+
+            substitution: vec![],
+            constraints: vec![],
+            scopes: vec![HashMap::new()],
+            types: HashMap::new(),
+            units: units.clone(),
+        }
+    }
+
+    /// Defines the types of the variables of [`eval::Environment::with_prelude`],
+    /// except `print` which takes any number of arguments.
+    pub fn define_builtins(&mut self) {
+        self.define("true", Scheme::mono(Ty::Bool));
+        self.define("false", Scheme::mono(Ty::Bool));
+
+        let len = self.forall(Constraint::ANY, |a| Ty::function([a], Ty::Integer));
+        self.define("len", len);
+
+        let abs = self.forall(Constraint::NUMERIC, |n| Ty::function([n.clone()], n));
+        self.define("abs", abs);
+
+        for name in ["min", "max"] {
+            let scheme = self.forall(Constraint::ORDERED, |a| {
+                Ty::function([a.clone(), a.clone()], a)
+            });
+            self.define(name, scheme);
+        }
+
+        for name in ["sqrt", "sin", "cos", "exp", "ln"] {
+            let scheme = self.forall(Constraint::NUMERIC, |n| Ty::function([n], Ty::Real));
+            self.define(name, scheme);
+        }
+
+        let string = self.forall(Constraint::ANY, |a| Ty::function([a], Ty::String));
+        self.define("string", string);
+    }
+
+    /// Defines a variable of the host, visible in the whole program.
+    pub fn define(&mut self, name: &str, scheme: Scheme) {
+        self.scopes[0].insert(name.to_string(), scheme);
+    }
+
+    /// Checks the statements, returning the type of the last one.
+    pub fn check(&mut self, statements: &[Statement<'s>]) -> Ty {
+        let ty = self.statements(statements);
+        self.resolve(&ty)
+    }
+
+    /// The type inferred for the expression with the span.
+    pub fn type_at(&self, span: Span) -> Option<Ty> {
+        self.types.get(&span).map(|ty| self.resolve(ty))
+    }
+
+    /// The type with the variables unified so far replaced by their types.
+    pub fn resolve(&self, ty: &Ty) -> Ty {
+        match self.shallow(ty) {
+            Ty::Tuple(items) => Ty::Tuple(items.iter().map(|t| self.resolve(t)).collect()),
+            Ty::Array(element) => Ty::array(self.resolve(&element)),
+            Ty::Map(key, value) => {
+                Ty::Map(Box::new(self.resolve(&key)), Box::new(self.resolve(&value)))
+            }
+            Ty::Function(parameters, output) => Ty::function(
+                parameters.iter().map(|t| self.resolve(t)),
+                self.resolve(&output),
+            ),
+
+            ty => ty,
+        }
+    }
+
+    /// The type with the variable it is replaced by, but not the ones in it.
+    fn shallow(&self, ty: &Ty) -> Ty {
+        let mut ty = ty;
+
+        while let Ty::Var(v) = ty {
+            match &self.substitution[v.0 as usize] {
+                Some(t) => ty = t,
+                None => break,
+            }
+        }
+
+        ty.clone()
+    }
+
+    fn fresh_var(&mut self, constraint: Constraint) -> TyVar {
+        self.substitution.push(None);
+        self.constraints.push(constraint);
+
+        TyVar(self.substitution.len() as u32 - 1)
+    }
+
+    fn fresh(&mut self, constraint: Constraint) -> Ty {
+        Ty::Var(self.fresh_var(constraint))
+    }
+
+    /// A scheme with a single variable.
+    fn forall(&mut self, constraint: Constraint, ty: impl FnOnce(Ty) -> Ty) -> Scheme {
+        let var = self.fresh_var(constraint);
+
+        Scheme {
+            vars: vec![(var, constraint)],
+            ty: ty(Ty::Var(var)),
+        }
+    }
+
+    fn instantiate(&mut self, scheme: &Scheme) -> Ty {
+        let with: Vec<_> = scheme
+            .vars
+            .iter()
+            .map(|(var, constraint)| (*var, self.fresh(*constraint)))
+            .collect();
+
+        scheme.ty.replace(&with)
+    }
+
+    /// The scheme of the type with the variables that aren't in the types
+    /// of the variables in scope made generic.
+    fn generalize(&self, ty: &Ty) -> Scheme {
+        let ty = self.resolve(ty);
+
+        let mut vars = vec![];
+        ty.vars(&mut vars);
+
+        let mut in_scope = vec![];
+        for scheme in self.scopes.iter().flat_map(|s| s.values()) {
+            let mut free = vec![];
+            self.resolve(&scheme.ty).vars(&mut free);
+
+            in_scope.extend(
+                free.into_iter()
+                    .filter(|v| scheme.vars.iter().all(|(g, _)| g != v)),
+            );
+        }
+
+        vars.retain(|v| !in_scope.contains(v));
+
+        Scheme {
+            vars: vars
+                .into_iter()
+                .map(|v| (v, self.constraints[v.0 as usize]))
+                .collect(),
+            ty,
+        }
+    }
+
+    fn define_local(&mut self, name: &str, scheme: Scheme) {
+        self.scopes
+            .last_mut()
+            .expect("the host scope is never popped")
+            .insert(name.to_string(), scheme);
+    }
+
+    fn lookup(&mut self, name: &str) -> Option<Ty> {
+        let scheme = self.scopes.iter().rev().find_map(|s| s.get(name))?.clone();
+        Some(self.instantiate(&scheme))
+    }
+
+    /// The type of the variable, unknown if it isn't defined since the
+    /// [`Resolver`](crate::resolve::Resolver) reports those.
+    fn name(&mut self, path: &Path<'s>) -> Ty {
+        match self.lookup(&path.to_string()) {
+            Some(ty) => ty,
+            None => self.fresh(Constraint::ANY),
+        }
+    }
+
+    fn unify(&mut self, a: &Ty, b: &Ty) -> Result<(), UnifyError> {
+        let (a, b) = (self.shallow(a), self.shallow(b));
+
+        match (&a, &b) {
+            (Ty::Var(x), Ty::Var(y)) if x == y => Ok(()),
+            (Ty::Var(x), Ty::Var(y)) => {
+                let (x, y) = (x.0 as usize, y.0 as usize);
+                let both = self.constraints[x]
+                    .and(self.constraints[y])
+                    .ok_or(UnifyError::Mismatch)?;
+
+                self.constraints[y] = both;
+                self.substitution[x] = Some(b);
+                Ok(())
+            }
+
+            (Ty::Var(var), ty) | (ty, Ty::Var(var)) => {
+                let ty = self.resolve(ty);
+                let constraint = self.constraints[var.0 as usize];
+
+                if ty.contains(*var) {
+                    return Err(UnifyError::Infinite);
+                }
+
+                if !constraint.allows(&ty) {
+                    return Err(UnifyError::Constraint(constraint, ty));
+                }
+
+                self.substitution[var.0 as usize] = Some(ty);
+                Ok(())
+            }
+
+            (Ty::Tuple(a), Ty::Tuple(b)) if a.len() == b.len() => {
+                a.iter().zip(b).try_for_each(|(a, b)| self.unify(a, b))
+            }
+            (Ty::Array(a), Ty::Array(b)) => self.unify(a, b),
+            (Ty::Map(a_key, a_value), Ty::Map(b_key, b_value)) => {
+                self.unify(a_key, b_key)?;
+                self.unify(a_value, b_value)
+            }
+            (Ty::Function(a_parameters, a_output), Ty::Function(b_parameters, b_output))
+                if a_parameters.len() == b_parameters.len() =>
+            {
+                for (a, b) in a_parameters.iter().zip(b_parameters) {
+                    self.unify(a, b)?;
+                }
+
+                self.unify(a_output, b_output)
+            }
+
+            (a, b) if a == b => Ok(()),
+            _ => Err(UnifyError::Mismatch),
+        }
+    }
+
+    fn report(&mut self, ty: DiagnosticType, span: Span) {
+        let position = self.source.position(span);
+        self.diagnostics.extend([Diagnostic::new(ty, position)]);
+    }
+
+    fn mismatch(&mut self, expected: String, found: &Ty, span: Span, expected_at: Option<Span>) {
+        let found = self.resolve(found).to_string();
+        self.report(
+            DiagnosticType::TypeMismatch {
+                expected,
+                found,
+                expected_at,
+            },
+            span,
+        );
+    }
+
+    /// Unifies the type found at `span` with the one expected there, which
+    /// comes from the expression at `expected_at` if any. Returns whether
+    /// they were unified.
+    fn expect(&mut self, found: &Ty, span: Span, expected: &Ty, expected_at: Option<Span>) -> bool {
+        let error = match self.unify(expected, found) {
+            Ok(()) => return true,
+            Err(e) => e,
+        };
+
+        match error {
+            UnifyError::Mismatch => {
+                let expected = self.resolve(expected).to_string();
+                self.mismatch(expected, found, span, expected_at);
+            }
+            UnifyError::Infinite => self.report(DiagnosticType::InfiniteType, span),
+            UnifyError::Constraint(constraint, ty) => {
+                self.mismatch(constraint.to_string(), &ty, span, expected_at)
+            }
+        }
+
+        false
+    }
+
+    /// Restricts the type found at `span` to the constraint.
+    fn constrain(&mut self, ty: &Ty, constraint: Constraint, span: Span) -> bool {
+        match self.shallow(ty) {
+            Ty::Var(var) => match self.constraints[var.0 as usize].and(constraint) {
+                Some(both) => {
+                    self.constraints[var.0 as usize] = both;
+                    true
+                }
+                None => {
+                    let found = self.constraints[var.0 as usize].to_string();
+                    self.report(
+                        DiagnosticType::TypeMismatch {
+                            expected: constraint.to_string(),
+                            found,
+                            expected_at: None,
+                        },
+                        span,
+                    );
+
+                    false
+                }
+            },
+
+            ty if constraint.allows(&ty) => true,
+            ty => {
+                self.mismatch(constraint.to_string(), &ty, span, None);
+                false
+            }
+        }
+    }
+
+    /// The type of an annotation, the paths that aren't builtin types are
+    /// the types of struct literals.
+    fn annotation(&mut self, ty: &Type<'s>) -> Ty {
+        match ty {
+            Type::Infer => self.fresh(Constraint::ANY),
+
+            Type::Path(path) => match path.as_identifier() {
+                Some(
+                    "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64"
+                    | "u128" | "usize",
+                ) => Ty::Integer,
+                Some("f32" | "f64") => Ty::Real,
+                Some("String" | "str") => Ty::String,
+                Some("char") => Ty::Char,
+                Some("bool") => Ty::Bool,
+
+                _ => match path.segments().last() {
+                    Some(PathSegment::Name { name, generics }) => match (*name, &generics[..]) {
+                        ("Vec", [element]) => Ty::array(self.annotation(element)),
+                        ("Map" | "HashMap", [key, value]) => Ty::Map(
+                            Box::new(self.annotation(key)),
+                            Box::new(self.annotation(value)),
+                        ),
+
+                        _ => Ty::Struct(path.to_string()),
+                    },
+
+                    _ => Ty::Struct(path.to_string()),
+                },
+            },
+
+            Type::Tuple(items) => Ty::Tuple(items.iter().map(|t| self.annotation(t)).collect()),
+            Type::Array { element, .. } => Ty::array(self.annotation(element)),
+            Type::Function { parameters, output } => {
+                let parameters: Vec<_> = parameters.iter().map(|t| self.annotation(t)).collect();
+                Ty::function(parameters, self.annotation(output))
+            }
+        }
+    }
+
+    fn statements(&mut self, statements: &[Statement<'s>]) -> Ty {
+        let mut ty = Ty::unit();
+
+        for s in statements {
+            ty = self.statement(s);
+        }
+
+        ty
+    }
+
+    fn statement(&mut self, statement: &Statement<'s>) -> Ty {
+        match statement.inner() {
+            Statement::Let { pattern, ty, value } => {
+                self.let_statement(pattern, ty.as_ref(), value.as_ref());
+                Ty::unit()
+            }
+
+            Statement::Expression { expr, semicolon } => {
+                let ty = self.expression(expr);
+
+                match semicolon {
+                    true => Ty::unit(),
+                    false => ty,
+                }
+            }
+
+            Statement::Macro(_) | Statement::Fixity { .. } => Ty::unit(),
+
+            Statement::Attributed { .. } | Statement::Documented { .. } => unreachable!(),
+        }
+    }
+
+    fn let_statement(
+        &mut self,
+        pattern: &Pattern<'s>,
+        ty: Option<&Type<'s>>,
+        value: Option<&Expression<'s>>,
+    ) {
+        let annotation = ty.map(|t| self.annotation(t));
+
+        // only lambdas are generalized, the other values are computed once
+        let (ty, generalize) = match (value, pattern) {
+            // a lambda can call itself through the name it's bound to
+            (Some(value @ Expression::Lambda { .. }), Pattern::Binding(name)) => {
+                let itself = match &annotation {
+                    Some(annotation) => annotation.clone(),
+                    None => self.fresh(Constraint::ANY),
+                };
+
+                self.scopes.push(HashMap::from([(
+                    name.to_string(),
+                    Scheme::mono(itself.clone()),
+                )]));
+                let ty = self.expression(value);
+                self.scopes.pop();
+
+                self.expect(&ty, value.span(), &itself, None);
+                (ty, true)
+            }
+
+            (Some(value), _) => {
+                let ty = self.expression(value);
+
+                if let Some(annotation) = &annotation {
+                    self.expect(&ty, value.span(), annotation, None);
+                }
+
+                (ty, matches!(value, Expression::Lambda { .. }))
+            }
+
+            (None, _) => match annotation {
+                Some(annotation) => (annotation, false),
+                None => (self.fresh(Constraint::ANY), false),
+            },
+        };
+
+        let span = value.map(|v| v.span()).unwrap_or_default();
+        let mut bindings = vec![];
+        self.pattern(pattern, &ty, span, None, &mut bindings);
+
+        for (name, ty) in bindings {
+            let scheme = match generalize {
+                true => self.generalize(&ty),
+                false => Scheme::mono(ty),
+            };
+
+            self.define_local(name, scheme);
+        }
+    }
+
+    /// Checks that the pattern matches values of the type, collecting the
+    /// types of its bindings. Patterns have no spans, the mismatches are
+    /// reported at `span`.
+    fn pattern(
+        &mut self,
+        pattern: &Pattern<'s>,
+        ty: &Ty,
+        span: Span,
+        expected_at: Option<Span>,
+        bindings: &mut Vec<(&'s str, Ty)>,
+    ) {
+        match pattern {
+            Pattern::Wildcard => (),
+
+            Pattern::Binding(name) => bindings.push((name, ty.clone())),
+
+            Pattern::Literal(literal) => self.pattern_literal(literal, ty, span, expected_at),
+
+            Pattern::Range { start, end, .. } => {
+                for literal in start.iter().chain(end) {
+                    self.pattern_literal(literal, ty, span, expected_at);
+                }
+
+                self.constrain(ty, Constraint::ORDERED, span);
+            }
+
+            Pattern::Tuple(patterns) => {
+                let items: Vec<_> = patterns
+                    .iter()
+                    .map(|_| self.fresh(Constraint::ANY))
+                    .collect();
+                self.expect(&Ty::Tuple(items.clone()), span, ty, expected_at);
+
+                for (p, item) in patterns.iter().zip(&items) {
+                    self.pattern(p, item, span, expected_at, bindings);
+                }
+            }
+
+            Pattern::Path(path) => {
+                if let Some(constant) = self.lookup(&path.to_string()) {
+                    self.expect(&constant, span, ty, expected_at);
+                }
+            }
+
+            Pattern::TupleStruct { path, fields } => {
+                let items: Vec<_> = fields.iter().map(|_| self.fresh(Constraint::ANY)).collect();
+
+                if let Some(constructor) = self.lookup(&path.to_string()) {
+                    let output = self.fresh(Constraint::ANY);
+                    let found = Ty::function(items.clone(), output.clone());
+
+                    if self.expect(&found, span, &constructor, None) {
+                        self.expect(&output, span, ty, expected_at);
+                    }
+                }
+
+                for (p, item) in fields.iter().zip(&items) {
+                    self.pattern(p, item, span, expected_at, bindings);
+                }
+            }
+
+            // every alternative binds the same names, to the same types
+            Pattern::Or(alternatives) => {
+                let mut first: Option<Vec<_>> = None;
+
+                for a in alternatives {
+                    let mut alternative = vec![];
+                    self.pattern(a, ty, span, expected_at, &mut alternative);
+
+                    let Some(first) = &first else {
+                        first = Some(alternative);
+                        continue;
+                    };
+
+                    for (name, ty) in alternative {
+                        if let Some((_, expected)) = first.iter().find(|(n, _)| *n == name) {
+                            self.expect(&ty, span, expected, None);
+                        }
+                    }
+                }
+
+                bindings.extend(first.unwrap_or_default());
+            }
+        }
+    }
+
+    fn block(&mut self, block: &Block<'s>) -> Ty {
+        self.scopes.push(HashMap::new());
+        let ty = self.statements(block.statements());
+        self.scopes.pop();
+
+        ty
+    }
+
+    fn expression(&mut self, expr: &Expression<'s>) -> Ty {
+        let ty = self.infer(expr);
+
+        if expr.span() != Span::default() {
+            self.types.insert(expr.span(), ty.clone());
+        }
+
+        ty
+    }
+
+    fn infer(&mut self, expr: &Expression<'s>) -> Ty {
+        let span = expr.span();
+
+        match expr {
+            Expression::Lit { value, .. } => self.literal(value),
+
+            Expression::Name(path) => self.name(path),
+
+            Expression::Prefix { op, right, .. } => {
+                let ty = self.expression(right);
+
+                match op {
+                    Operator::Minus | Operator::Plus => {
+                        self.constrain(&ty, Constraint::SIGNED, right.span());
+                        ty
+                    }
+                    Operator::DoublePlus | Operator::DoubleMinus => {
+                        self.constrain(&ty, Constraint::NUMERIC, right.span());
+                        ty
+                    }
+                    Operator::Bang => {
+                        self.constrain(&ty, Constraint::BITWISE, right.span());
+                        ty
+                    }
+                    Operator::Tilda => {
+                        self.expect(&ty, right.span(), &Ty::Integer, None);
+                        Ty::Integer
+                    }
+
+                    _ => self.fresh(Constraint::ANY),
+                }
+            }
+
+            Expression::Postfix { left, op, .. } => {
+                let ty = self.expression(left);
+
+                match op {
+                    // factorial
+                    Operator::Bang => {
+                        self.expect(&ty, left.span(), &Ty::Integer, None);
+                        Ty::Integer
+                    }
+
+                    _ => self.fresh(Constraint::ANY),
+                }
+            }
+
+            Expression::Infix {
+                left, op, right, ..
+            } => match op {
+                Operator::Pipeline => {
+                    let argument = (self.expression(left), left.span());
+
+                    // `x |> f(y)` is `f(x, y)`
+                    if let Expression::Call {
+                        function,
+                        arguments,
+                        ..
+                    } = right.as_ref()
+                    {
+                        let callee = self.name(function);
+
+                        let mut all = vec![argument];
+                        for a in arguments {
+                            all.push((self.expression(a), a.span()));
+                        }
+
+                        self.call(callee, function.span(), all, span)
+                    } else {
+                        let callee = self.expression(right);
+                        self.call(callee, right.span(), vec![argument], span)
+                    }
+                }
+
+                op if op.is_assignment() => {
+                    let target = (self.expression(left), left.span());
+                    let value = (self.expression(right), right.span());
+
+                    if *op == Operator::Equals {
+                        self.expect(&value.0, value.1, &target.0, Some(target.1));
+                    } else {
+                        let result = self.operator(eval::compound_operator(*op), &target, &value);
+                        self.expect(&result, span, &target.0, Some(target.1));
+                    }
+
+                    Ty::unit()
+                }
+
+                op => {
+                    let left = (self.expression(left), left.span());
+                    let right = (self.expression(right), right.span());
+
+                    self.operator(*op, &left, &right)
+                }
+            },
+
+            Expression::CustomInfix {
+                left, op, right, ..
+            } => {
+                let callee = match self.lookup(op) {
+                    Some(ty) => ty,
+                    None => self.fresh(Constraint::ANY),
+                };
+
+                let arguments = vec![
+                    (self.expression(left), left.span()),
+                    (self.expression(right), right.span()),
+                ];
+
+                self.call(callee, span, arguments, span)
+            }
+
+            Expression::Call {
+                function,
+                arguments,
+                ..
+            } => {
+                let callee = self.name(function);
+                let arguments = arguments
+                    .iter()
+                    .map(|a| (self.expression(a), a.span()))
+                    .collect();
+
+                self.call(callee, function.span(), arguments, span)
+            }
+
+            Expression::Index { expr, with, .. } => self.index(expr, with),
+
+            Expression::Lambda {
+                parameters, body, ..
+            } => {
+                self.scopes.push(HashMap::new());
+
+                let mut types = vec![];
+                for p in parameters {
+                    let ty = self.fresh(Constraint::ANY);
+                    self.define_local(p.name(), Scheme::mono(ty.clone()));
+                    types.push(ty);
+                }
+
+                let output = self.expression(body);
+                self.scopes.pop();
+
+                Ty::function(types, output)
+            }
+
+            Expression::Match {
+                scrutinee, arms, ..
+            } => {
+                let ty = self.expression(scrutinee);
+                let mut result: Option<(Ty, Span)> = None;
+
+                for arm in arms {
+                    self.scopes.push(HashMap::new());
+
+                    let mut bindings = vec![];
+                    let at = Span::from(arm.position);
+                    self.pattern(&arm.pattern, &ty, at, Some(scrutinee.span()), &mut bindings);
+
+                    for (name, ty) in bindings {
+                        self.define_local(name, Scheme::mono(ty));
+                    }
+
+                    if let Some(guard) = &arm.guard {
+                        let guard_ty = self.expression(guard);
+                        self.expect(&guard_ty, guard.span(), &Ty::Bool, None);
+                    }
+
+                    let body = self.expression(&arm.body);
+                    match &result {
+                        Some((first, first_span)) => {
+                            self.expect(&body, arm.body.span(), first, Some(*first_span));
+                        }
+                        None => result = Some((body, arm.body.span())),
+                    }
+
+                    self.scopes.pop();
+                }
+
+                match result {
+                    Some((ty, _)) => ty,
+                    None => self.fresh(Constraint::ANY),
+                }
+            }
+
+            Expression::Tuple { items, .. } => {
+                Ty::Tuple(items.iter().map(|i| self.expression(i)).collect())
+            }
+
+            Expression::Array { items, .. } => Ty::array(self.same(items.iter())),
+
+            Expression::Repeat { value, count, .. } => {
+                let element = self.expression(value);
+                let count_ty = self.expression(count);
+                self.expect(&count_ty, count.span(), &Ty::Integer, None);
+
+                Ty::array(element)
+            }
+
+            Expression::Map { entries, .. } => {
+                let key = self.same(entries.iter().map(|(k, _)| k));
+                let value = self.same(entries.iter().map(|(_, v)| v));
+
+                Ty::Map(Box::new(key), Box::new(value))
+            }
+
+            Expression::Struct {
+                path, fields, base, ..
+            } => {
+                for f in fields {
+                    match &f.value {
+                        Some(value) => {
+                            self.expression(value);
+                        }
+                        None => {
+                            self.lookup(f.name());
+                        }
+                    }
+                }
+
+                let ty = Ty::Struct(path.to_string());
+
+                if let Some(base) = base {
+                    let base_ty = self.expression(base);
+                    self.expect(&base_ty, base.span(), &ty, None);
+                }
+
+                ty
+            }
+
+            Expression::Block(block) => self.block(block),
+
+            Expression::If {
+                condition,
+                then,
+                otherwise,
+                ..
+            } => {
+                let condition_ty = self.expression(condition);
+                self.expect(&condition_ty, condition.span(), &Ty::Bool, None);
+
+                let then_ty = self.block(then);
+
+                match otherwise {
+                    Some(otherwise) => {
+                        let otherwise_ty = self.expression(otherwise);
+                        self.expect(&otherwise_ty, otherwise.span(), &then_ty, Some(then.span()));
+
+                        then_ty
+                    }
+
+                    // the value is the empty tuple when the condition is false
+                    None => {
+                        self.expect(&then_ty, then.span(), &Ty::unit(), None);
+                        Ty::unit()
+                    }
+                }
+            }
+
+            Expression::While {
+                condition, body, ..
+            } => {
+                let condition_ty = self.expression(condition);
+                self.expect(&condition_ty, condition.span(), &Ty::Bool, None);
+                self.block(body);
+
+                Ty::unit()
+            }
+
+            Expression::Attributed { expr, .. } => self.expression(expr),
+
+            // left by a failed expansion
+            Expression::MacroCall { .. } => self.fresh(Constraint::ANY),
+        }
+    }
+
+    /// The type of the expressions, which have to be the same as the first.
+    fn same<'e>(&mut self, exprs: impl Iterator<Item = &'e Expression<'s>>) -> Ty
+    where
+        's: 'e,
+    {
+        let ty = self.fresh(Constraint::ANY);
+        let mut first = None;
+
+        for e in exprs {
+            let found = self.expression(e);
+            self.expect(&found, e.span(), &ty, first);
+            first.get_or_insert(e.span());
+        }
+
+        ty
+    }
+
+    /// The type of a literal, a new variable for a quantity of an unknown
+    /// unit since the tokenizer reports it.
+    fn literal(&mut self, literal: &Literal) -> Ty {
+        match literal {
+            Literal::Number(_, Some(suffix)) if suffix.is_real() => Ty::Real,
+            Literal::Number(_, Some(_)) => Ty::Integer,
+            Literal::Number(n, None) if n.is_integer() => Ty::Integer,
+            Literal::Number(_, None) => Ty::Real,
+            Literal::Quantity { unit, .. } => match self.units.get(unit) {
+                Some(unit) => Ty::Quantity(unit.dimension()),
+                None => self.fresh(Constraint::ANY),
+            },
+            Literal::String(_) => Ty::String,
+            Literal::Char(_) => Ty::Char,
+        }
+    }
+
+    /// Checks a literal of a pattern matching a value of type `ty`.
+    fn pattern_literal(
+        &mut self,
+        literal: &PatternLiteral,
+        ty: &Ty,
+        span: Span,
+        expected_at: Option<Span>,
+    ) {
+        let found = self.literal(literal.value());
+
+        if self.expect(&found, span, ty, expected_at) && literal.is_negative() {
+            self.constrain(ty, Constraint::SIGNED, span);
+        }
+    }
+
+    /// The type of a binary operator's result, `left` and `right` are the
+    /// types of the operands with their spans.
+    fn operator(&mut self, op: Operator, left: &(Ty, Span), right: &(Ty, Span)) -> Ty {
+        use Operator as Op;
+
+        // an integer with a real is computed with reals, like in `eval`
+        let mixed = matches!(
+            (self.shallow(&left.0), self.shallow(&right.0)),
+            (Ty::Integer, Ty::Real) | (Ty::Real, Ty::Integer)
+        );
+
+        match op {
+            Op::Plus | Op::Minus | Op::Star | Op::Slash | Op::Percent | Op::DoubleStar if mixed => {
+                Ty::Real
+            }
+            Op::DoubleEquals
+            | Op::BangEquals
+            | Op::LesserThan
+            | Op::GreaterThan
+            | Op::LesserThanEquals
+            | Op::GreaterThanEquals
+                if mixed =>
+            {
+                Ty::Bool
+            }
+
+            Op::DoubleAnd | Op::DoubleOr => {
+                self.expect(&left.0, left.1, &Ty::Bool, None);
+                self.expect(&right.0, right.1, &Ty::Bool, None);
+
+                Ty::Bool
+            }
+
+            Op::DoubleEquals | Op::BangEquals => {
+                self.expect(&right.0, right.1, &left.0, Some(left.1));
+                Ty::Bool
+            }
+
+            Op::LesserThan | Op::GreaterThan | Op::LesserThanEquals | Op::GreaterThanEquals => {
+                self.same_operands(left, right, Constraint::ORDERED);
+                Ty::Bool
+            }
+
+            Op::Plus => match (self.shallow(&left.0), self.shallow(&right.0)) {
+                (Ty::String, Ty::Char) | (Ty::Char, Ty::String) => Ty::String,
+
+                _ => {
+                    self.same_operands(left, right, Constraint::ADDABLE);
+                    left.0.clone()
+                }
+            },
+
+            Op::Minus | Op::Percent => {
+                self.same_operands(left, right, Constraint::SIGNED);
+                left.0.clone()
+            }
+
+            // a quantity scales by a number, and divided by a quantity of
+            // its dimension it's the number of times it's in it
+            Op::Star | Op::Slash => match (op, self.shallow(&left.0), self.shallow(&right.0)) {
+                (Op::Slash, Ty::Quantity(_), Ty::Quantity(_)) => {
+                    self.expect(&right.0, right.1, &left.0, Some(left.1));
+                    Ty::Real
+                }
+                (_, quantity @ Ty::Quantity(_), _) => {
+                    self.constrain(&right.0, Constraint::NUMERIC, right.1);
+                    quantity
+                }
+                (Op::Star, _, quantity @ Ty::Quantity(_)) => {
+                    self.constrain(&left.0, Constraint::NUMERIC, left.1);
+                    quantity
+                }
+
+                _ => {
+                    self.same_operands(left, right, Constraint::NUMERIC);
+                    left.0.clone()
+                }
+            },
+
+            Op::DoubleStar => {
+                self.same_operands(left, right, Constraint::NUMERIC);
+                left.0.clone()
+            }
+
+            Op::SingleAnd | Op::SingleOr | Op::Caret => {
+                self.same_operands(left, right, Constraint::BITWISE);
+                left.0.clone()
+            }
+
+            Op::LeftShift | Op::RightShift => {
+                self.expect(&left.0, left.1, &Ty::Integer, None);
+                self.expect(&right.0, right.1, &Ty::Integer, None);
+
+                Ty::Integer
+            }
+
+            _ => self.fresh(Constraint::ANY),
+        }
+    }
+
+    /// Operands of the same type, which satisfies the constraint.
+    fn same_operands(&mut self, left: &(Ty, Span), right: &(Ty, Span), constraint: Constraint) {
+        if self.expect(&right.0, right.1, &left.0, Some(left.1)) {
+            self.constrain(&left.0, constraint, left.1);
+        }
+    }
+
+    /// The type of the result of calling the function found at
+    /// `callee_span` with the arguments, at `span`.
+    fn call(
+        &mut self,
+        callee: Ty,
+        callee_span: Span,
+        arguments: Vec<(Ty, Span)>,
+        span: Span,
+    ) -> Ty {
+        match self.shallow(&callee) {
+            Ty::Function(parameters, output) if parameters.len() == arguments.len() => {
+                for (p, (a, a_span)) in parameters.iter().zip(&arguments) {
+                    self.expect(a, *a_span, p, Some(callee_span));
+                }
+
+                *output
+            }
+
+            Ty::Var(_) | Ty::Function(..) => {
+                let output = self.fresh(Constraint::ANY);
+                let found = Ty::function(arguments.into_iter().map(|(t, _)| t), output.clone());
+                self.expect(&found, span, &callee, Some(callee_span));
+
+                output
+            }
+
+            ty => {
+                self.mismatch("a function".to_string(), &ty, callee_span, None);
+                self.fresh(Constraint::ANY)
+            }
+        }
+    }
+
+    fn index(&mut self, expr: &Expression<'s>, with: &Expression<'s>) -> Ty {
+        let ty = self.expression(expr);
+        let index = self.expression(with);
+
+        match self.shallow(&ty) {
+            Ty::String => {
+                self.expect(&index, with.span(), &Ty::Integer, None);
+                Ty::Char
+            }
+
+            Ty::Array(element) => {
+                self.expect(&index, with.span(), &Ty::Integer, None);
+                *element
+            }
+
+            Ty::Map(key, value) => {
+                self.expect(&index, with.span(), &key, Some(expr.span()));
+                *value
+            }
+
+            Ty::Tuple(items) => {
+                self.expect(&index, with.span(), &Ty::Integer, None);
+
+                match with {
+                    Expression::Lit {
+                        value: Literal::Number(NumberLiteral::Integer(i), _),
+                        ..
+                    } if (*i as usize) < items.len() => items[*i as usize].clone(),
+
+                    // the index is only known when running, so every item
+                    // has to have the same type
+                    _ => {
+                        let element = self.fresh(Constraint::ANY);
+                        for item in &items {
+                            self.expect(item, expr.span(), &element, None);
+                        }
+
+                        element
+                    }
+                }
+            }
+
+            Ty::Var(_) => {
+                let element = self.fresh(Constraint::ANY);
+                self.expect(&ty, expr.span(), &Ty::array(element.clone()), None);
+                self.expect(&index, with.span(), &Ty::Integer, None);
+
+                element
+            }
+
+            ty => {
+                let expected = "an array, a string, a map or a tuple".to_string();
+                self.mismatch(expected, &ty, expr.span(), None);
+
+                self.fresh(Constraint::ANY)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        eval::{self, Environment, Value},
+        parser::Parser,
+        tokenizer::Tokenizer,
+    };
+
+    fn parse(text: &str) -> Vec<Statement<'_>> {
+        let source = Source::new("test", text);
+        let (mut tokenizer_diagnostics, mut parser_diagnostics) = (vec![], vec![]);
+
+        let tokenizer = Tokenizer::new(source, &mut tokenizer_diagnostics);
+        Parser::new(tokenizer, &mut parser_diagnostics, Default::default())
+            .program()
+            .unwrap()
+    }
+
+    /// The type of the statements, and the diagnostics' types.
+    fn check(text: &str) -> (String, Vec<DiagnosticType>) {
+        let statements = parse(text);
+        let mut diagnostics = vec![];
+
+        let mut checker = Checker::new(Source::new("test", text), &mut diagnostics);
+        checker.define_builtins();
+        let ty = checker.check(&statements).to_string();
+
+        (ty, diagnostics.iter().map(|d| d.ty().clone()).collect())
+    }
+
+    fn checked(text: &str) -> String {
+        let (ty, diagnostics) = check(text);
+        assert!(diagnostics.is_empty(), "{text}: {diagnostics:?}");
+
+        ty
+    }
+
+    #[test]
+    fn integers_with_reals() {
+        for (text, ty, value) in [
+            ("1 + 2.5", "real", Value::Real(3.5)),
+            ("2.5 * 2", "real", Value::Real(5.)),
+            ("7 % 2.5", "real", Value::Real(2.)),
+            ("1 < 2.5", "bool", Value::Bool(true)),
+            ("2 == 2.0", "bool", Value::Bool(true)),
+        ] {
+            assert_eq!(checked(text), ty, "{text}");
+
+            let value_found = eval::run(&parse(text), &Environment::with_prelude()).unwrap();
+            assert_eq!(value_found, value, "{text}");
+        }
+
+        // not known to be a real at the operator
+        let (_, diagnostics) = check("let f = x => x + 2.5; f(1)");
+        assert!(matches!(
+            diagnostics.as_slice(),
+            [DiagnosticType::TypeMismatch { .. }]
+        ));
+    }
+
+    #[test]
+    fn let_polymorphism() {
+        assert_eq!(
+            checked("let id = x => x; (id(1), id(\"a\"))"),
+            "(integer, string)"
+        );
+
+        // a parameter isn't generalized
+        let (_, diagnostics) = check("let f = id => (id(1), id(\"a\"));");
+        assert!(matches!(
+            diagnostics.as_slice(),
+            [DiagnosticType::TypeMismatch { .. }]
+        ));
+    }
+
+    #[test]
+    fn infinite_types() {
+        let (_, diagnostics) = check("let f = g => g(g);");
+        assert!(matches!(
+            diagnostics.as_slice(),
+            [DiagnosticType::InfiniteType]
+        ));
+    }
+
+    #[test]
+    fn mismatches() {
+        let (_, diagnostics) = check("let x = 1; x + \"a\"");
+        assert_eq!(
+            diagnostics,
+            [DiagnosticType::TypeMismatch {
+                expected: "integer".to_string(),
+                found: "string".to_string(),
+                expected_at: Some(Span::new(11, 12)),
+            }]
+        );
+
+        let (_, diagnostics) = check("if 1 { 2 } else { 3 }");
+        assert!(matches!(
+            diagnostics.as_slice(),
+            [DiagnosticType::TypeMismatch { expected, found, expected_at: None }]
+                if expected == "bool" && found == "integer"
+        ));
+    }
+}
@@ -0,0 +1,248 @@
+use std::fmt::Display;
+
+use crate::{parser::write_separated, units::Dimension};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TyVar(pub(crate) u32);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ty {
+    /// A type not known yet, see [`Constraint`] for the ones it can be.
+    Var(TyVar),
+
+    Integer,
+    Real,
+    String,
+    Char,
+    Bool,
+    /// A number with a unit of the dimension, like `500ms`.
+    Quantity(Dimension),
+
+    /// The empty tuple is the type of statements and of loops.
+    Tuple(Vec<Ty>),
+    Array(Box<Ty>),
+    Map(Box<Ty>, Box<Ty>),
+    Function(Vec<Ty>, Box<Ty>),
+    /// The type of a struct literal, by the path of the struct.
+    Struct(String),
+}
+
+impl Ty {
+    pub fn unit() -> Self {
+        Self::Tuple(vec![])
+    }
+
+    pub fn array(element: Ty) -> Self {
+        Self::Array(Box::new(element))
+    }
+
+    pub fn function(parameters: impl IntoIterator<Item = Ty>, output: Ty) -> Self {
+        Self::Function(parameters.into_iter().collect(), Box::new(output))
+    }
+
+    /// Whether the type variable appears in the type, which has to be
+    /// resolved first.
+    pub fn contains(&self, var: TyVar) -> bool {
+        match self {
+            Self::Var(v) => *v == var,
+            Self::Tuple(items) => items.iter().any(|t| t.contains(var)),
+            Self::Array(element) => element.contains(var),
+            Self::Map(key, value) => key.contains(var) || value.contains(var),
+            Self::Function(parameters, output) => {
+                parameters.iter().any(|t| t.contains(var)) || output.contains(var)
+            }
+
+            Self::Integer
+            | Self::Real
+            | Self::String
+            | Self::Char
+            | Self::Bool
+            | Self::Quantity(_)
+            | Self::Struct(_) => false,
+        }
+    }
+
+    /// The type variables in the type, each once.
+    pub fn vars(&self, out: &mut Vec<TyVar>) {
+        match self {
+            Self::Var(v) if !out.contains(v) => out.push(*v),
+            Self::Tuple(items) => items.iter().for_each(|t| t.vars(out)),
+            Self::Array(element) => element.vars(out),
+            Self::Map(key, value) => {
+                key.vars(out);
+                value.vars(out);
+            }
+            Self::Function(parameters, output) => {
+                parameters.iter().for_each(|t| t.vars(out));
+                output.vars(out);
+            }
+
+            _ => (),
+        }
+    }
+
+    /// The type with the variables replaced by the types paired with them.
+    pub fn replace(&self, with: &[(TyVar, Ty)]) -> Ty {
+        match self {
+            Self::Var(v) => with
+                .iter()
+                .find(|(var, _)| var == v)
+                .map_or_else(|| self.clone(), |(_, ty)| ty.clone()),
+
+            Self::Tuple(items) => Self::Tuple(items.iter().map(|t| t.replace(with)).collect()),
+            Self::Array(element) => Self::array(element.replace(with)),
+            Self::Map(key, value) => {
+                Self::Map(Box::new(key.replace(with)), Box::new(value.replace(with)))
+            }
+            Self::Function(parameters, output) => Self::function(
+                parameters.iter().map(|t| t.replace(with)),
+                output.replace(with),
+            ),
+
+            _ => self.clone(),
+        }
+    }
+}
+
+impl Display for Ty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Var(v) => write!(f, "t{}", v.0),
+
+            Self::Integer => write!(f, "integer"),
+            Self::Real => write!(f, "real"),
+            Self::String => write!(f, "string"),
+            Self::Char => write!(f, "char"),
+            Self::Bool => write!(f, "bool"),
+            Self::Quantity(dimension) => write!(f, "{dimension}"),
+
+            Self::Tuple(items) => {
+                write!(f, "(")?;
+                write_separated(f, items, ", ")?;
+
+                if items.len() == 1 {
+                    write!(f, ",")?;
+                }
+
+                write!(f, ")")
+            }
+
+            Self::Array(element) => write!(f, "[{element}]"),
+            Self::Map(key, value) => write!(f, "{{{key}: {value}}}"),
+
+            Self::Function(parameters, output) => {
+                write!(f, "(")?;
+                write_separated(f, parameters, ", ")?;
+                write!(f, ") => {output}")
+            }
+
+            Self::Struct(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+/// The types a type variable can be, for the operators defined on several
+/// types. A set of the kinds of types, the other kinds are never in it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Constraint(u8);
+
+impl Constraint {
+    const INTEGER: u8 = 1;
+    const REAL: u8 = 1 << 1;
+    const STRING: u8 = 1 << 2;
+    const CHAR: u8 = 1 << 3;
+    const BOOL: u8 = 1 << 4;
+    const ARRAY: u8 = 1 << 5;
+    /// Tuples, maps, functions and structs.
+    const OTHER: u8 = 1 << 6;
+    const QUANTITY: u8 = 1 << 7;
+
+    /// Any type.
+    pub const ANY: Self = Self(u8::MAX);
+    /// For the arithmetic operators.
+    pub const NUMERIC: Self = Self(Self::INTEGER | Self::REAL);
+    /// For `-` and `%`, which also apply to quantities.
+    pub const SIGNED: Self = Self(Self::INTEGER | Self::REAL | Self::QUANTITY);
+    /// For `+`, which also joins strings and arrays.
+    pub const ADDABLE: Self =
+        Self(Self::INTEGER | Self::REAL | Self::STRING | Self::ARRAY | Self::QUANTITY);
+    /// For `<` and the other ordering operators.
+    pub const ORDERED: Self =
+        Self(Self::INTEGER | Self::REAL | Self::STRING | Self::CHAR | Self::BOOL | Self::QUANTITY);
+    /// For `&`, `|` and `^`.
+    pub const BITWISE: Self = Self(Self::INTEGER | Self::BOOL);
+
+    fn kind(ty: &Ty) -> u8 {
+        match ty {
+            Ty::Integer => Self::INTEGER,
+            Ty::Real => Self::REAL,
+            Ty::String => Self::STRING,
+            Ty::Char => Self::CHAR,
+            Ty::Bool => Self::BOOL,
+            Ty::Array(_) => Self::ARRAY,
+            Ty::Quantity(_) => Self::QUANTITY,
+            Ty::Tuple(_) | Ty::Map(..) | Ty::Function(..) | Ty::Struct(_) => Self::OTHER,
+
+            Ty::Var(_) => u8::MAX,
+        }
+    }
+
+    /// Whether a type that isn't a variable satisfies the constraint.
+    pub fn allows(&self, ty: &Ty) -> bool {
+        self.0 & Self::kind(ty) != 0
+    }
+
+    /// The types satisfying both constraints, `None` if there are none.
+    pub fn and(&self, other: Constraint) -> Option<Constraint> {
+        let both = self.0 & other.0;
+        (both != 0).then_some(Self(both))
+    }
+}
+
+impl Display for Constraint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names = [
+            (Self::INTEGER, "integer"),
+            (Self::REAL, "real"),
+            (Self::STRING, "string"),
+            (Self::CHAR, "char"),
+            (Self::BOOL, "bool"),
+            (Self::ARRAY, "array"),
+            (Self::OTHER, "other type"),
+            (Self::QUANTITY, "quantity"),
+        ];
+
+        let mut first = true;
+        for (bit, name) in names {
+            if self.0 & bit != 0 {
+                if !first {
+                    write!(f, " or ")?;
+                }
+
+                write!(f, "{name}")?;
+                first = false;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A type with variables that can be replaced by any type satisfying their
+/// constraints, for the `let` bound lambdas used at several types.
+#[derive(Debug, Clone)]
+pub struct Scheme {
+    pub(crate) vars: Vec<(TyVar, Constraint)>,
+    pub(crate) ty: Ty,
+}
+
+impl Scheme {
+    /// A type that isn't generalized.
+    pub fn mono(ty: Ty) -> Self {
+        Self { vars: vec![], ty }
+    }
+
+    pub fn ty(&self) -> &Ty {
+        &self.ty
+    }
+}
@@ -0,0 +1,258 @@
+//! The units of the quantity literals like `500ms` and `16KiB`.
+//!
+//! A quantity is kept in the base unit of its dimension, so `500ms + 2s` is
+//! `2.5s`, and only quantities of the same dimension can be added. Dividing
+//! a quantity by one of the unit converts it to a number, `90deg / 1rad`.
+
+use std::{collections::HashMap, fmt::Display, sync::OnceLock};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Dimension {
+    Time,
+    Information,
+    Angle,
+}
+
+impl Dimension {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Time => "time",
+            Self::Information => "information",
+            Self::Angle => "angle",
+        }
+    }
+
+    /// The unit with a factor of one, the quantities are printed in it.
+    pub fn base_unit(&self) -> &'static str {
+        match self {
+            Self::Time => "s",
+            Self::Information => "B",
+            Self::Angle => "rad",
+        }
+    }
+}
+
+impl Display for Dimension {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Unit {
+    pub(crate) dimension: Dimension,
+    /// How many of the dimension's base unit one of this unit is.
+    pub(crate) factor: f64,
+}
+
+impl Unit {
+    pub fn dimension(&self) -> Dimension {
+        self.dimension
+    }
+
+    pub fn factor(&self) -> f64 {
+        self.factor
+    }
+}
+
+/// The units by name, see [`UnitRegistry::standard`] for the known ones.
+#[derive(Debug, Clone, Default)]
+pub struct UnitRegistry {
+    units: HashMap<String, Unit>,
+}
+
+impl UnitRegistry {
+    /// A registry without units.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The time, information and angle units of [`Dialect::standard`], and
+    /// of the passes made without a registry.
+    ///
+    /// [`Dialect::standard`]: crate::dialect::Dialect::standard
+    pub fn standard() -> &'static UnitRegistry {
+        static STANDARD: OnceLock<UnitRegistry> = OnceLock::new();
+
+        STANDARD.get_or_init(|| {
+            use std::f64::consts::PI;
+
+            let mut units = Self::new();
+            units
+                .add("ns", Dimension::Time, 1e-9)
+                .add("us", Dimension::Time, 1e-6)
+                .add("ms", Dimension::Time, 1e-3)
+                .add("s", Dimension::Time, 1.)
+                .add("min", Dimension::Time, 60.)
+                .add("h", Dimension::Time, 3600.)
+                .add("d", Dimension::Time, 86400.);
+
+            units
+                .add("bit", Dimension::Information, 0.125)
+                .add("B", Dimension::Information, 1.)
+                .add("KB", Dimension::Information, 1e3)
+                .add("MB", Dimension::Information, 1e6)
+                .add("GB", Dimension::Information, 1e9)
+                .add("TB", Dimension::Information, 1e12)
+                .add("KiB", Dimension::Information, 1024.)
+                .add("MiB", Dimension::Information, 1024f64.powi(2))
+                .add("GiB", Dimension::Information, 1024f64.powi(3))
+                .add("TiB", Dimension::Information, 1024f64.powi(4));
+
+            units
+                .add("rad", Dimension::Angle, 1.)
+                .add("deg", Dimension::Angle, PI / 180.)
+                .add("turn", Dimension::Angle, 2. * PI);
+
+            units
+        })
+    }
+
+    /// Adds a unit, replacing the one with the same name.
+    pub fn add(&mut self, name: impl Into<String>, dimension: Dimension, factor: f64) -> &mut Self {
+        self.units.insert(name.into(), Unit { dimension, factor });
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<Unit> {
+        self.units.get(name).copied()
+    }
+
+    /// The value in the dimension's base unit of an amount of the unit.
+    pub fn to_base(&self, amount: f64, unit: &str) -> Option<(f64, Dimension)> {
+        let unit = self.get(unit)?;
+        Some((amount * unit.factor, unit.dimension))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dialect::Dialect,
+        eval::{self, Environment, RuntimeErrorKind, Value},
+        fold::Folder,
+        parser::{statement::Statement, Parser},
+        tokenizer::Tokenizer,
+        typeck::{Checker, Ty},
+        vm::{Compiler, Vm},
+        Diagnostic, DiagnosticType, Source,
+    };
+
+    fn with_weeks() -> Dialect {
+        let mut dialect = Dialect::standard();
+        dialect.units.add("week", Dimension::Time, 7. * 86400.);
+        dialect
+    }
+
+    /// The statements with the diagnostics of the tokenizer and the parser.
+    fn parse<'s>(text: &'s str, dialect: &Dialect) -> (Vec<Statement<'s>>, Vec<Diagnostic<'s>>) {
+        let (mut diagnostics, mut parser_diagnostics) = (vec![], vec![]);
+
+        let tokenizer =
+            Tokenizer::with_dialect(Source::new("test", text), &mut diagnostics, dialect);
+        let statements = Parser::with_dialect(tokenizer, &mut parser_diagnostics, dialect)
+            .program()
+            .unwrap();
+
+        diagnostics.extend(parser_diagnostics);
+        (statements, diagnostics)
+    }
+
+    fn evaluate<'s>(
+        statements: &[Statement<'s>],
+        dialect: &Dialect,
+    ) -> Result<Value<'s>, RuntimeErrorKind> {
+        let env = Environment::with_units(&dialect.units);
+        eval::run(statements, &env).map_err(|e| e.kind().clone())
+    }
+
+    fn check<'s>(text: &'s str, dialect: &Dialect) -> (Ty, Vec<Diagnostic<'s>>) {
+        let (statements, _) = parse(text, dialect);
+        let mut diagnostics = vec![];
+
+        let mut checker =
+            Checker::with_units(Source::new("test", text), &mut diagnostics, &dialect.units);
+        let ty = checker.check(&statements);
+        let ty = checker.resolve(&ty);
+
+        (ty, diagnostics)
+    }
+
+    #[test]
+    fn custom_units() {
+        let dialect = with_weeks();
+        let six_days = Value::Quantity {
+            value: 6. * 86400.,
+            dimension: Dimension::Time,
+        };
+
+        let (statements, diagnostics) = parse("1week - 1d", &dialect);
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        assert_eq!(evaluate(&statements, &dialect), Ok(six_days.clone()));
+
+        let chunk = Compiler::with_units(&dialect.units)
+            .compile_program(&statements)
+            .unwrap();
+        let env = Environment::with_units(&dialect.units);
+        assert_eq!(
+            Vm::new().run(&chunk, &env).map_err(|e| e.kind().clone()),
+            Ok(six_days)
+        );
+
+        let (ty, diagnostics) = check("1week - 1d", &dialect);
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        assert_eq!(ty, Ty::Quantity(Dimension::Time));
+
+        let (mut statements, _) = parse("1week - 1d;", &dialect);
+        Folder::with_units(Source::new("test", ""), &mut vec![], &dialect.units)
+            .fold(&mut statements);
+        assert_eq!(statements[0].to_string(), "518400.0s;");
+
+        // `2d` is within a week
+        let (_, diagnostics) = parse("match x { 0s..1week => 1, 2d => 2, _ => 3 }", &dialect);
+        assert!(matches!(
+            diagnostics.as_slice(),
+            [d] if matches!(d.ty(), DiagnosticType::UnreachableMatchArm)
+        ));
+    }
+
+    #[test]
+    fn unknown_units() {
+        let dialect = Dialect::standard();
+
+        let (statements, diagnostics) = parse("1week", &dialect);
+        assert!(matches!(
+            diagnostics.as_slice(),
+            [d] if matches!(d.ty(), DiagnosticType::UnknownUnit)
+        ));
+        assert_eq!(
+            evaluate(&statements, &dialect),
+            Err(RuntimeErrorKind::UnknownUnit("week".to_string()))
+        );
+
+        // reported by the tokenizer only
+        let (_, diagnostics) = check("1week", &dialect);
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    }
+
+    #[test]
+    fn mixed_dimensions() {
+        let dialect = with_weeks();
+
+        let (statements, _) = parse("1week + 1B", &dialect);
+        assert_eq!(
+            evaluate(&statements, &dialect),
+            Err(RuntimeErrorKind::IncompatibleUnits {
+                left: "time",
+                right: "information",
+            })
+        );
+
+        let (_, diagnostics) = check("1week + 1B", &dialect);
+        assert!(matches!(
+            diagnostics.as_slice(),
+            [d] if matches!(d.ty(), DiagnosticType::TypeMismatch { .. })
+        ));
+    }
+}
@@ -0,0 +1,173 @@
+use std::fmt::Display;
+
+use crate::{
+    eval::Value,
+    tokenizer::{Operator, Span},
+};
+
+/// A stack machine instruction, the operands are popped and the results pushed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Instruction {
+    /// Pushes the constant with the index.
+    Constant(u32),
+    /// Pushes the empty tuple.
+    Unit,
+    Pop,
+
+    GetLocal(u32),
+    SetLocal(u32),
+    /// Pushes the variable of the environment named by the name with the index.
+    GetGlobal(u32),
+    SetGlobal(u32),
+
+    Prefix(Operator),
+    Infix(Operator),
+    Postfix(Operator),
+    /// Pops the index, then the value indexed with it.
+    Index,
+
+    /// Collects the number of values into a tuple.
+    Tuple(u32),
+    Array(u32),
+    /// Pops the count, then the value repeated to make an array.
+    Repeat,
+
+    /// Jumps to the instruction with the index.
+    Jump(u32),
+    /// Pops a bool and jumps if it's false.
+    JumpIfFalse(u32),
+    /// Jumps if the bool on the top is false, and pops it otherwise, for `&&`.
+    JumpIfFalseOrPop(u32),
+    /// Jumps if the bool on the top is true, and pops it otherwise, for `||`.
+    JumpIfTrueOrPop(u32),
+    /// Fails if the value on the top isn't a bool.
+    ExpectBool,
+
+    /// Pops the function, then the number of arguments.
+    Call(u32),
+    /// Ends the chunk with the value on the top.
+    Return,
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Constant(i) => write!(f, "constant {i}"),
+            Self::Unit => write!(f, "unit"),
+            Self::Pop => write!(f, "pop"),
+            Self::GetLocal(i) => write!(f, "get_local {i}"),
+            Self::SetLocal(i) => write!(f, "set_local {i}"),
+            Self::GetGlobal(i) => write!(f, "get_global {i}"),
+            Self::SetGlobal(i) => write!(f, "set_global {i}"),
+            Self::Prefix(op) => write!(f, "prefix {op}"),
+            Self::Infix(op) => write!(f, "infix {op}"),
+            Self::Postfix(op) => write!(f, "postfix {op}"),
+            Self::Index => write!(f, "index"),
+            Self::Tuple(n) => write!(f, "tuple {n}"),
+            Self::Array(n) => write!(f, "array {n}"),
+            Self::Repeat => write!(f, "repeat"),
+            Self::Jump(to) => write!(f, "jump {to:04}"),
+            Self::JumpIfFalse(to) => write!(f, "jump_if_false {to:04}"),
+            Self::JumpIfFalseOrPop(to) => write!(f, "jump_if_false_or_pop {to:04}"),
+            Self::JumpIfTrueOrPop(to) => write!(f, "jump_if_true_or_pop {to:04}"),
+            Self::ExpectBool => write!(f, "expect_bool"),
+            Self::Call(n) => write!(f, "call {n}"),
+            Self::Return => write!(f, "return"),
+        }
+    }
+}
+
+/// Compiled code with the constants and names it refers to by index.
+#[derive(Debug, Clone, Default)]
+pub struct Chunk<'s> {
+    pub(crate) code: Vec<Instruction>,
+    /// The span of the expression each instruction was compiled from.
+    pub(crate) spans: Vec<Span>,
+    pub(crate) constants: Vec<Value<'s>>,
+    pub(crate) names: Vec<String>,
+    /// The number of local variable slots.
+    pub(crate) locals: usize,
+}
+
+impl<'s> Chunk<'s> {
+    pub fn code(&self) -> &[Instruction] {
+        &self.code
+    }
+
+    pub fn constants(&self) -> &[Value<'s>] {
+        &self.constants
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn locals(&self) -> usize {
+        self.locals
+    }
+
+    /// The span of the expression the instruction was compiled from.
+    pub fn span(&self, index: usize) -> Span {
+        self.spans[index]
+    }
+
+    /// Appends an instruction, returning its index.
+    pub(crate) fn push(&mut self, instruction: Instruction, span: Span) -> usize {
+        self.code.push(instruction);
+        self.spans.push(span);
+
+        self.code.len() - 1
+    }
+
+    /// The index of the constant, adding it if it's not in the pool yet.
+    pub(crate) fn constant(&mut self, value: Value<'s>) -> u32 {
+        // `1` and `1.0` are equal but not the same constant
+        let existing = self
+            .constants
+            .iter()
+            .position(|c| c.type_name() == value.type_name() && *c == value);
+
+        let index = existing.unwrap_or_else(|| {
+            self.constants.push(value);
+            self.constants.len() - 1
+        });
+
+        index as u32
+    }
+
+    /// The index of the name, adding it if it's not in the pool yet.
+    pub(crate) fn name(&mut self, name: String) -> u32 {
+        let index = match self.names.iter().position(|n| *n == name) {
+            Some(i) => i,
+            None => {
+                self.names.push(name);
+                self.names.len() - 1
+            }
+        };
+
+        index as u32
+    }
+}
+
+/// The disassembly, one instruction per line with the constants and names
+/// it refers to.
+impl Display for Chunk<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, instruction) in self.code.iter().enumerate() {
+            write!(f, "{i:04}  {instruction}")?;
+
+            match instruction {
+                Instruction::Constant(c) => write!(f, "  ; {}", self.constants[*c as usize])?,
+                Instruction::GetGlobal(n) | Instruction::SetGlobal(n) => {
+                    write!(f, "  ; {}", self.names[*n as usize])?
+                }
+
+                _ => (),
+            }
+
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
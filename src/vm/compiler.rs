@@ -0,0 +1,423 @@
+use crate::{
+    eval,
+    parser::{
+        expression::{Expression, Path},
+        pattern::Pattern,
+        statement::{Block, Statement},
+    },
+    tokenizer::{Operator, Span},
+    units::UnitRegistry,
+};
+
+use super::chunk::{Chunk, Instruction as I};
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum CompileError {
+    #[error("{construct} can't be compiled, at {span}")]
+    Unsupported { construct: &'static str, span: Span },
+
+    #[error("Invalid literal at {span}: {kind}")]
+    InvalidLiteral {
+        kind: eval::RuntimeErrorKind,
+        span: Span,
+    },
+}
+
+/// Compiles statements to a [`Chunk`] with the same results as [`eval::run`].
+///
+/// Variables defined with `let` live in the chunk's local slots, the other
+/// names are looked up in the environment the chunk runs in. See the
+/// [module](super) for what isn't compiled.
+#[derive(Debug)]
+pub struct Compiler<'s> {
+    chunk: Chunk<'s>,
+    /// The local variables in scope, later ones shadow earlier ones.
+    locals: Vec<(&'s str, u32)>,
+    /// The units of the quantity literals, which are compiled to constants.
+    units: UnitRegistry,
+}
+
+impl Default for Compiler<'_> {
+    fn default() -> Self {
+        Self::with_units(UnitRegistry::standard())
+    }
+}
+
+impl<'s> Compiler<'s> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compiles the quantity literals in the given units, which should be
+    /// the ones of the environment the chunk runs in.
+    pub fn with_units(units: &UnitRegistry) -> Self {
+        Self {
+            chunk: Chunk::default(),
+            locals: vec![],
+            units: units.clone(),
+        }
+    }
+
+    pub fn compile_program(
+        mut self,
+        statements: &[Statement<'s>],
+    ) -> Result<Chunk<'s>, CompileError> {
+        self.statements(statements, Span::default())?;
+        self.emit(I::Return, Span::default());
+
+        Ok(self.chunk)
+    }
+
+    pub fn compile_expression(mut self, expr: &Expression<'s>) -> Result<Chunk<'s>, CompileError> {
+        self.expression(expr)?;
+        self.emit(I::Return, expr.span());
+
+        Ok(self.chunk)
+    }
+
+    fn emit(&mut self, instruction: I, span: Span) -> usize {
+        self.chunk.push(instruction, span)
+    }
+
+    /// The index of the next instruction, where a jump emitted now lands.
+    fn here(&self) -> u32 {
+        self.chunk.code.len() as u32
+    }
+
+    /// Makes the jump at `jump` land on the next instruction.
+    fn patch(&mut self, jump: usize) {
+        let target = self.here();
+
+        match &mut self.chunk.code[jump] {
+            I::Jump(to) | I::JumpIfFalse(to) | I::JumpIfFalseOrPop(to) | I::JumpIfTrueOrPop(to) => {
+                *to = target
+            }
+
+            i => unreachable!("{i} is not a jump"),
+        }
+    }
+
+    fn define_local(&mut self, name: &'s str) -> u32 {
+        let slot = self.chunk.locals as u32;
+        self.chunk.locals += 1;
+        self.locals.push((name, slot));
+
+        slot
+    }
+
+    fn local(&self, path: &Path<'s>) -> Option<u32> {
+        let name = path.as_identifier()?;
+
+        self.locals
+            .iter()
+            .rev()
+            .find(|(n, _)| *n == name)
+            .map(|(_, slot)| *slot)
+    }
+
+    fn get_variable(&mut self, path: &Path<'s>, span: Span) {
+        match self.local(path) {
+            Some(slot) => self.emit(I::GetLocal(slot), span),
+            None => {
+                let name = self.chunk.name(path.to_string());
+                self.emit(I::GetGlobal(name), span)
+            }
+        };
+    }
+
+    fn set_variable(&mut self, path: &Path<'s>, span: Span) {
+        match self.local(path) {
+            Some(slot) => self.emit(I::SetLocal(slot), span),
+            None => {
+                let name = self.chunk.name(path.to_string());
+                self.emit(I::SetGlobal(name), span)
+            }
+        };
+    }
+
+    /// Leaves the value of the last statement on the stack, like [`eval::run`].
+    fn statements(&mut self, statements: &[Statement<'s>], span: Span) -> Result<(), CompileError> {
+        if statements.is_empty() {
+            self.emit(I::Unit, span);
+        }
+
+        for (i, s) in statements.iter().enumerate() {
+            self.statement(s, i == statements.len() - 1)?;
+        }
+
+        Ok(())
+    }
+
+    /// Leaves the value of the statement on the stack if `keep` is set.
+    fn statement(&mut self, statement: &Statement<'s>, keep: bool) -> Result<(), CompileError> {
+        match statement.inner() {
+            Statement::Let { pattern, value, .. } => {
+                let span = value.as_ref().map(|v| v.span()).unwrap_or_default();
+
+                match value {
+                    Some(value) => self.expression(value)?,
+                    None => {
+                        self.emit(I::Unit, span);
+                    }
+                }
+
+                match pattern {
+                    Pattern::Binding(name) => {
+                        let slot = self.define_local(name);
+                        self.emit(I::SetLocal(slot), span);
+                    }
+
+                    Pattern::Wildcard => {
+                        self.emit(I::Pop, span);
+                    }
+
+                    _ => {
+                        return Err(CompileError::Unsupported {
+                            construct: "a destructuring let",
+                            span,
+                        })
+                    }
+                }
+
+                if keep {
+                    self.emit(I::Unit, span);
+                }
+            }
+
+            Statement::Expression { expr, semicolon } => {
+                self.expression(expr)?;
+
+                if *semicolon || !keep {
+                    self.emit(I::Pop, expr.span());
+                }
+
+                if *semicolon && keep {
+                    self.emit(I::Unit, expr.span());
+                }
+            }
+
+            Statement::Macro(_) | Statement::Fixity { .. } => {
+                if keep {
+                    self.emit(I::Unit, Span::default());
+                }
+            }
+
+            Statement::Attributed { .. } | Statement::Documented { .. } => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    fn block(&mut self, block: &Block<'s>) -> Result<(), CompileError> {
+        let outer = self.locals.len();
+        self.statements(block.statements(), block.span())?;
+        self.locals.truncate(outer);
+
+        Ok(())
+    }
+
+    fn expression(&mut self, expr: &Expression<'s>) -> Result<(), CompileError> {
+        let span = expr.span();
+        let unsupported = |construct| Err(CompileError::Unsupported { construct, span });
+
+        match expr {
+            Expression::Lit { value, .. } => {
+                let value = eval::literal(value, &self.units)
+                    .map_err(|kind| CompileError::InvalidLiteral { kind, span })?;
+                let constant = self.chunk.constant(value);
+                self.emit(I::Constant(constant), span);
+            }
+
+            Expression::Name(path) => self.get_variable(path, span),
+
+            Expression::Prefix { op, right, .. } => {
+                // a negated literal is a constant, `-9223372036854775808`
+                // included
+                if let (Operator::Minus, Expression::Lit { value, .. }) = (op, right.as_ref()) {
+                    let value = eval::negated_literal(value, &self.units)
+                        .map_err(|kind| CompileError::InvalidLiteral { kind, span })?;
+                    let constant = self.chunk.constant(value);
+                    self.emit(I::Constant(constant), span);
+
+                    return Ok(());
+                }
+
+                self.expression(right)?;
+                self.emit(I::Prefix(*op), span);
+            }
+
+            Expression::Postfix { left, op, .. } => {
+                self.expression(left)?;
+                self.emit(I::Postfix(*op), span);
+            }
+
+            Expression::Infix {
+                left, op, right, ..
+            } => match op {
+                Operator::DoubleAnd | Operator::DoubleOr => {
+                    self.expression(left)?;
+
+                    let jump = if *op == Operator::DoubleAnd {
+                        self.emit(I::JumpIfFalseOrPop(0), span)
+                    } else {
+                        self.emit(I::JumpIfTrueOrPop(0), span)
+                    };
+
+                    self.expression(right)?;
+                    self.emit(I::ExpectBool, span);
+                    self.patch(jump);
+                }
+
+                Operator::Pipeline => {
+                    self.expression(left)?;
+
+                    // `x |> f(y)` is `f(x, y)`
+                    if let Expression::Call {
+                        function,
+                        arguments,
+                        ..
+                    } = right.as_ref()
+                    {
+                        for a in arguments {
+                            self.expression(a)?;
+                        }
+
+                        self.get_variable(function, function.span());
+                        self.emit(I::Call(arguments.len() as u32 + 1), span);
+                    } else {
+                        self.expression(right)?;
+                        self.emit(I::Call(1), span);
+                    }
+                }
+
+                op if op.is_assignment() => {
+                    let Expression::Name(path) = left.as_ref() else {
+                        return unsupported("an assignment to something other than a variable");
+                    };
+
+                    if *op != Operator::Equals {
+                        self.get_variable(path, path.span());
+                    }
+
+                    self.expression(right)?;
+
+                    if *op != Operator::Equals {
+                        self.emit(I::Infix(eval::compound_operator(*op)), span);
+                    }
+
+                    self.set_variable(path, path.span());
+                    self.emit(I::Unit, span);
+                }
+
+                op => {
+                    self.expression(left)?;
+                    self.expression(right)?;
+                    self.emit(I::Infix(*op), span);
+                }
+            },
+
+            Expression::CustomInfix {
+                left, op, right, ..
+            } => {
+                self.expression(left)?;
+                self.expression(right)?;
+
+                let name = self.chunk.name(op.clone());
+                self.emit(I::GetGlobal(name), span);
+                self.emit(I::Call(2), span);
+            }
+
+            Expression::Call {
+                function,
+                arguments,
+                ..
+            } => {
+                for a in arguments {
+                    self.expression(a)?;
+                }
+
+                self.get_variable(function, function.span());
+                self.emit(I::Call(arguments.len() as u32), span);
+            }
+
+            Expression::Index { expr, with, .. } => {
+                self.expression(expr)?;
+                self.expression(with)?;
+                self.emit(I::Index, span);
+            }
+
+            Expression::Tuple { items, .. } => {
+                for i in items {
+                    self.expression(i)?;
+                }
+
+                self.emit(I::Tuple(items.len() as u32), span);
+            }
+
+            Expression::Array { items, .. } => {
+                for i in items {
+                    self.expression(i)?;
+                }
+
+                self.emit(I::Array(items.len() as u32), span);
+            }
+
+            Expression::Repeat { value, count, .. } => {
+                self.expression(value)?;
+                self.expression(count)?;
+                self.emit(I::Repeat, count.span());
+            }
+
+            Expression::Block(block) => self.block(block)?,
+
+            Expression::If {
+                condition,
+                then,
+                otherwise,
+                ..
+            } => {
+                self.expression(condition)?;
+                let to_otherwise = self.emit(I::JumpIfFalse(0), condition.span());
+
+                self.block(then)?;
+                let to_end = self.emit(I::Jump(0), span);
+
+                self.patch(to_otherwise);
+                match otherwise {
+                    Some(otherwise) => self.expression(otherwise)?,
+                    None => {
+                        self.emit(I::Unit, span);
+                    }
+                }
+
+                self.patch(to_end);
+            }
+
+            Expression::While {
+                condition, body, ..
+            } => {
+                let start = self.here();
+
+                self.expression(condition)?;
+                let to_end = self.emit(I::JumpIfFalse(0), condition.span());
+
+                self.block(body)?;
+                self.emit(I::Pop, span);
+                self.emit(I::Jump(start), span);
+
+                self.patch(to_end);
+                self.emit(I::Unit, span);
+            }
+
+            Expression::Attributed { expr, .. } => self.expression(expr)?,
+
+            Expression::Lambda { .. } => return unsupported("a lambda"),
+            Expression::Match { .. } => return unsupported("a match"),
+            Expression::Map { .. } => return unsupported("a map literal"),
+            Expression::Struct { .. } => return unsupported("a struct literal"),
+            Expression::MacroCall { .. } => return unsupported("a macro call"),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,263 @@
+//! A bytecode compiler and a stack machine running its [`Chunk`]s.
+//!
+//! The operators have the semantics of [`eval`], so a compiled program has
+//! the same results and errors as evaluating its syntax tree directly.
+//!
+//! The compiler covers the hot formulas and loops: literals, names, `let`
+//! of a name or `_`, the operators, calls of the environment's functions,
+//! indexing, tuples, arrays and `[value; count]`, blocks, `if` and `while`.
+//! Lambdas, `match`, map and struct literals, destructuring `let`s and
+//! assignments to anything but a variable are a
+//! [`CompileError::Unsupported`], such programs are left to the tree walking
+//! [`eval::run`].
+//!
+//! `cargo bench` compares the two on a few programs, see `benches/vm.rs`.
+
+pub mod chunk;
+pub mod compiler;
+
+use crate::eval::{self, Environment, RuntimeError, RuntimeErrorKind, Value};
+
+pub use self::{
+    chunk::{Chunk, Instruction},
+    compiler::{CompileError, Compiler},
+};
+
+/// Runs chunks, keeping its stack between the runs to reuse its memory.
+#[derive(Debug, Default)]
+pub struct Vm<'s> {
+    stack: Vec<Value<'s>>,
+    locals: Vec<Value<'s>>,
+}
+
+impl<'s> Vm<'s> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn pop(&mut self) -> Value<'s> {
+        self.stack.pop().expect("the compiler balances the stack")
+    }
+
+    fn peek(&self) -> &Value<'s> {
+        self.stack.last().expect("the compiler balances the stack")
+    }
+
+    /// Runs the chunk with the names that aren't local variables looked up
+    /// in `env`.
+    pub fn run(
+        &mut self,
+        chunk: &Chunk<'s>,
+        env: &Environment<'s>,
+    ) -> Result<Value<'s>, RuntimeError> {
+        self.stack.clear();
+        self.locals.clear();
+        self.locals.resize(chunk.locals, Value::unit());
+
+        let mut ip = 0;
+        loop {
+            let instruction = chunk.code[ip];
+            let span = chunk.spans[ip];
+            let error = |kind| RuntimeError::new(kind, span);
+            ip += 1;
+
+            match instruction {
+                Instruction::Constant(c) => self.stack.push(chunk.constants[c as usize].clone()),
+                Instruction::Unit => self.stack.push(Value::unit()),
+                Instruction::Pop => {
+                    self.pop();
+                }
+
+                Instruction::GetLocal(slot) => self.stack.push(self.locals[slot as usize].clone()),
+                Instruction::SetLocal(slot) => self.locals[slot as usize] = self.pop(),
+
+                Instruction::GetGlobal(name) => {
+                    let name = &chunk.names[name as usize];
+                    match env.get(name) {
+                        Some(value) => self.stack.push(value),
+                        None => return Err(error(RuntimeErrorKind::UnknownName(name.clone()))),
+                    }
+                }
+
+                Instruction::SetGlobal(name) => {
+                    let name = &chunk.names[name as usize];
+                    let value = self.pop();
+
+                    if !env.assign(name, value) {
+                        return Err(error(RuntimeErrorKind::UnknownName(name.clone())));
+                    }
+                }
+
+                Instruction::Prefix(op) => {
+                    let right = self.pop();
+                    self.stack.push(eval::prefix(op, right).map_err(error)?);
+                }
+
+                Instruction::Infix(op) => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    self.stack
+                        .push(eval::infix(left, op, right).map_err(error)?);
+                }
+
+                Instruction::Postfix(op) => {
+                    let left = self.pop();
+                    self.stack.push(eval::postfix(op, left).map_err(error)?);
+                }
+
+                Instruction::Index => {
+                    let index = self.pop();
+                    let value = self.pop();
+                    self.stack.push(eval::index(value, index).map_err(error)?);
+                }
+
+                Instruction::Tuple(n) => {
+                    let items = self.stack.split_off(self.stack.len() - n as usize);
+                    self.stack.push(Value::Tuple(items));
+                }
+
+                Instruction::Array(n) => {
+                    let items = self.stack.split_off(self.stack.len() - n as usize);
+                    self.stack.push(Value::Array(items));
+                }
+
+                Instruction::Repeat => {
+                    let count = self.pop();
+                    let value = self.pop();
+                    self.stack.push(eval::repeat(value, count).map_err(error)?);
+                }
+
+                Instruction::Jump(to) => ip = to as usize,
+
+                Instruction::JumpIfFalse(to) => match self.pop() {
+                    Value::Bool(true) => (),
+                    Value::Bool(false) => ip = to as usize,
+                    v => return Err(error(RuntimeErrorKind::type_mismatch("a bool", &v))),
+                },
+
+                Instruction::JumpIfFalseOrPop(to) | Instruction::JumpIfTrueOrPop(to) => {
+                    let jump_if = matches!(instruction, Instruction::JumpIfTrueOrPop(_));
+
+                    match self.peek() {
+                        Value::Bool(b) if *b == jump_if => ip = to as usize,
+                        Value::Bool(_) => {
+                            self.pop();
+                        }
+                        v => return Err(error(RuntimeErrorKind::type_mismatch("a bool", v))),
+                    }
+                }
+
+                Instruction::ExpectBool => {
+                    if !matches!(self.peek(), Value::Bool(_)) {
+                        return Err(error(RuntimeErrorKind::type_mismatch(
+                            "a bool",
+                            self.peek(),
+                        )));
+                    }
+                }
+
+                Instruction::Call(n) => {
+                    let function = self.pop();
+                    let args = self.stack.split_off(self.stack.len() - n as usize);
+                    self.stack.push(eval::call(&function, args, span)?);
+                }
+
+                Instruction::Return => return Ok(self.pop()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parser::{statement::Statement, Parser},
+        tokenizer::{Span, Tokenizer},
+        Source,
+    };
+
+    fn parse(text: &str) -> Vec<Statement<'_>> {
+        let source = Source::new("test", text);
+        let (mut tokenizer_diagnostics, mut parser_diagnostics) = (vec![], vec![]);
+
+        let tokenizer = Tokenizer::new(source, &mut tokenizer_diagnostics);
+        Parser::new(tokenizer, &mut parser_diagnostics, Default::default())
+            .program()
+            .unwrap()
+    }
+
+    fn run<'s>(statements: &[Statement<'s>]) -> Result<Value<'s>, RuntimeError> {
+        let chunk = Compiler::new().compile_program(statements).unwrap();
+        Vm::new().run(&chunk, &Environment::with_prelude())
+    }
+
+    #[test]
+    fn same_results_as_eval() {
+        for text in [
+            "1 + 2 * 3 - 4 / 2",
+            "2.5 * 2 + 1",
+            "-9223372036854775808",
+            "\"a\" + 'b'",
+            "let x = 2; let y = x ** 10; y % 7",
+            "let x = 1; { let x = 2; x } + x",
+            "let x = 1; x += 2; x *= 3; x",
+            "let _ = 1;",
+            "false && 1 / 0 == 1 || true",
+            "2 |> max(3)",
+            "-4 |> abs",
+            "if 1 < 2 { \"yes\" } else { \"no\" }",
+            "if false { 1 }",
+            "let i = 0; let n = 1; while i < 10 { n *= 2; i += 1; } n",
+            "[1, 2, 3][1] + (4, 5)[0]",
+            "[(1, 'a'); 3]",
+            "len([0; 4]) + 5!",
+            "1 / 0",
+            "[1][5]",
+            "[1; -1]",
+            "1 + \"a\"",
+            "true && 1",
+            "9223372036854775807 + 1",
+            "unknown + 1",
+            "let x = 1; y = 2",
+        ] {
+            let statements = parse(text);
+
+            assert_eq!(
+                run(&statements),
+                eval::run(&statements, &Environment::with_prelude()),
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn unsupported_constructs() {
+        for (text, unsupported, span) in [
+            ("{1: 2}", "a map literal", Span::new(0, 6)),
+            ("P { x: 1 }", "a struct literal", Span::new(0, 10)),
+            ("1 + (x => x)", "a lambda", Span::new(4, 12)),
+            ("match 1 { _ => 2 }", "a match", Span::new(0, 18)),
+            (
+                "let (a, b) = (1, 2);",
+                "a destructuring let",
+                Span::new(13, 19),
+            ),
+            (
+                "let a = [1]; a[0] = 2",
+                "an assignment to something other than a variable",
+                Span::new(13, 21),
+            ),
+        ] {
+            match Compiler::new().compile_program(&parse(text)) {
+                Err(CompileError::Unsupported {
+                    construct,
+                    span: found,
+                }) => {
+                    assert_eq!((construct, found), (unsupported, span), "{text}")
+                }
+                found => panic!("{text} compiled to {found:?}"),
+            }
+        }
+    }
+}